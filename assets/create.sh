@@ -14,3 +14,6 @@ patch "$JAVADOC_FILE" "$JAVADOC_PATCH"
 prettyprint cache --init --blank --source="$ASSET_DIR" --target="$ASSET_DIR"
 
 patch -R "$JAVADOC_FILE" "$JAVADOC_PATCH"
+
+# Split the new assets by syntax and theme, for the printers which only load what they use
+cargo test --manifest-path="${ASSET_DIR}/../Cargo.toml" --release --lib save_split_assets -- --ignored
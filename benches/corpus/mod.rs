@@ -0,0 +1,144 @@
+//! Generated inputs for the benchmarks. They are the same on every run, so that timings of
+//! different commits can be compared.
+
+/// A deterministic pseudo-random number generator (a linear congruential generator).
+pub struct Random(u64);
+
+impl Random {
+    pub fn new() -> Self {
+        Random(0x2545_f491_4f6c_dd1d)
+    }
+
+    pub fn below(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        ((self.0 >> 33) as usize) % bound
+    }
+
+    pub fn pick<'a>(&mut self, words: &[&'a str]) -> &'a str {
+        words[self.below(words.len())]
+    }
+}
+
+const WORDS: &[&str] = &[
+    "buffer", "config", "cursor", "entry", "handle", "index", "line", "offset", "parser", "range",
+    "reader", "region", "state", "style", "theme", "token", "value", "width", "writer",
+];
+
+/// Rust code of about `lines` lines: functions with comments, strings, numbers and closures.
+pub fn rust(lines: usize) -> String {
+    let mut random = Random::new();
+    let mut code = String::from("use std::collections::HashMap;\n\n");
+    let mut count = 2;
+    while count < lines {
+        let name = random.pick(WORDS);
+        let other = random.pick(WORDS);
+        code.push_str(&format!(
+            "/// Computes the {name} of the {other}.\n\
+             pub fn {name}_{n}(input: &[u8], {other}: usize) -> Result<Vec<String>, String> {{\n\
+             \x20   let mut map: HashMap<usize, &str> = HashMap::new();\n\
+             \x20   for (i, byte) in input.iter().enumerate().filter(|(_, b)| **b > {n}) {{\n\
+             \x20       map.insert(i * {other} + 0x{n:x}, \"{name} {other}\"); // {other}\n\
+             \x20   }}\n\
+             \x20   Ok(map.values().map(|v| v.to_string()).collect())\n\
+             }}\n\n",
+            name = name,
+            other = other,
+            n = random.below(1000)
+        ));
+        count += 9;
+    }
+    code
+}
+
+/// JavaScript code of about `lines` lines: classes, template strings and arrow functions.
+pub fn javascript(lines: usize) -> String {
+    let mut random = Random::new();
+    let mut code = String::from("'use strict';\n\n");
+    let mut count = 2;
+    while count < lines {
+        let name = random.pick(WORDS);
+        let other = random.pick(WORDS);
+        code.push_str(&format!(
+            "class {name}{n} extends Base {{\n\
+             \x20 constructor({other}) {{\n\
+             \x20   super();\n\
+             \x20   this.{other} = {other} ?? {n}; // default\n\
+             \x20 }}\n\
+             \x20 async load() {{\n\
+             \x20   const items = await fetch(`/api/{name}/${{this.{other}}}`);\n\
+             \x20   return items.filter((item) => item.size > {n}).map((item) => item.id);\n\
+             \x20 }}\n\
+             }}\n\n",
+            name = capitalize(name),
+            other = other,
+            n = random.below(1000)
+        ));
+        count += 11;
+    }
+    code
+}
+
+/// A pretty-printed JSON array of `entries` objects.
+pub fn json(entries: usize) -> String {
+    let mut random = Random::new();
+    let objects: Vec<String> = (0..entries)
+        .map(|id| {
+            format!(
+                "  {{\n    \"id\": {},\n    \"name\": \"{} {}\",\n    \"score\": {}.{},\n    \"active\": {},\n    \"tags\": [\"{}\", \"{}\"]\n  }}",
+                id,
+                random.pick(WORDS),
+                random.pick(WORDS),
+                random.below(100),
+                random.below(100),
+                random.below(2) == 0,
+                random.pick(WORDS),
+                random.pick(WORDS)
+            )
+        })
+        .collect();
+    format!("[\n{}\n]\n", objects.join(",\n"))
+}
+
+/// The JSON of `json` on a single line, like minified files.
+pub fn minified_json(entries: usize) -> String {
+    let mut minified: String = json(entries)
+        .lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join("");
+    minified.push('\n');
+    minified
+}
+
+/// A log of `lines` lines with timestamps, levels and key-value pairs.
+pub fn log(lines: usize) -> String {
+    let mut random = Random::new();
+    let levels = ["DEBUG", "INFO", "INFO", "INFO", "WARN", "ERROR"];
+    (0..lines)
+        .map(|i| {
+            format!(
+                "2019-01-01 10:{:02}:{:02}.{:03} {} [{}] {} {}={} took {}ms\n",
+                (i / 60) % 60,
+                i % 60,
+                random.below(1000),
+                random.pick(&levels),
+                random.pick(WORDS),
+                random.pick(WORDS),
+                random.pick(WORDS),
+                random.below(10_000),
+                random.below(500)
+            )
+        })
+        .collect()
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
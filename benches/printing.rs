@@ -0,0 +1,110 @@
+//! Timings of common printing setups, on the inputs of the `corpus` module. Run them with
+//! `cargo bench`, or e.g. `cargo bench -- wrap` for the benchmarks whose names contain `wrap`.
+
+use std::env;
+use std::time::{Duration, Instant};
+
+use prettyprint::{LineRange, LineRanges, OutputWrap, PrettyPrinter};
+
+mod corpus;
+
+/// Minimum time spent measuring each benchmark.
+const MEASUREMENT_TIME: Duration = Duration::from_secs(2);
+
+struct Bench {
+    filters: Vec<String>,
+}
+
+impl Bench {
+    /// Renders `input` repeatedly and prints the mean time of a rendering.
+    fn run(&self, name: &str, options: &PrettyPrinter, language: &str, input: &str) {
+        if !self.filters.is_empty() && !self.filters.iter().any(|f| name.contains(f.as_str())) {
+            return;
+        }
+
+        let printer = options.clone().language(language).build();
+        let render = || {
+            printer
+                .render_string(input)
+                .expect("rendering failed")
+                .len()
+        };
+
+        // The first rendering loads the syntaxes and themes.
+        let bytes = render();
+        let start = Instant::now();
+        let mut iterations = 0;
+        while start.elapsed() < MEASUREMENT_TIME {
+            render();
+            iterations += 1;
+        }
+
+        let mean = start.elapsed() / iterations;
+        let throughput = input.len() as f64 / mean.as_secs_f64() / (1024.0 * 1024.0);
+        println!(
+            "{:<28} {:>10.3} ms/iter {:>8.2} MiB/s   ({} iterations, {} bytes out)",
+            name,
+            mean.as_secs_f64() * 1000.0,
+            throughput,
+            iterations,
+            bytes
+        );
+    }
+}
+
+fn printer() -> PrettyPrinter {
+    let mut printer = PrettyPrinter::default();
+    printer.colored_output(true).term_width(100usize);
+    printer
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`, the other arguments are filters.
+    let bench = Bench {
+        filters: env::args()
+            .skip(1)
+            .filter(|arg| !arg.starts_with("--"))
+            .collect(),
+    };
+
+    let rust = corpus::rust(5_000);
+    let javascript = corpus::javascript(5_000);
+    let json = corpus::json(1_000);
+    let minified = corpus::minified_json(1_000);
+    let log = corpus::log(5_000);
+
+    let default = printer();
+    bench.run("rust", &default, "rust", &rust);
+    bench.run("javascript", &default, "javascript", &javascript);
+    bench.run("json", &default, "json", &json);
+    bench.run("log", &default, "log", &log);
+
+    let mut plain = printer();
+    plain.header(false).grid(false).line_numbers(false);
+    bench.run("rust/plain", &plain, "rust", &rust);
+
+    let mut wrapped = printer();
+    wrapped
+        .term_width(40usize)
+        .output_wrap(OutputWrap::Character);
+    let mut unwrapped = printer();
+    unwrapped.term_width(40usize).output_wrap(OutputWrap::None);
+    bench.run("rust/wrap", &wrapped, "rust", &rust);
+    bench.run("rust/no-wrap", &unwrapped, "rust", &rust);
+
+    let mut true_color = printer();
+    true_color.true_color(true);
+    let mut ansi_256 = printer();
+    ansi_256.true_color(false);
+    bench.run("rust/truecolor", &true_color, "rust", &rust);
+    bench.run("rust/256-colors", &ansi_256, "rust", &rust);
+
+    bench.run("json/long-line", &default, "json", &minified);
+    bench.run("json/long-line/wrap", &wrapped, "json", &minified);
+
+    let mut range = printer();
+    range.line_ranges(LineRanges::from(
+        vec![LineRange::from("2000:2100").unwrap()],
+    ));
+    bench.run("rust/range", &range, "rust", &rust);
+}
@@ -0,0 +1,13 @@
+/// Fibonacci implementation in Rust
+/// ```
+/// assert_eq!(fib(0), 1);
+/// assert_eq!(fib(1), 1);
+/// assert_eq!(fib(9), 55);
+/// ```
+pub fn fib(n: usize) -> usize {
+    let (mut a, mut b) = (1, 1);
+    for _ in 1..n {
+        (a, b) = (b, a + b);
+    }
+    b
+}
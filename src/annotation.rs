@@ -0,0 +1,85 @@
+use std::ops::Range;
+
+use ansi_term::Colour::{Cyan, Green, Red, Yellow};
+use ansi_term::Style;
+use unicode_width::UnicodeWidthStr;
+
+use crate::preprocessor::expand_tabs;
+
+/// How severe the issue of an `Annotation` is, which decides its color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Level {
+    #[default]
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl Level {
+    pub(crate) fn style(self) -> Style {
+        match self {
+            Level::Error => Red.bold(),
+            Level::Warning => Yellow.bold(),
+            Level::Note => Cyan.bold(),
+            Level::Help => Green.bold(),
+        }
+    }
+}
+
+/// A message about a span of a line, printed beneath it like the diagnostics of a compiler:
+/// `^^^^ message`. See `.annotate()`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Annotation {
+    /// The line, as numbered in the output
+    pub line: usize,
+    /// The characters of the line to underline, counted from zero
+    pub col_range: Range<usize>,
+    pub message: String,
+    pub level: Level,
+}
+
+impl Annotation {
+    pub fn new<S: Into<String>>(
+        line: usize,
+        col_range: Range<usize>,
+        message: S,
+        level: Level,
+    ) -> Self {
+        Annotation {
+            line,
+            col_range,
+            message: message.into(),
+            level,
+        }
+    }
+
+    /// The position and the width of the carets under `line`, in columns. Tabs are expanded to
+    /// `tab_width` columns, unless it's zero. A span at the end of the line (or beyond) gets a
+    /// single caret behind the last character.
+    pub(crate) fn carets(&self, line: &str, tab_width: usize) -> (usize, usize) {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let width = |chars: usize| {
+            let text: String = line.chars().take(chars).collect();
+            if tab_width > 0 {
+                expand_tabs(&text, tab_width, &mut 0).width()
+            } else {
+                text.width()
+            }
+        };
+        let start = width(self.col_range.start);
+        let end = width(self.col_range.end.max(self.col_range.start));
+        (start, (end - start).max(1))
+    }
+}
+
+#[test]
+fn carets() {
+    let annotation = Annotation::new(1, 4..8, "unused", Level::Warning);
+    assert_eq!((4, 4), annotation.carets("let main = 1;\n", 4));
+    assert_eq!((7, 4), annotation.carets("\tlet main = 1;\n", 4));
+    assert_eq!((3, 1), annotation.carets("let", 4));
+
+    let empty = Annotation::new(1, 2..2, "expected `;`", Level::Error);
+    assert_eq!((2, 1), empty.carets("a b\n", 4));
+}
@@ -1,34 +1,331 @@
-use std::fs::File;
-use std::io::BufReader;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor};
+use std::iter;
+use std::mem;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
-use syntect::dumps::{from_binary, from_reader};
+use syntect::dumps::{dump_to_file, from_binary, from_reader};
 use syntect::highlighting::{Theme, ThemeSet};
 use syntect::parsing::{SyntaxReference, SyntaxSet};
 
+use crate::builder::PrettyPrint;
 use crate::dirs::PROJECT_DIRS;
 use crate::errors::*;
 use crate::inputfile::{InputFile, InputFileReader};
+use crate::modeline::modeline_token;
+use crate::subset::{syntax_subset, SplitSyntax, SplitSyntaxSet};
+use crate::suggestion::did_you_mean;
 use crate::syntax_mapping::SyntaxMapping;
+use crate::theme::ThemeBuilder;
 
 pub const PRETTYPRINT_THEME_DEFAULT: &str = "Monokai Extended";
 
+lazy_static! {
+    /// The assets integrated into the library, deserialized when they're first used.
+    static ref INTEGRATED_ASSETS: Arc<HighlightingAssets> =
+        Arc::new(HighlightingAssets::from_binary());
+
+    /// The assets of prettyprint's cache, or the integrated ones if there is no cache.
+    static ref CACHED_ASSETS: DirAssets = DirAssets::new(PROJECT_DIRS.cache_dir());
+
+    /// The integrated assets split by syntax and theme, of which printers load what they use.
+    static ref SPLIT_ASSETS: SplitAssets = SplitAssets::new(include_bytes!("../assets/split.bin"));
+}
+
+/// The modification time and the size of each asset file in a directory, `None` if it's missing.
+type Stamp = Vec<Option<(SystemTime, u64)>>;
+
+/// The assets of a directory, loaded when they're first used and again once its files change,
+/// e.g. after `save_to_dir()`. Without assets in the directory, they're the integrated ones.
+struct DirAssets {
+    dir: PathBuf,
+    loaded: Mutex<Option<(Stamp, Option<Arc<HighlightingAssets>>)>>,
+}
+
+impl DirAssets {
+    fn new(dir: &Path) -> Self {
+        DirAssets {
+            dir: dir.to_path_buf(),
+            loaded: Mutex::new(None),
+        }
+    }
+
+    fn get(&self) -> Arc<HighlightingAssets> {
+        self.get_from_dir()
+            .unwrap_or_else(HighlightingAssets::integrated)
+    }
+
+    /// The assets of the directory, `None` if it has none.
+    fn get_from_dir(&self) -> Option<Arc<HighlightingAssets>> {
+        let stamp: Stamp = ["syntaxes.bin", "themes.bin"]
+            .iter()
+            .map(|name| {
+                let metadata = fs::metadata(self.dir.join(name)).ok()?;
+                Some((metadata.modified().ok()?, metadata.len()))
+            })
+            .collect();
+
+        let mut loaded = self
+            .loaded
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        match *loaded {
+            Some((ref loaded_stamp, ref assets)) if *loaded_stamp == stamp => assets.clone(),
+            _ => {
+                let assets = HighlightingAssets::from_dir(&self.dir).ok().map(Arc::new);
+                *loaded = Some((stamp, assets.clone()));
+                assets
+            }
+        }
+    }
+}
+
+/// The header of `assets/split.bin`: the syntaxes, the lengths of their dumped definitions, and
+/// the names of the themes with the lengths of their dumps.
+type SplitHeader = (Vec<SplitSyntax>, Vec<usize>, Vec<(String, usize)>);
+
+/// The themes and syntaxes which a print uses, sorted.
+type SplitKey = (Vec<String>, Vec<String>);
+
+/// The integrated assets, split by syntax and theme (see `split_integrated_assets()`), so that a
+/// print only loads the syntaxes and themes it uses.
+pub(crate) struct SplitAssets {
+    syntaxes: SplitSyntaxSet<'static>,
+    themes: Vec<(String, &'static [u8])>,
+    /// All syntaxes without their contexts, and no themes
+    index: HighlightingAssets,
+    loaded: Mutex<HashMap<SplitKey, Arc<HighlightingAssets>>>,
+}
+
+impl SplitAssets {
+    /// Reads the length of the header (8 bytes, little endian), the header and the dumps which
+    /// it lists, one after another.
+    fn new(bytes: &'static [u8]) -> Self {
+        let (length, rest) = bytes.split_at(8);
+        let length = u64::from_le_bytes(length.try_into().unwrap()) as usize;
+        let (header, mut dumps) = rest.split_at(length);
+        let (syntaxes, lengths, themes): SplitHeader = from_binary(header);
+
+        let mut take = |length: usize| {
+            let (dump, rest) = dumps.split_at(length);
+            dumps = rest;
+            dump
+        };
+        let definitions = lengths.into_iter().map(&mut take).collect();
+        let themes = themes
+            .into_iter()
+            .map(|(name, length)| (name, take(length)))
+            .collect();
+
+        let syntaxes = SplitSyntaxSet::new(syntaxes, definitions);
+        let index = HighlightingAssets {
+            syntax_set: syntaxes.index(),
+            theme_set: ThemeSet::default(),
+        };
+        SplitAssets {
+            syntaxes,
+            themes,
+            index,
+            loaded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The assets to look syntaxes up with. They can't highlight anything.
+    pub fn index(&self) -> &HighlightingAssets {
+        &self.index
+    }
+
+    pub fn theme(&self, name: &str) -> Option<Theme> {
+        self.themes
+            .iter()
+            .find(|(theme, _)| theme == name)
+            .map(|(_, dump)| from_binary(dump))
+    }
+
+    pub fn theme_names(&self) -> impl Iterator<Item = &str> {
+        self.themes.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn themes(&self) -> BTreeMap<String, Theme> {
+        self.themes
+            .iter()
+            .map(|(name, dump)| (name.clone(), from_binary(dump)))
+            .collect()
+    }
+
+    /// The syntaxes named `syntaxes` with the ones they embed (see `SplitSyntaxSet::subset()`),
+    /// and the themes named `themes` with the default one. Each subset is only loaded once.
+    pub fn subset(&self, syntaxes: &[String], themes: &[&str]) -> Arc<HighlightingAssets> {
+        let mut syntaxes = syntaxes.to_vec();
+        syntaxes.sort();
+        syntaxes.dedup();
+        let mut themes: Vec<String> = themes
+            .iter()
+            .chain(iter::once(&PRETTYPRINT_THEME_DEFAULT))
+            .map(|theme| theme.to_string())
+            .collect();
+        themes.sort();
+        themes.dedup();
+
+        let mut loaded = self
+            .loaded
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        let key = (syntaxes, themes);
+        if let Some(assets) = loaded.get(&key) {
+            return Arc::clone(assets);
+        }
+        let assets = Arc::new(HighlightingAssets {
+            syntax_set: self.syntaxes.subset(&key.0),
+            theme_set: ThemeSet {
+                themes: key
+                    .1
+                    .iter()
+                    .filter_map(|name| Some((name.clone(), self.theme(name)?)))
+                    .collect(),
+            },
+        });
+        loaded.insert(key, Arc::clone(&assets));
+        assets
+    }
+}
+
+/// A language which can be highlighted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageInfo {
+    pub name: String,
+    /// File extensions (and file names) which are highlighted in this language
+    pub extensions: Vec<String>,
+    /// Further names accepted by `.language()`, and file names mapped to this language
+    pub aliases: Vec<String>,
+}
+
+/// A language which is known to be available, so that a typo in its name shows up when
+/// configuring the printer rather than as unhighlighted output. Pass it to `.language()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Language(String);
+
+impl Language {
+    /// Looks up `name` (a language name or file extension, as accepted by `.language()`) in the
+    /// languages of `printer`, custom syntaxes included.
+    pub fn find(printer: &PrettyPrint, name: &str) -> Result<Language> {
+        if printer.uses_integrated_assets() {
+            HighlightingAssets::split().index().find_language(name)
+        } else {
+            printer.get_assets()?.find_language(name)
+        }
+    }
+
+    /// The name of the language, e.g. `Ruby`.
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<Language> for String {
+    fn from(language: Language) -> String {
+        language.0
+    }
+}
+
+/// A theme which was looked up once, so that a typo in its name shows up when configuring the
+/// printer. Clones share the theme, also across threads. Pass it to `.resolved_theme()`.
+#[derive(Debug, Clone)]
+pub struct ThemeHandle {
+    name: String,
+    theme: Arc<Theme>,
+}
+
+impl ThemeHandle {
+    /// Looks up the theme `name` in the themes of `printer`, custom themes included.
+    pub fn find(printer: &PrettyPrint, name: &str) -> Result<ThemeHandle> {
+        let unknown = |names: &mut dyn Iterator<Item = &str>| -> Error {
+            format!("Unknown theme '{}'{}", name, did_you_mean(name, names)).into()
+        };
+        let theme = if printer.uses_integrated_assets() {
+            let split = HighlightingAssets::split();
+            split
+                .theme(name)
+                .ok_or_else(|| unknown(&mut split.theme_names()))?
+        } else {
+            let assets = printer.get_assets()?;
+            let themes = &assets.theme_set.themes;
+            themes
+                .get(name)
+                .cloned()
+                .ok_or_else(|| unknown(&mut themes.keys().map(String::as_str)))?
+        };
+        Ok(ThemeHandle {
+            name: name.to_string(),
+            theme: Arc::new(theme),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn theme(&self) -> &Theme {
+        &self.theme
+    }
+}
+
+/// A theme which is loaded in addition to the integrated ones.
+#[derive(Debug, Clone)]
+pub enum ThemeSource {
+    File(PathBuf),
+    Str { name: String, xml: String },
+    Builder(ThemeBuilder),
+}
+
+/// The syntax and theme sets. Loading them takes a while, so many printers (e.g. of a server)
+/// can share them, see `PrettyPrinter::with_assets()`.
 pub struct HighlightingAssets {
     pub syntax_set: SyntaxSet,
     pub theme_set: ThemeSet,
 }
 
+impl Clone for HighlightingAssets {
+    fn clone(&self) -> Self {
+        HighlightingAssets {
+            syntax_set: self.syntax_set.clone(),
+            theme_set: ThemeSet {
+                themes: self.theme_set.themes.clone(),
+            },
+        }
+    }
+}
+
+impl Default for HighlightingAssets {
+    fn default() -> Self {
+        HighlightingAssets::new()
+    }
+}
+
+impl fmt::Debug for HighlightingAssets {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("HighlightingAssets")
+    }
+}
+
 impl HighlightingAssets {
     pub fn new() -> Self {
-        Self::from_cache().unwrap_or_else(|_| Self::from_binary())
+        Self::from_dir(PROJECT_DIRS.cache_dir()).unwrap_or_else(|_| Self::from_binary())
     }
 
-    fn from_cache() -> Result<Self> {
-        let theme_set_path = theme_set_path();
-        let syntax_set_file = File::open(&syntax_set_path()).chain_err(|| {
+    /// Loads the `syntaxes.bin` and `themes.bin` in `dir`, e.g. a cache created by `bat`.
+    pub fn from_dir(dir: &Path) -> Result<Self> {
+        let theme_set_path = dir.join("themes.bin");
+        let syntax_set_path = dir.join("syntaxes.bin");
+        let syntax_set_file = File::open(&syntax_set_path).chain_err(|| {
             format!(
                 "Could not load cached syntax set '{}'",
-                syntax_set_path().to_string_lossy()
+                syntax_set_path.to_string_lossy()
             )
         })?;
         let syntax_set: SyntaxSet = from_reader(BufReader::new(syntax_set_file))
@@ -49,6 +346,41 @@ impl HighlightingAssets {
         })
     }
 
+    /// A copy with only the syntaxes of `languages` (names or file extensions, as accepted by
+    /// `.language()`), the syntaxes they embed and plain text, for tools which only ever
+    /// highlight a few languages. It needs a fraction of the memory, and saved with
+    /// `save_to_dir()` it loads faster, too.
+    pub fn with_languages(&self, languages: &[&str]) -> Result<Self> {
+        let names: Vec<String> = languages
+            .iter()
+            .map(|language| self.find_language(language).map(String::from))
+            .collect::<Result<_>>()?;
+
+        Ok(HighlightingAssets {
+            syntax_set: syntax_subset(&self.syntax_set, &names),
+            theme_set: ThemeSet {
+                themes: self.theme_set.themes.clone(),
+            },
+        })
+    }
+
+    /// Saves the `syntaxes.bin` and `themes.bin` in `dir`, to be loaded with `from_dir()` or
+    /// `.asset_dir()`.
+    pub fn save_to_dir(&self, dir: &Path) -> Result<()> {
+        let error = |name: &str, error: Box<dyn std::error::Error>| {
+            format!(
+                "Could not save '{}': {}",
+                dir.join(name).to_string_lossy(),
+                error
+            )
+        };
+        dump_to_file(&self.syntax_set, dir.join("syntaxes.bin"))
+            .map_err(|e| error("syntaxes.bin", e))?;
+        dump_to_file(&self.theme_set, dir.join("themes.bin"))
+            .map_err(|e| error("themes.bin", e))?;
+        Ok(())
+    }
+
     fn get_integrated_syntaxset() -> SyntaxSet {
         from_binary(include_bytes!("../assets/syntaxes.bin"))
     }
@@ -57,7 +389,30 @@ impl HighlightingAssets {
         from_binary(include_bytes!("../assets/themes.bin"))
     }
 
-    fn from_binary() -> Self {
+    /// The integrated assets, shared by all printers which use them. Unlike `from_binary()`,
+    /// they are only deserialized once.
+    pub fn integrated() -> Arc<HighlightingAssets> {
+        Arc::clone(&INTEGRATED_ASSETS)
+    }
+
+    /// The assets of `new()`, shared by all printers which use them. They're loaded again
+    /// once the files of the cache change.
+    pub fn cached() -> Arc<HighlightingAssets> {
+        CACHED_ASSETS.get()
+    }
+
+    /// The assets of prettyprint's cache, `None` if there are none and `cached()` gives the
+    /// integrated ones.
+    pub(crate) fn cached_from_dir() -> Option<Arc<HighlightingAssets>> {
+        CACHED_ASSETS.get_from_dir()
+    }
+
+    /// The integrated assets, split by syntax and theme.
+    pub(crate) fn split() -> &'static SplitAssets {
+        &SPLIT_ASSETS
+    }
+
+    pub fn from_binary() -> Self {
         let syntax_set = Self::get_integrated_syntaxset();
         let theme_set = Self::get_integrated_themeset();
 
@@ -67,68 +422,416 @@ impl HighlightingAssets {
         }
     }
 
+    /// Adds a `.tmTheme` file, named after the file (`Corporate.tmTheme` → `Corporate`).
+    pub fn add_theme_from_file(&mut self, path: &Path) -> Result<()> {
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| format!("Invalid theme file name '{}'", path.to_string_lossy()))?;
+        let theme = ThemeSet::get_theme(path)
+            .chain_err(|| format!("Could not load theme '{}'", path.to_string_lossy()))?;
+
+        self.theme_set.themes.insert(name.to_string(), theme);
+        Ok(())
+    }
+
+    /// Adds a theme from the XML contents of a `.tmTheme` file.
+    pub fn add_theme_from_str(&mut self, name: &str, xml: &str) -> Result<()> {
+        let theme = ThemeSet::load_from_reader(&mut Cursor::new(xml))
+            .chain_err(|| format!("Could not load theme '{}'", name))?;
+
+        self.theme_set.themes.insert(name.to_string(), theme);
+        Ok(())
+    }
+
+    /// Adds a theme built in code, e.g. with a `ThemeBuilder`.
+    pub fn add_theme(&mut self, name: &str, theme: Theme) {
+        self.theme_set.themes.insert(name.to_string(), theme);
+    }
+
+    pub fn add_theme_source(&mut self, source: &ThemeSource) -> Result<()> {
+        match source {
+            ThemeSource::File(path) => self.add_theme_from_file(path),
+            ThemeSource::Str { name, xml } => self.add_theme_from_str(name, xml),
+            ThemeSource::Builder(builder) => {
+                self.add_theme(builder.name(), builder.build()?);
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds the `.sublime-syntax` files in `path` (and its subfolders) to the syntax set.
+    pub fn add_syntax_from_folder(&mut self, path: &Path) -> Result<()> {
+        let mut builder = mem::replace(&mut self.syntax_set, SyntaxSet::new()).into_builder();
+        let result = builder.add_from_folder(path, true);
+        self.syntax_set = builder.build();
+
+        result.chain_err(|| format!("Could not load syntaxes from '{}'", path.to_string_lossy()))
+    }
+
+    /// All visible languages, sorted by name.
+    pub fn get_languages(&self, syntax_mapping: &SyntaxMapping) -> Vec<LanguageInfo> {
+        let mut languages: Vec<LanguageInfo> = self
+            .syntax_set
+            .syntaxes()
+            .iter()
+            .filter(|syntax| !syntax.hidden)
+            .map(|syntax| {
+                let mut aliases = vec![syntax.name.to_lowercase()];
+                aliases.extend(
+                    syntax_mapping
+                        .iter()
+                        .filter(|(_, to)| {
+                            self.syntax_set
+                                .find_syntax_by_token(to)
+                                .is_some_and(|mapped| mapped.name == syntax.name)
+                        })
+                        .map(|(from, _)| from.to_string()),
+                );
+                aliases.retain(|alias| {
+                    *alias != syntax.name && !syntax.file_extensions.contains(alias)
+                });
+                aliases.sort();
+
+                LanguageInfo {
+                    name: syntax.name.clone(),
+                    extensions: syntax.file_extensions.clone(),
+                    aliases,
+                }
+            })
+            .collect();
+
+        languages.sort_by_key(|language| language.name.to_lowercase());
+        languages
+    }
+
+    pub fn find_language(&self, name: &str) -> Result<Language> {
+        self.syntax_set
+            .find_syntax_by_token(name)
+            .map(|syntax| Language(syntax.name.clone()))
+            .ok_or_else(|| {
+                let suggestion = did_you_mean(name, self.language_tokens());
+                format!("Unknown language '{}'{}", name, suggestion).into()
+            })
+    }
+
+    /// The names and file extensions of the visible syntaxes.
+    fn language_tokens(&self) -> impl Iterator<Item = &str> {
+        self.syntax_set
+            .syntaxes()
+            .iter()
+            .filter(|syntax| !syntax.hidden)
+            .flat_map(|syntax| {
+                iter::once(syntax.name.as_str())
+                    .chain(syntax.file_extensions.iter().map(String::as_str))
+            })
+    }
+
     pub fn get_theme(&self, theme: &str) -> &Theme {
+        self.get_theme_among(theme, self.theme_set.themes.keys().map(String::as_str))
+    }
+
+    /// Like `get_theme()`, suggesting one of the themes named `names` for an unknown theme.
+    pub(crate) fn get_theme_among<'a, I>(&self, theme: &str, names: I) -> &Theme
+    where
+        I: Iterator<Item = &'a str>,
+    {
         match self.theme_set.themes.get(theme) {
             Some(theme) => theme,
             None => {
                 use ansi_term::Colour::Yellow;
                 eprintln!(
-                    "{}: Unknown theme '{}', using default.",
+                    "{}: Unknown theme '{}'{}, using default.",
                     Yellow.paint("[prettyprint warning]"),
-                    theme
+                    theme,
+                    did_you_mean(theme, names)
                 );
                 &self.theme_set.themes[PRETTYPRINT_THEME_DEFAULT]
             }
         }
     }
 
+    /// Detects the syntax from the first line of a file: the first line patterns of the syntaxes,
+    /// the interpreter of a shebang (`#!/usr/bin/env python3`) or a leading `<?php`/`<?xml`.
+    fn get_first_line_syntax(&self, first_line: &[u8]) -> Option<&SyntaxReference> {
+        let line = String::from_utf8_lossy(first_line);
+
+        self.syntax_set
+            .find_syntax_by_first_line(&line)
+            .or_else(|| {
+                first_line_token(&line)
+                    .and_then(|token| self.syntax_set.find_syntax_by_token(token))
+            })
+    }
+
     pub fn get_syntax(
         &self,
         language: Option<String>,
         filename: &InputFile,
         reader: &mut InputFileReader,
         mapping: &SyntaxMapping,
+        modelines: bool,
     ) -> &SyntaxReference {
+        // Modelines are explicit about the language, so they win over file names.
+        let modeline_syntax = if modelines && language.is_none() {
+            modeline_token(filename, reader)
+                .and_then(|token| self.syntax_set.find_syntax_by_token(&token))
+        } else {
+            None
+        };
+
         let syntax = match (language, filename) {
             (Some(language), _) => self.syntax_set.find_syntax_by_token(&language),
+            (None, _) if modeline_syntax.is_some() => modeline_syntax,
             (None, InputFile::Ordinary(filename)) => {
                 let path = Path::new(&filename);
                 let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
                 let extension = path.extension().and_then(|x| x.to_str()).unwrap_or("");
 
-                let file_name = mapping.replace(file_name);
-                let extension = mapping.replace(extension);
-
-                let ext_syntax = self
-                    .syntax_set
-                    .find_syntax_by_extension(&file_name)
-                    .or_else(|| self.syntax_set.find_syntax_by_extension(&extension));
+                let ext_syntax = mapping
+                    .get(filename)
+                    .and_then(|token| self.syntax_set.find_syntax_by_token(token))
+                    .or_else(|| self.syntax_set.find_syntax_by_extension(file_name))
+                    .or_else(|| self.syntax_set.find_syntax_by_extension(extension));
                 let line_syntax = if ext_syntax.is_none() {
-                    String::from_utf8(reader.first_line.clone())
-                        .ok()
-                        .and_then(|l| self.syntax_set.find_syntax_by_first_line(&l))
+                    self.get_first_line_syntax(&reader.first_line)
                 } else {
                     None
                 };
-                let syntax = ext_syntax.or(line_syntax);
-                syntax
+                ext_syntax.or(line_syntax)
             }
-            (None, InputFile::StdIn) => String::from_utf8(reader.first_line.clone())
-                .ok()
-                .and_then(|l| self.syntax_set.find_syntax_by_first_line(&l)),
-            (None, InputFile::String(_)) => String::from_utf8(reader.first_line.clone())
-                .ok()
-                .and_then(|l| self.syntax_set.find_syntax_by_first_line(&l)),
+            (None, InputFile::StdIn)
+            | (None, InputFile::String(_))
+            | (None, InputFile::Bytes(_)) => self.get_first_line_syntax(&reader.first_line),
         };
 
         syntax.unwrap_or_else(|| self.syntax_set.find_syntax_plain_text())
     }
 }
 
-fn theme_set_path() -> PathBuf {
-    PROJECT_DIRS.cache_dir().join("themes.bin")
+/// A language token for the first line of a file, e.g. `python` for `#!/usr/bin/python3 -u`.
+fn first_line_token(line: &str) -> Option<&str> {
+    let line = line.trim_start_matches('\u{feff}').trim_end();
+
+    if let Some(shebang) = line.strip_prefix("#!") {
+        // Skip `env` and its options, e.g. `#!/usr/bin/env -S node --harmony`.
+        let interpreter = shebang
+            .split_whitespace()
+            .map(|word| word.rsplit('/').next().unwrap_or(word))
+            .find(|word| *word != "env" && !word.starts_with('-') && !word.contains('='))?;
+
+        // Versioned interpreters like `python3.7` or `ruby2.6`.
+        let interpreter = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+        return Some(match interpreter {
+            "sh" | "ash" | "dash" | "ksh" | "zsh" => "bash",
+            "node" | "nodejs" | "deno" => "js",
+            "pwsh" | "powershell" => "ps1",
+            "Rscript" => "r",
+            "runhaskell" | "runghc" => "hs",
+            interpreter => interpreter,
+        });
+    }
+
+    let line = line.to_lowercase();
+    if line.starts_with("<?php") {
+        Some("php")
+    } else if line.starts_with("<?xml") {
+        Some("xml")
+    } else if line.starts_with("<!doctype html") || line.starts_with("<html") {
+        Some("html")
+    } else {
+        None
+    }
+}
+
+#[test]
+fn first_lines() {
+    assert_eq!(Some("python"), first_line_token("#!/usr/bin/env python3\n"));
+    assert_eq!(Some("python"), first_line_token("#!/usr/bin/python3.7 -u"));
+    assert_eq!(
+        Some("js"),
+        first_line_token("#!/usr/bin/env -S node --harmony")
+    );
+    assert_eq!(Some("bash"), first_line_token("#!/bin/sh"));
+    assert_eq!(Some("php"), first_line_token("<?php echo 1;"));
+    assert_eq!(Some("xml"), first_line_token("<?xml version=\"1.0\"?>"));
+    assert_eq!(None, first_line_token("fn main() {}"));
+}
+
+#[test]
+fn first_line_syntaxes() {
+    let assets = HighlightingAssets::from_binary();
+    let syntax = |line: &str| {
+        assets
+            .get_first_line_syntax(line.as_bytes())
+            .map(|syntax| syntax.name.clone())
+    };
+
+    assert_eq!(
+        Some("Python".to_string()),
+        syntax("#!/usr/bin/env python3\n")
+    );
+    assert_eq!(
+        Some("JavaScript".to_string()),
+        syntax("#!/usr/bin/env node\n")
+    );
+    assert_eq!(Some("XML".to_string()), syntax("<?xml version=\"1.0\"?>\n"));
+    assert_eq!(None, syntax("hello\n"));
+}
+
+#[test]
+fn assets_from_dir() {
+    let dir = std::env::temp_dir().join("prettyprint-assets-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    assert!(HighlightingAssets::from_dir(&dir.join("missing")).is_err());
+
+    let assets = HighlightingAssets::from_binary();
+    assets.save_to_dir(&dir).unwrap();
+
+    let loaded = HighlightingAssets::from_dir(&dir).unwrap();
+    assert_eq!(assets.theme_set.themes.len(), loaded.theme_set.themes.len());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn assets_reloaded_from_dir() {
+    let dir = std::env::temp_dir().join("prettyprint-reload-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let assets = DirAssets::new(&dir);
+    assert!(Arc::ptr_eq(
+        &HighlightingAssets::integrated(),
+        &assets.get()
+    ));
+
+    let subset = HighlightingAssets::from_binary()
+        .with_languages(&["rs"])
+        .unwrap();
+    subset.save_to_dir(&dir).unwrap();
+    let loaded = assets.get();
+    assert_eq!(
+        subset.syntax_set.syntaxes().len(),
+        loaded.syntax_set.syntaxes().len()
+    );
+    assert!(Arc::ptr_eq(&loaded, &assets.get()));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(Arc::ptr_eq(
+        &HighlightingAssets::integrated(),
+        &assets.get()
+    ));
+}
+
+#[test]
+fn assets_with_languages() {
+    let assets = HighlightingAssets::from_binary();
+    let subset = assets.with_languages(&["rs", "html"]).unwrap();
+    let names: Vec<&str> = subset
+        .syntax_set
+        .syntaxes()
+        .iter()
+        .map(|syntax| syntax.name.as_str())
+        .collect();
+    for name in &["Rust", "HTML", "JavaScript", "CSS", "Plain Text"] {
+        assert!(names.contains(name), "{} is missing", name);
+    }
+    assert!(!names.contains(&"Python"));
+    assert!(names.len() < assets.syntax_set.syntaxes().len() / 2);
+
+    assert!(assets.with_languages(&["rusty"]).is_err());
+}
+
+#[test]
+fn split_assets() {
+    use syntect::easy::HighlightLines;
+
+    let assets = HighlightingAssets::from_binary();
+    let split = HighlightingAssets::split();
+    let names = |assets: &HighlightingAssets| -> Vec<String> {
+        let syntaxes = assets.syntax_set.syntaxes();
+        syntaxes.iter().map(|syntax| syntax.name.clone()).collect()
+    };
+    assert_eq!(names(&assets), names(split.index()));
+    assert!(split
+        .theme_names()
+        .eq(assets.theme_set.themes.keys().map(String::as_str)));
+
+    let subset = split.subset(&["HTML".to_string()], &["TwoDark"]);
+    let subset_names = names(&subset);
+    for name in &["HTML", "JavaScript", "CSS", "Plain Text"] {
+        assert!(
+            subset_names.iter().any(|n| n == name),
+            "{} is missing",
+            name
+        );
+    }
+    assert!(!subset_names.iter().any(|name| name == "Python"));
+    let themes: Vec<&String> = subset.theme_set.themes.keys().collect();
+    assert_eq!(vec!["Monokai Extended", "TwoDark"], themes);
+
+    let theme = &assets.theme_set.themes["TwoDark"];
+    let mut expected = HighlightLines::new(
+        assets.syntax_set.find_syntax_by_name("HTML").unwrap(),
+        theme,
+    );
+    let mut highlighted = HighlightLines::new(
+        subset.syntax_set.find_syntax_by_name("HTML").unwrap(),
+        theme,
+    );
+    for line in &[
+        "<style>p { color: red; }</style>",
+        "<script>let x = 1 + '2';</script>",
+        "<p class=\"a\">Text &amp; more</p>",
+    ] {
+        assert_eq!(
+            expected.highlight(line, &assets.syntax_set),
+            highlighted.highlight(line, &subset.syntax_set)
+        );
+    }
+
+    assert!(Arc::ptr_eq(
+        &subset,
+        &split.subset(&["HTML".to_string()], &["TwoDark", "TwoDark"])
+    ));
+}
+
+/// `assets/split.bin`, made of the integrated `syntaxes.bin` and `themes.bin`, see
+/// `SplitAssets::new()`.
+#[cfg(test)]
+fn split_integrated_assets() -> Vec<u8> {
+    use crate::subset::split_syntax_set;
+    use syntect::dumps::dump_binary;
+
+    let assets = HighlightingAssets::from_binary();
+    let (syntaxes, definitions) = split_syntax_set(&assets.syntax_set);
+    let themes: Vec<(String, Vec<u8>)> = assets
+        .theme_set
+        .themes
+        .iter()
+        .map(|(name, theme)| (name.clone(), dump_binary(theme)))
+        .collect();
+
+    let header: SplitHeader = (
+        syntaxes,
+        definitions.iter().map(Vec::len).collect(),
+        themes
+            .iter()
+            .map(|(name, dump)| (name.clone(), dump.len()))
+            .collect(),
+    );
+    let header = dump_binary(&header);
+    let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+    bytes.extend(header);
+    bytes.extend(definitions.into_iter().flatten());
+    bytes.extend(themes.into_iter().flat_map(|(_, dump)| dump));
+    bytes
 }
 
-fn syntax_set_path() -> PathBuf {
-    PROJECT_DIRS.cache_dir().join("syntaxes.bin")
+/// Writes `assets/split.bin`, see `assets/create.sh`.
+#[test]
+#[ignore]
+fn save_split_assets() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/split.bin");
+    fs::write(path, split_integrated_assets()).unwrap();
 }
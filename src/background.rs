@@ -0,0 +1,127 @@
+use std::env;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Background {
+    Dark,
+    Light,
+}
+
+lazy_static! {
+    /// Background of the terminal, detected once per process as querying the terminal takes a
+    /// moment.
+    pub static ref BACKGROUND: Option<Background> = detect();
+}
+
+/// Detects the terminal background from `COLORFGBG`, or else by asking the terminal for its
+/// background color (OSC 11).
+fn detect() -> Option<Background> {
+    env::var("COLORFGBG")
+        .ok()
+        .and_then(|value| from_colorfgbg(&value))
+        .or_else(query_terminal)
+}
+
+/// `COLORFGBG` is set to `foreground;background` (e.g. `15;0`) with ANSI color numbers.
+fn from_colorfgbg(value: &str) -> Option<Background> {
+    let background: u8 = value.rsplit(';').next()?.parse().ok()?;
+    match background {
+        7 | 9..=15 => Some(Background::Light),
+        _ => Some(Background::Dark),
+    }
+}
+
+/// Parses the answer to an OSC 11 query, e.g. `\x1b]11;rgb:ffff/ffff/dddd\x07`.
+fn from_osc11_response(response: &str) -> Option<Background> {
+    let rgb = response.split("rgb:").nth(1)?;
+    let rgb = rgb
+        .trim_end_matches(['\x07', '\\'])
+        .trim_end_matches('\x1b');
+
+    let channels: Vec<f64> = rgb
+        .split('/')
+        .map(|channel| {
+            let max = 16f64.powi(channel.len() as i32) - 1.0;
+            u32::from_str_radix(channel, 16)
+                .ok()
+                .map(|value| f64::from(value) / max)
+        })
+        .collect::<Option<_>>()?;
+
+    match channels[..] {
+        [r, g, b] if 0.2126 * r + 0.7152 * g + 0.0722 * b > 0.5 => Some(Background::Light),
+        [_, _, _] => Some(Background::Dark),
+        _ => None,
+    }
+}
+
+#[cfg(unix)]
+fn query_terminal() -> Option<Background> {
+    use std::fs::OpenOptions;
+    use std::io::{Read, Write};
+    use std::os::unix::io::AsRawFd;
+
+    use termios::{tcsetattr, Termios, ECHO, ICANON, TCSANOW, VMIN, VTIME};
+
+    if !atty::is(atty::Stream::Stdout) || env::var("TERM").map_or(true, |term| term == "dumb") {
+        return None;
+    }
+
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+    let fd = tty.as_raw_fd();
+
+    let original = Termios::from_fd(fd).ok()?;
+    let mut raw = original;
+    raw.c_lflag &= !(ICANON | ECHO);
+    // Give up after 100ms if the terminal doesn't answer.
+    raw.c_cc[VMIN] = 0;
+    raw.c_cc[VTIME] = 1;
+    tcsetattr(fd, TCSANOW, &raw).ok()?;
+
+    let mut response = Vec::new();
+    if tty
+        .write_all(b"\x1b]11;?\x07")
+        .and_then(|_| tty.flush())
+        .is_ok()
+    {
+        let mut byte = [0; 1];
+        while let Ok(1) = tty.read(&mut byte) {
+            response.push(byte[0]);
+            if byte[0] == b'\x07' || response.ends_with(b"\x1b\\") || response.len() > 64 {
+                break;
+            }
+        }
+    }
+
+    tcsetattr(fd, TCSANOW, &original).ok()?;
+
+    from_osc11_response(&String::from_utf8_lossy(&response))
+}
+
+#[cfg(not(unix))]
+fn query_terminal() -> Option<Background> {
+    None
+}
+
+#[test]
+fn colorfgbg() {
+    assert_eq!(Some(Background::Dark), from_colorfgbg("15;0"));
+    assert_eq!(Some(Background::Light), from_colorfgbg("0;default;15"));
+    assert_eq!(None, from_colorfgbg("default"));
+}
+
+#[test]
+fn osc11_response() {
+    assert_eq!(
+        Some(Background::Light),
+        from_osc11_response("\x1b]11;rgb:ffff/ffff/dddd\x07")
+    );
+    assert_eq!(
+        Some(Background::Dark),
+        from_osc11_response("\x1b]11;rgb:28/2c/34\x1b\\")
+    );
+    assert_eq!(None, from_osc11_response(""));
+}
@@ -0,0 +1,65 @@
+use syntect::highlighting::{FontStyle, Style};
+
+use crate::html::css_color;
+
+/// BBCode has no escapes: brackets which could start a tag are followed by an empty tag, so that
+/// e.g. `[b]` in the code is printed as it is.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        escaped.push(c);
+        if c == '['
+            && chars
+                .peek()
+                .is_some_and(|&next| next == '/' || next.is_ascii_alphabetic())
+        {
+            escaped.push_str("[i][/i]");
+        }
+    }
+    escaped
+}
+
+/// A highlighted region, e.g. `[color=#f92672][b]fn[/b][/color]`. Whitespace keeps no tags.
+pub fn tag(style: Style, text: &str) -> String {
+    let mut tagged = escape(text);
+    if text.trim().is_empty() {
+        return tagged;
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        tagged = format!("[u]{}[/u]", tagged);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        tagged = format!("[i]{}[/i]", tagged);
+    }
+    if style.font_style.contains(FontStyle::BOLD) {
+        tagged = format!("[b]{}[/b]", tagged);
+    }
+    match css_color(style.foreground) {
+        Some(color) => format!("[color={}]{}[/color]", color, tagged),
+        None => tagged,
+    }
+}
+
+#[test]
+fn escapes() {
+    assert_eq!("a[0] [[i][/i]b] [[i][/i]/b]", escape("a[0] [b] [/b]"));
+}
+
+#[test]
+fn tags() {
+    use syntect::highlighting::Color;
+
+    let style = Style {
+        foreground: Color {
+            r: 0xf9,
+            g: 0x26,
+            b: 0x72,
+            a: 0xff,
+        },
+        font_style: FontStyle::BOLD,
+        ..Style::default()
+    };
+    assert_eq!("[color=#f92672][b]fn[/b][/color]", tag(style, "fn"));
+    assert_eq!("  ", tag(style, "  "));
+}
@@ -1,115 +1,908 @@
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::env;
-use std::io::Write;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::iter;
+use std::mem;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
-use syntect::highlighting::Theme;
+use atty::Stream;
 use console::Term;
+use content_inspector::ContentType;
+use regex::Regex;
+use syntect::highlighting::{Style, Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use unicode_width::UnicodeWidthStr;
 
-use crate::assets::{HighlightingAssets, PRETTYPRINT_THEME_DEFAULT};
+use crate::annotation::{Annotation, Level};
+use crate::assets::{
+    HighlightingAssets, Language, LanguageInfo, ThemeHandle, ThemeSource, PRETTYPRINT_THEME_DEFAULT,
+};
+use crate::background::{Background, BACKGROUND};
+use crate::ci::{self, CiEmitter};
+use crate::decoding::InvalidBytes;
+use crate::decorations::{format_line_number, Decoration, LineNumberFormat};
+use crate::diff::DIFF_SYNTAX;
+use crate::dirs::BAT_CACHE_DIR;
 use crate::errors::*;
-use crate::inputfile::{InputFile, InputFileReader};
+use crate::formatting::Formatting;
+use crate::html::{self, HtmlStyle, HtmlTabs, HtmlWrapper};
+use crate::injection::{Injection, LineHighlighter, DEFAULT_INJECTION_SELECTOR};
+use crate::inputfile::{unreadable, InputFile, InputFileReader};
 use crate::line_range::RangeCheckResult;
-use crate::output::OutputType;
-use crate::printer::{InteractivePrinter, Printer};
+#[cfg(feature = "notebook")]
+use crate::notebook::{self, CellNumber, Notebook};
+use crate::output::{Notify, OutputType, TerminalTitle};
+use crate::overlay::ProseChecker;
+use crate::parallel;
+use crate::preprocessor::{AnsiStripper, NonprintableReplacer, NonprintableSymbols, Preprocessor};
+use crate::printer::{
+    BbCodePrinter, Colors, HexdumpPrinter, HtmlPrinter, InteractivePrinter, JsonPrinter,
+    LatexPrinter, Printer, RtfPrinter, SvgPrinter,
+};
+use crate::side_by_side::{self, Columns};
 
 #[cfg(windows)]
 use ansi_term;
 
-use crate::line_range::LineRanges;
-use crate::style::{OutputComponent, OutputComponents, OutputWrap};
+use crate::line_range::{LineRange, LineRanges};
+use crate::style::{
+    Accessibility, GridCharset, OutputComponent, OutputComponents, OutputWrap, TrailingWhitespace,
+};
+use crate::symbol;
 use crate::syntax_mapping::SyntaxMapping;
+use crate::theme::ThemeBuilder;
+use crate::transform::{self, LineAction, LineFilter, Random, Sampling, Transform};
+use crate::width::{ConsoleWidth, WidthProvider, DEFAULT_WIDTH};
 
+/// Version of the rendered output. It is increased whenever the output changes for the same
+/// input and options, so that tools which compare rendered output (e.g. golden tests) can pin
+/// it with `.output_format_version()` and keep getting the output they compare to.
+///
+/// 1. The first version.
+/// 2. Wrapped lines get the continuation marker without line numbers, and the gutter of
+///    `.dedup_repeats()` is only as wide as the ranges of the repeated lines.
+pub const OUTPUT_FORMAT_VERSION: u32 = 2;
+
+#[derive(Debug, Clone)]
+pub(crate) enum AssetSource {
+    Path(PathBuf),
+    Bat,
+    Shared(Arc<HighlightingAssets>),
+}
+
+/// Sets of options for common environments, see `.preset()`.
 #[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Preset {
+    /// Reproducible output for CI logs: no pager, a fixed width, 256 colors, an ASCII grid and
+    /// each file in a foldable group on GitHub Actions and GitLab CI
+    Ci,
+    /// Linear output for screen readers and text to speech: no colors, grid or pager, each line
+    /// prefixed with `line N:` and no box-drawing characters
+    ScreenReader,
+}
+
+const DETACHED_ASSETS: &str =
+    "Syntaxes and themes can't be loaded from files when rendering without filesystem access";
+
+/// Terminal width of the `Ci` preset.
+const CI_TERM_WIDTH: usize = 120;
+
+/// Size of the output buffer when streaming.
+const STREAM_BUFFER_BYTES: usize = 64 * 1024;
+
+/// When streaming, the output is flushed after this many lines.
+const STREAM_FLUSH_LINES: usize = 1024;
+
+/// When streaming, longer lines (after the first) are split into pieces of this many bytes.
+const STREAM_LINE_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum PagingMode {
+    #[default]
     Always,
     QuitIfOneScreen,
     Never,
 }
 
-impl Default for PagingMode {
-    fn default() -> Self {
-        PagingMode::Always
-    }
+/// What the printer writes, see `.output_format()`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputFormat {
+    /// Text with ANSI escape sequences
+    #[default]
+    Terminal,
+    /// A standalone HTML page
+    Html,
+    /// An SVG image
+    Svg,
+    /// A `Verbatim` environment for LaTeX documents with the `fancyvrb` and `xcolor` packages
+    Latex,
+    /// An RTF document, e.g. to paste into a word processor or an email
+    Rtf,
+    /// Text with BBCode tags, e.g. for forums
+    BbCode,
+    /// The highlighted regions of each line, as JSON Lines
+    Json,
 }
 
 /// The main pretty print object.
 ///
 /// This gets created through a builder.
-#[derive(Default, Builder, Debug)]
-#[builder(name = "PrettyPrinter", setter(into))]
+#[derive(Default, Builder, Debug, Clone)]
+#[builder(
+    name = "PrettyPrinter",
+    setter(into),
+    build_fn(name = "try_build", private)
+)]
 pub struct PrettyPrint {
     // This is a hack, because we can not use skip right now
     // See https://github.com/colin-kiegel/rust-derive-builder/issues/110
-    /// Language for syntax highlighting
+    /// Language for syntax highlighting, a name or a `Language`
     #[builder(default = "\"unknown\".to_string()")]
-    language: String,
+    pub(crate) language: String,
 
     /// Whether or not to show/replace non-printable
     /// characters like space, tab and newline.
     #[builder(default = "false")]
-    show_nonprintable: bool,
+    pub(crate) show_nonprintable: bool,
+
+    /// Remove the ANSI escape sequences (CSI and OSC) which the input already contains, before
+    /// it is highlighted
+    #[builder(default = "false")]
+    pub(crate) strip_ansi: bool,
+
+    /// Encoding of the input, see `.encoding()`
+    #[builder(private, default)]
+    pub(crate) encoding_label: Option<String>,
+
+    /// What to do with lines which aren't valid in the encoding of the input
+    #[builder(default)]
+    pub(crate) invalid_bytes: InvalidBytes,
+
+    /// Show text as a hexdump, like binary content with `binary_hexdump`
+    #[builder(default = "false")]
+    pub(crate) hexdump: bool,
+
+    /// Show binary content as a hexdump, instead of only noting it in the header
+    #[builder(default = "false")]
+    pub(crate) binary_hexdump: bool,
+
+    /// Only show the first bytes of a hexdump, and how many were cut off
+    #[builder(default = "None")]
+    pub(crate) binary_preview_bytes: Option<usize>,
+
+    /// Mark the line break of each line with a dimmed `␍␊` or `␊`, to spot mixed line endings
+    #[builder(default = "false")]
+    pub(crate) show_line_endings: bool,
+
+    /// Symbols shown for the non-printable characters, e.g.
+    /// `NonprintableSymbols::CONTROL_PICTURES`
+    #[builder(default)]
+    pub(crate) nonprintable_symbols: NonprintableSymbols,
+
+    /// The character width of the terminal, instead of asking the `.width_provider()` at each
+    /// print
+    #[builder(default = "None")]
+    pub(crate) term_width: Option<usize>,
 
-    /// The character width of the terminal
-    #[builder(default = "Term::stdout().size().1 as usize")]
-    term_width: usize,
+    /// Tells the width at each print, see `.width_provider()`
+    #[builder(private, default)]
+    pub(crate) width_source: Option<Arc<dyn WidthProvider>>,
 
     /// The width of tab characters.
     /// Currently, a value of 0 will cause tabs to be
     /// passed through without expanding them.
     #[builder(default = "0")]
-    tab_width: usize,
+    pub(crate) tab_width: usize,
+
+    /// Only add colors, so that stripping the ANSI escape sequences gives back the original
+    /// bytes of the file. Overrides every option which changes or adds text
+    #[builder(default = "false")]
+    pub(crate) copy_fidelity: bool,
 
-    /// Whether or not to simply loop through all input (`cat` mode)
+    /// Whether or not to simply loop through all input (`cat` mode), printing the bytes as they are
+    /// without highlighting or decorations
     #[builder(default = "false")]
-    loop_through: bool,
+    pub(crate) loop_through: bool,
+
+    /// Whether or not the output should be colorized when printing to a terminal. Set
+    /// explicitly, it wins over the `NO_COLOR` and `CLICOLOR_FORCE` environment variables
+    #[builder(default = "None")]
+    pub(crate) colored_output: Option<bool>,
 
-    /// Whether or not the output should be colorized
+    /// Whether the `NO_COLOR` and `CLICOLOR_FORCE` environment variables decide about colors
+    /// when `colored_output` isn't set
     #[builder(default = "true")]
-    colored_output: bool,
+    pub(crate) color_env: bool,
+
+    /// Colorize the output even if it isn't a terminal. `NO_COLOR` still turns colors off
+    #[builder(default = "false")]
+    pub(crate) force_colors: bool,
+
+    /// Wraps each file in a collapsible group of the CI log, see `.ci_emitter()`
+    #[builder(private, default)]
+    pub(crate) emitter: Option<Arc<dyn CiEmitter>>,
+
+    /// Errors reported to the CI service, by file and line number
+    #[builder(private, default)]
+    pub(crate) ci_errors: Vec<(String, usize, String)>,
+
+    /// Columns of the gutter given with `.decoration()`
+    #[builder(private, default)]
+    pub(crate) custom_decorations: Vec<Arc<dyn Decoration>>,
+
+    /// Stages given to `.preprocessor()`, in order
+    #[builder(private, default)]
+    pub(crate) preprocessors: Vec<Arc<dyn Preprocessor>>,
+
+    /// Messages printed beneath spans of lines
+    #[builder(private, default)]
+    pub(crate) annotations: Vec<Annotation>,
 
     /// Whether or not the output terminal supports true color
     #[builder(default = "is_truecolor_terminal()")]
-    true_color: bool,
+    pub(crate) true_color: bool,
 
     /// Print grid
     #[builder(default = "true")]
-    grid: bool,
+    pub(crate) grid: bool,
+
+    /// Draw the grid with ASCII characters (`|`, `-`, `+`) instead of box-drawing characters
+    #[builder(default = "false")]
+    pub(crate) ascii_grid: bool,
+
+    /// Draw the grid with custom characters, e.g. `GridCharset::DOUBLE`. Takes precedence over
+    /// `ascii_grid`
+    #[builder(default = "None")]
+    pub(crate) grid_charset: Option<GridCharset>,
 
     /// Print header with output file name
     #[builder(default = "true")]
-    header: bool,
+    pub(crate) header: bool,
+
+    /// Highlight the input as a unified diff, whatever its file name or language
+    #[builder(default = "false")]
+    pub(crate) diff_mode: bool,
+
+    /// Paint the background color of the theme behind the whole output, up to the terminal
+    /// width, e.g. for dark themes on light terminals
+    #[builder(default = "false")]
+    pub(crate) use_background: bool,
+
+    /// Make the file name in the header a `file://` OSC 8 hyperlink
+    #[builder(default = "false")]
+    pub(crate) header_hyperlink: bool,
+
+    /// Template given to `.line_number_links()`
+    #[builder(private, default)]
+    pub(crate) line_link_template: Option<String>,
+
+    /// Underline URLs in the content
+    #[builder(default = "false")]
+    pub(crate) underline_urls: bool,
+
+    /// Make URLs in the content clickable with OSC 8 hyperlinks, in terminals supporting them
+    #[builder(default = "false")]
+    pub(crate) url_hyperlinks: bool,
+
+    /// Print documentation comments as wrapped paragraphs of prose between the code
+    #[builder(default = "false")]
+    pub(crate) literate: bool,
+
+    /// List the functions, types and sections defined in the file below the header
+    #[builder(default = "false")]
+    pub(crate) table_of_contents: bool,
+
+    /// List the identifiers of the file and the lines they occur in below it
+    #[builder(default = "false")]
+    pub(crate) identifier_index: bool,
+
+    /// Text in front of the file name in the header
+    #[builder(default = "\"File: \".to_string()")]
+    pub(crate) header_prefix: String,
+
+    /// Line of text below the file, e.g. "generated by my-tool"
+    #[builder(default = "None")]
+    pub(crate) footer: Option<String>,
+
+    /// Show the size of files in the header
+    #[builder(default = "false")]
+    pub(crate) header_size: bool,
+
+    /// Show the modification time of files in the header
+    #[builder(default = "false")]
+    pub(crate) header_modified: bool,
+
+    /// Show the unix permissions of files in the header
+    #[builder(default = "false")]
+    pub(crate) header_permissions: bool,
+
+    /// Only print a separator instead of the header, if the file is the same as the one
+    /// printed before (by this printer or a clone of it), e.g. when re-printing a watched file
+    #[builder(default = "false")]
+    pub(crate) collapse_repeated_header: bool,
+
+    /// The title of the last header, shared by all clones
+    #[builder(private, default)]
+    pub(crate) last_header: Arc<Mutex<Option<String>>>,
+
+    /// Print the header inside the top line of the grid, instead of a row of its own
+    #[builder(default = "false")]
+    pub(crate) frame_title: bool,
 
     /// Print line numbers
     #[builder(default = "true")]
-    line_numbers: bool,
+    pub(crate) line_numbers: bool,
+
+    /// Mark the lines changed since the last git commit in the gutter: `+` for added, `~` for
+    /// modified lines and `-` where lines were removed
+    #[builder(default = "false")]
+    pub(crate) changes: bool,
+
+    /// Number shown for the first line, e.g. to show where an excerpt is located in its file.
+    /// The gutter fits the number of the last line of strings and byte slices; the length of
+    /// files isn't known before they're read, so it only fits the first one there
+    #[builder(default = "1")]
+    pub(crate) first_line_number: usize,
+
+    /// Print a sparkline of the line lengths below the file
+    #[builder(default = "false")]
+    pub(crate) overview: bool,
+
+    /// Experimental: show a minimap of the file in the rightmost column. The whole file is
+    /// read before printing.
+    #[builder(default = "false")]
+    pub(crate) minimap: bool,
+
+    /// Mark whitespace at the end of lines with `·` markers or a red background
+    #[builder(default = "TrailingWhitespace::None")]
+    pub(crate) trailing_whitespace: TrailingWhitespace,
+
+    /// Markers which convey the information of the colors, e.g. for screen readers
+    #[builder(default)]
+    pub(crate) accessibility: Accessibility,
 
     /// Text wrapping mode
     #[builder(default = "OutputWrap::None")]
-    output_wrap: OutputWrap,
+    pub(crate) output_wrap: OutputWrap,
+
+    /// Transformations of the lines (e.g. sorting), applied before printing
+    #[builder(default)]
+    pub(crate) transforms: Vec<Transform>,
+
+    /// Number of threads which parse the lines ahead of printing them, in windows of a few
+    /// thousand lines per thread: one per CPU core if it's zero. With one (the default), lines
+    /// are parsed as they're printed.
+    #[builder(default = "1")]
+    pub(crate) highlight_threads: usize,
+
+    /// Read, highlight and write the input line by line with buffers of a fixed size, flushing
+    /// the output every few lines, e.g. to pipe huge logs into a pager. The options which need
+    /// the whole file are turned off
+    #[builder(default = "false")]
+    pub(crate) streaming: bool,
+
+    /// Only print a sample of the lines, e.g. of a huge file. The skipped lines are not
+    /// highlighted, so the highlighting of multi-line constructs can be off.
+    #[builder(default = "None")]
+    pub(crate) sample: Option<Sampling>,
+
+    /// Collapse runs of empty lines to at most `squeeze_limit` lines, like `cat -s`
+    #[builder(default = "false")]
+    pub(crate) squeeze_blank: bool,
+
+    /// Number of consecutive empty lines kept by `squeeze_blank`
+    #[builder(default = "1")]
+    pub(crate) squeeze_limit: usize,
+
+    /// Collapse runs of identical lines into one line with a repeat counter
+    #[builder(default = "false")]
+    pub(crate) dedup_repeats: bool,
+
+    /// Marker shown in the line number column of wrapped lines (e.g. `↪`), or in a column of
+    /// its own without line numbers. An empty string leaves the column blank.
+    #[builder(default = "String::new()")]
+    pub(crate) continuation_marker: String,
+
+    /// Whether to print the file a symbolic link points to, or only the link target
+    #[builder(default = "true")]
+    pub(crate) follow_symlinks: bool,
+
+    /// Whether to read FIFOs, devices and sockets, which are refused otherwise
+    #[builder(default = "false")]
+    pub(crate) allow_special_files: bool,
+
+    /// Regex which finds the timestamp in log lines (the first capture group, if any). If set,
+    /// the time elapsed since the previous timestamp is shown in the gutter.
+    #[builder(default = "String::new()")]
+    pub(crate) timestamp_pattern: String,
+
+    /// Whether vim and emacs modelines (`# vim: ft=yaml`, `-*- mode: ruby -*-`) select the
+    /// language. Off by default, so that the input doesn't pick its own highlighting.
+    #[builder(default = "false")]
+    pub(crate) modelines: bool,
+
+    /// Whether to set the title of the terminal window to the printed file while printing
+    #[builder(default = "false")]
+    pub(crate) set_terminal_title: bool,
+
+    /// Signal through the terminal when printing is complete
+    #[builder(default = "None")]
+    pub(crate) notify_on_complete: Option<Notify>,
+
+    /// Output format version the caller relies on (see `OUTPUT_FORMAT_VERSION`). Earlier
+    /// versions are rendered like they were, later ones fail to print.
+    #[builder(default = "None")]
+    pub(crate) output_format_version: Option<u32>,
 
     /// Pager or STDOUT
     #[builder(default = "PagingMode::QuitIfOneScreen")]
-    paging_mode: PagingMode,
+    pub(crate) paging_mode: PagingMode,
+
+    /// Fail if a rendered row is wider than this many columns, listing the offending lines
+    #[builder(default = "None")]
+    pub(crate) assert_max_width: Option<usize>,
+
+    /// Terminal output or one of the markup formats, like HTML
+    #[builder(default = "OutputFormat::Terminal")]
+    pub(crate) output_format: OutputFormat,
+
+    /// Whether HTML output is a standalone page or embedded into another page
+    #[builder(default = "HtmlWrapper::Page")]
+    pub(crate) html_wrapper: HtmlWrapper,
+
+    /// Whether HTML output is styled by attributes or by classes
+    #[builder(default = "HtmlStyle::Inline")]
+    pub(crate) html_style: HtmlStyle,
+
+    /// Whether HTML output keeps tabs (shown with `tab-size`) or expands them to spaces
+    #[builder(default = "HtmlTabs::Keep")]
+    pub(crate) html_tabs: HtmlTabs,
 
     /// Specifies the lines that should be printed
     #[builder(default)]
-    line_ranges: LineRanges,
+    pub(crate) line_ranges: LineRanges,
+
+    /// Print snip markers between the line ranges
+    #[builder(default = "false")]
+    pub(crate) snip: bool,
+
+    /// Only print the block of this definition, see `.symbol()`
+    #[builder(private, default)]
+    pub(crate) symbol_query: Option<String>,
+
+    /// Lines of context printed around the block of `.symbol()`
+    #[builder(default = "2")]
+    pub(crate) symbol_context: usize,
 
     /// The syntax highlighting theme
     #[builder(default = "String::from(PRETTYPRINT_THEME_DEFAULT)")]
-    theme: String,
+    pub(crate) theme: String,
+
+    /// The theme from `PRETTYPRINT_THEME`, replacing `theme` if it's in the theme set
+    #[builder(private, default)]
+    pub(crate) env_theme: Option<String>,
+
+    /// Themes loaded in addition to the integrated ones
+    #[builder(private, default)]
+    pub(crate) custom_themes: Vec<ThemeSource>,
+
+    /// Where to load the syntax and theme sets from, instead of prettyprint's cache
+    #[builder(private, default)]
+    pub(crate) asset_source: Option<AssetSource>,
+
+    /// Folders with syntaxes loaded in addition to the integrated ones
+    #[builder(private, default)]
+    pub(crate) syntax_folders: Vec<PathBuf>,
+
+    /// Themes for dark and light terminal backgrounds, replacing `theme`
+    #[builder(private, default)]
+    pub(crate) auto_themes: Option<(String, String)>,
+
+    /// Rendering without access to files, the terminal or the environment, see `render()`
+    #[builder(setter(skip), default = "false")]
+    pub(crate) detached: bool,
+
+    /// A theme resolved with `ThemeHandle::find`, replacing `theme` and `auto_themes`
+    #[builder(private, default)]
+    pub(crate) theme_handle: Option<ThemeHandle>,
 
     /// File extension/name mappings
     #[builder(default)]
-    syntax_mapping: SyntaxMapping,
+    pub(crate) syntax_mapping: SyntaxMapping,
+
+    /// Words given to `.emphasize_words()`
+    #[builder(private, default)]
+    pub(crate) emphasized_words: Vec<String>,
+
+    /// Patterns given to `.highlight_pattern()`
+    #[builder(private, default)]
+    pub(crate) highlight_patterns: Vec<String>,
+
+    /// Callback given to `.line_number_format()`
+    #[builder(private, default)]
+    pub(crate) line_number_formatter: Option<LineNumberFormat>,
+
+    /// Formats of the sizes and modification times in the header, see `.formatting()`
+    #[builder(private, default)]
+    pub(crate) formatting_hooks: Formatting,
+
+    /// Callback given to `.check_prose()`
+    #[builder(private, default)]
+    pub(crate) prose_checker: Option<ProseChecker>,
+
+    /// Callback given to `.line_filter()`
+    #[builder(private, default)]
+    pub(crate) line_filter_hook: Option<LineFilter>,
+
+    /// Rules added with `.link_rule()`
+    #[builder(private, default)]
+    pub(crate) link_rules: Vec<(String, String)>,
+
+    /// Mappings added with `.map_syntax()`
+    #[builder(private, default)]
+    pub(crate) syntax_patterns: Vec<(String, String)>,
 
     /// Command to start the pager
     #[builder(default = "None")]
-    pager: Option<String>,
+    pub(crate) pager: Option<String>,
 
     /// Whether to print some characters using italics
     #[builder(default = "false")]
-    use_italic_text: bool,
+    pub(crate) use_italic_text: bool,
+
+    /// Languages highlighted inside regions of other languages
+    #[builder(private, default)]
+    pub(crate) injections: Vec<Injection>,
+}
+
+impl PrettyPrinter {
+    /// A builder for printers which share the syntax and theme sets `assets`, instead of
+    /// loading their own. Syntaxes and themes added to a printer are added to a copy.
+    pub fn with_assets(assets: Arc<HighlightingAssets>) -> Self {
+        PrettyPrinter {
+            asset_source: Some(Some(AssetSource::Shared(assets))),
+            ..PrettyPrinter::default()
+        }
+    }
+
+    /// Builds the printer. Every option has a default, so this can't fail; options which turn
+    /// out to be unusable (e.g. an unknown encoding) are reported when printing.
+    pub fn build(&self) -> PrettyPrint {
+        self.try_build()
+            .unwrap_or_else(|error| unreachable!("every option has a default: {}", error))
+    }
+
+    /// Highlights the code blocks of `host` tagged with `language` (e.g. Markdown code fences
+    /// with the info string `rust` for `.inject("markdown", "rust")`), and the ones which have no
+    /// language of their own, as `language`. Unknown languages are ignored.
+    pub fn inject<T: Into<String>>(&mut self, host: T, language: T) -> &mut Self {
+        let (host, language) = (host.into(), language.into());
+        self.push_injection(Injection::code_fence(host.clone(), language.clone()));
+        self.inject_scope(host, DEFAULT_INJECTION_SELECTOR.to_string(), language)
+    }
+
+    /// Highlights all regions of `host` whose scopes match `selector` as `language`.
+    ///
+    /// For example, `.inject_scope("python", "string.quoted.double.block", "sql")` highlights
+    /// triple-quoted Python strings as SQL.
+    pub fn inject_scope<T: Into<String>>(
+        &mut self,
+        host: T,
+        selector: T,
+        language: T,
+    ) -> &mut Self {
+        self.push_injection(Injection::new(
+            host.into(),
+            selector.into(),
+            language.into(),
+        ))
+    }
+
+    /// Highlights the bodies of heredocs opened with `delimiter` (e.g. `<<SQL`) as `language`.
+    pub fn heredoc<T: Into<String>>(&mut self, delimiter: T, language: T) -> &mut Self {
+        self.push_injection(Injection::heredoc(delimiter.into(), language.into()))
+    }
+
+    /// Highlights template literals tagged with `tag` (e.g. ``sql`SELECT 1` ``) as `language`.
+    pub fn tagged_template<T: Into<String>>(&mut self, tag: T, language: T) -> &mut Self {
+        self.push_injection(Injection::tagged_template(tag.into(), language.into()))
+    }
+
+    /// Uses `dark_theme` or `light_theme`, depending on the background color of the terminal.
+    /// If the background can't be detected, `dark_theme` is used.
+    pub fn theme_auto<T: Into<String>>(&mut self, dark_theme: T, light_theme: T) -> &mut Self {
+        self.auto_themes = Some(Some((dark_theme.into(), light_theme.into())));
+        self
+    }
+
+    /// Uses a theme which was looked up once with `ThemeHandle::find`.
+    pub fn resolved_theme(&mut self, theme: ThemeHandle) -> &mut Self {
+        self.theme_handle = Some(Some(theme));
+        self
+    }
+
+    /// Loads a `.tmTheme` file, which can then be selected by its file name without extension
+    /// (e.g. `.theme("Corporate")` for `Corporate.tmTheme`).
+    pub fn add_theme_from_file<P: Into<PathBuf>>(&mut self, path: P) -> &mut Self {
+        self.push_theme(ThemeSource::File(path.into()))
+    }
+
+    /// Loads a theme named `name` from the XML contents of a `.tmTheme` file.
+    pub fn add_theme_from_str<T: Into<String>>(&mut self, name: T, xml: T) -> &mut Self {
+        self.push_theme(ThemeSource::Str {
+            name: name.into(),
+            xml: xml.into(),
+        })
+    }
+
+    /// Loads the syntax and theme sets from the `syntaxes.bin` and `themes.bin` in `dir`.
+    /// Printing fails if they can't be loaded.
+    pub fn asset_dir<P: Into<PathBuf>>(&mut self, dir: P) -> &mut Self {
+        self.asset_source = Some(Some(AssetSource::Path(dir.into())));
+        self
+    }
+
+    /// Reuses the syntax and theme sets cached by `bat` (e.g. after `bat cache --build`), if
+    /// there are any.
+    pub fn bat_assets(&mut self) -> &mut Self {
+        self.asset_source = Some(Some(AssetSource::Bat));
+        self
+    }
+
+    /// Loads the `.sublime-syntax` files in a folder, so that languages which aren't integrated
+    /// can be highlighted.
+    pub fn add_syntax_from_folder<P: Into<PathBuf>>(&mut self, path: P) -> &mut Self {
+        self.syntax_folders
+            .get_or_insert_with(Vec::new)
+            .push(path.into());
+        self
+    }
+
+    /// Adds a theme defined in code, which can then be selected by its name.
+    pub fn add_theme_from_builder(&mut self, theme: ThemeBuilder) -> &mut Self {
+        self.push_theme(ThemeSource::Builder(theme))
+    }
+
+    /// Highlights files matching a glob pattern (`*.conf`, `Dockerfile.*`), a file name or an
+    /// extension with the given language, e.g. `.map_syntax("*.conf", "INI")`.
+    pub fn map_syntax<S: Into<String>>(&mut self, from: S, language: S) -> &mut Self {
+        self.syntax_patterns
+            .get_or_insert_with(Vec::new)
+            .push((from.into(), language.into()));
+        self
+    }
+
+    /// Emphasizes whole words like `TODO` or `unsafe` wherever they occur, regardless of the
+    /// syntax highlighting.
+    pub fn emphasize_words<S: AsRef<str>>(&mut self, words: &[S]) -> &mut Self {
+        self.emphasized_words
+            .get_or_insert_with(Vec::new)
+            .extend(words.iter().map(|word| word.as_ref().to_string()));
+        self
+    }
+
+    /// Highlights every match of the regex `pattern` with its own background (or an underline,
+    /// if the theme has no color for it), over the syntax highlighting, like `grep --color`.
+    pub fn highlight_pattern<S: Into<String>>(&mut self, pattern: S) -> &mut Self {
+        self.highlight_patterns
+            .get_or_insert_with(Vec::new)
+            .push(pattern.into());
+        self
+    }
+
+    /// Links matches of `pattern` to the URL `template`, which may refer to capture groups,
+    /// e.g. `.link_rule(r"JIRA-\d+", "https://jira.example.com/browse/$0")`. The links are
+    /// shown like URLs, see `.underline_urls()` and `.url_hyperlinks()`.
+    pub fn link_rule<S: Into<String>>(&mut self, pattern: S, template: S) -> &mut Self {
+        self.link_rules
+            .get_or_insert_with(Vec::new)
+            .push((pattern.into(), template.into()));
+        self
+    }
+
+    /// Passes the text of every comment and string to `check`, which returns the byte ranges of
+    /// that text to underline, e.g. the misspelled words found by a spellchecker.
+    pub fn check_prose<F>(&mut self, check: F) -> &mut Self
+    where
+        F: Fn(&str) -> Vec<Range<usize>> + Send + Sync + 'static,
+    {
+        self.prose_checker = Some(Some(ProseChecker(Arc::new(check))));
+        self
+    }
+
+    /// Passes the number (as shown) and the text of every line to `filter` before it is
+    /// highlighted, which keeps the line, skips it, replaces it or masks parts of it, e.g. the
+    /// secrets in a config file.
+    pub fn line_filter<F>(&mut self, filter: F) -> &mut Self
+    where
+        F: Fn(usize, &str) -> LineAction + Send + Sync + 'static,
+    {
+        self.line_filter_hook = Some(Some(LineFilter(Arc::new(filter))));
+        self
+    }
+
+    /// Makes each line number an OSC 8 hyperlink, e.g. to open the line in an editor with
+    /// `vscode://file{path}:{line}`. `{path}` is replaced by the absolute path of the file, as in
+    /// `file://` URLs, and `{line}` by the line number.
+    pub fn line_number_links<S: Into<String>>(&mut self, template: S) -> &mut Self {
+        self.line_link_template = Some(Some(template.into()));
+        self
+    }
+
+    /// Formats the line numbers in the gutter, e.g. `.line_number_format(|n| format!("{:04x}", n))`.
+    pub fn line_number_format<F>(&mut self, format: F) -> &mut Self
+    where
+        F: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        self.line_number_formatter = Some(Some(LineNumberFormat(Arc::new(format))));
+        self
+    }
+
+    /// Formats the numbers and dates of the decorations with the hooks of `formatting`, e.g. to
+    /// localize them. Its line number format replaces the one of `.line_number_format()`.
+    pub fn formatting(&mut self, formatting: Formatting) -> &mut Self {
+        if let Some(ref format) = formatting.line_number {
+            self.line_number_formatter = Some(Some(format.clone()));
+        }
+        self.formatting_hooks = Some(formatting);
+        self
+    }
+
+    /// Only prints the block of a definition like `fn parse_config` or `class Parser`, found by
+    /// its first occurrence in the file, instead of asking for line numbers.
+    pub fn symbol<S: Into<String>>(&mut self, definition: S) -> &mut Self {
+        self.symbol_query = Some(Some(definition.into()));
+        self
+    }
+
+    /// Decodes the input with an encoding other than UTF-8, given by its WHATWG label, e.g.
+    /// `latin1`, `windows-1252`, `shift_jis` or `euc-jp`. UTF-16 is detected by its BOM.
+    pub fn encoding<S: Into<String>>(&mut self, label: S) -> &mut Self {
+        self.encoding_label = Some(Some(label.into()));
+        self
+    }
+
+    /// Asks `provider` for the width at each print, instead of the terminal on stdout. A width
+    /// set with `.term_width()` takes precedence.
+    pub fn width_provider<P: WidthProvider + 'static>(&mut self, provider: P) -> &mut Self {
+        self.width_source = Some(Some(Arc::new(provider)));
+        self
+    }
+
+    /// Integrates the output with the log of a CI service, e.g. `GitHubActions`, `GitLabCi` or
+    /// `TeamCity`. `Preset::Ci` detects the service.
+    pub fn ci_emitter<E: CiEmitter + 'static>(&mut self, emitter: E) -> &mut Self {
+        self.emitter = Some(Some(Arc::new(emitter)));
+        self
+    }
+
+    /// Reports an error at a line of `file` to the CI service, as an annotation if it supports
+    /// them. It's only reported when `file` is printed; inputs which aren't files go by the
+    /// title of their group, e.g. `STDIN`.
+    pub fn ci_error<S: Into<String>>(&mut self, file: S, line: usize, message: S) -> &mut Self {
+        self.ci_errors
+            .get_or_insert_with(Vec::new)
+            .push((file.into(), line, message.into()));
+        self
+    }
+
+    /// Adds a column to the gutter, behind the line numbers.
+    pub fn decoration<D: Decoration + 'static>(&mut self, decoration: D) -> &mut Self {
+        self.custom_decorations
+            .get_or_insert_with(Vec::new)
+            .push(Arc::new(decoration));
+        self
+    }
+
+    /// Adds a stage to the preprocessing of each line before it is highlighted. The stages run
+    /// in the order they are added, after `strip_ansi` and before `show_nonprintable`.
+    pub fn preprocessor<P: Preprocessor + 'static>(&mut self, preprocessor: P) -> &mut Self {
+        self.preprocessors
+            .get_or_insert_with(Vec::new)
+            .push(Arc::new(preprocessor));
+        self
+    }
+
+    /// Prints a message beneath a span of a line, e.g. a compiler diagnostic.
+    pub fn annotate(&mut self, annotation: Annotation) -> &mut Self {
+        self.annotations
+            .get_or_insert_with(Vec::new)
+            .push(annotation);
+        self
+    }
+
+    /// Sets the options of a preset. Options set afterwards override them.
+    pub fn preset(&mut self, preset: Preset) -> &mut Self {
+        match preset {
+            Preset::Ci => {
+                self.paging_mode = Some(PagingMode::Never);
+                self.term_width = Some(Some(CI_TERM_WIDTH));
+                self.true_color = Some(false);
+                self.force_colors = Some(true);
+                self.ascii_grid = Some(true);
+                self.emitter = Some(ci::detect());
+            }
+            Preset::ScreenReader => {
+                self.paging_mode = Some(PagingMode::Never);
+                self.colored_output = Some(Some(false));
+                self.grid = Some(false);
+                self.ascii_grid = Some(true);
+                self.line_numbers = Some(true);
+                self.line_number_formatter =
+                    Some(Some(LineNumberFormat(Arc::new(|n| format!("line {}:", n)))));
+                self.output_wrap = Some(OutputWrap::None);
+                self.overview = Some(false);
+                self.minimap = Some(false);
+            }
+        }
+        self
+    }
+
+    /// Takes defaults for options which weren't set (yet) from the environment, so that users
+    /// of an application can customize it:
+    ///
+    /// * `PRETTYPRINT_THEME`: the theme, if it's in the theme set when printing
+    /// * `PRETTYPRINT_STYLE`: comma separated components, e.g. `numbers,grid` or `plain`
+    /// * `PRETTYPRINT_TABS`: the tab width
+    /// * `PRETTYPRINT_PAGER`: the pager command
+    ///
+    /// Invalid values are ignored.
+    pub fn env_defaults(&mut self, enable: bool) -> &mut Self {
+        if enable {
+            self.apply_env_defaults(|name| env::var(name).ok());
+        }
+        self
+    }
+
+    fn apply_env_defaults<F: Fn(&str) -> Option<String>>(&mut self, var: F) {
+        if self.theme.is_none() && self.auto_themes.is_none() && self.theme_handle.is_none() {
+            self.env_theme = Some(var("PRETTYPRINT_THEME"));
+        }
+        if self.tab_width.is_none() {
+            self.tab_width = var("PRETTYPRINT_TABS").and_then(|tabs| tabs.parse().ok());
+        }
+        if self.pager.is_none() {
+            self.pager = var("PRETTYPRINT_PAGER").map(Some);
+        }
+
+        let style = var("PRETTYPRINT_STYLE").and_then(|style| {
+            style
+                .split(',')
+                .map(|component| component.trim().parse())
+                .collect::<Result<HashSet<OutputComponent>>>()
+                .ok()
+        });
+        let components = match style {
+            Some(ref components) if !components.contains(&OutputComponent::Auto) => components,
+            _ => return,
+        };
+        let full = components.contains(&OutputComponent::Full);
+        let shown = |component| full || components.contains(&component);
+
+        self.grid = self.grid.or(Some(shown(OutputComponent::Grid)));
+        self.header = self.header.or(Some(shown(OutputComponent::Header)));
+        self.line_numbers = self.line_numbers.or(Some(shown(OutputComponent::Numbers)));
+        self.changes = self.changes.or(Some(shown(OutputComponent::Changes)));
+        let size = shown(OutputComponent::Size);
+        self.header_size = self.header_size.or(Some(size));
+        let modified = shown(OutputComponent::Modified);
+        self.header_modified = self.header_modified.or(Some(modified));
+        let permissions = shown(OutputComponent::Permissions);
+        self.header_permissions = self.header_permissions.or(Some(permissions));
+    }
+
+    fn push_theme(&mut self, source: ThemeSource) -> &mut Self {
+        self.custom_themes.get_or_insert_with(Vec::new).push(source);
+        self
+    }
+
+    fn push_injection(&mut self, injection: Injection) -> &mut Self {
+        self.injections.get_or_insert_with(Vec::new).push(injection);
+        self
+    }
 }
 
 impl PrettyPrint {
@@ -135,112 +928,1322 @@ impl PrettyPrint {
         self.run_controller(InputFile::String(input.into()), Some(header.into()))
     }
 
-    /// List all available themes for syntax highlighting
-    pub fn get_themes(&self) -> BTreeMap<String, Theme> {
-        let assets = HighlightingAssets::new();
-        assets.theme_set.themes
+    /// Prints a byte slice.
+    ///
+    /// UTF-8 content is highlighted like a string, binary content is
+    /// shown as a hexdump with byte offsets in the gutter, see `.binary_hexdump()`.
+    pub fn bytes(mut self, input: &[u8]) -> Result<()> {
+        self.binary_hexdump = true;
+        self.run_controller(InputFile::Bytes(input.to_vec()), None)
     }
 
-    fn run_controller(
-        &self,
-        input_file: InputFile,
-        header_overwrite: Option<String>,
-    ) -> Result<()> {
+    /// Prints two files side by side, with the changes from the first to the second marked: `-`
+    /// and `+` for removed and added lines, `~` for changed ones.
+    pub fn diff<A: Into<String>, B: Into<String>>(self, path_a: A, path_b: B) -> Result<()> {
         #[cfg(windows)]
         let _ = ansi_term::enable_ansi_support();
-        // let interactive_output = atty::is(Stream::Stdout);
-
-        let assets = HighlightingAssets::new();
-        let mut reader = input_file.get_reader()?;
-
-        let lang_opt = match self.language.as_ref() {
-            "unknown" => None,
-            s => Some(s.to_string()),
-        };
 
-        // This is faaaar from ideal, I know.
-        let mut printer = InteractivePrinter::new(
-            &assets,
-            &input_file,
-            &mut reader,
-            self.get_output_components(),
-            self.theme.clone(),
-            self.colored_output,
-            self.true_color,
-            self.term_width,
-            lang_opt,
-            self.syntax_mapping.clone(),
-            self.tab_width,
-            self.show_nonprintable,
-            self.output_wrap,
-            self.use_italic_text,
-        );
+        let files = [
+            InputFile::Ordinary(path_a.into()),
+            InputFile::Ordinary(path_b.into()),
+        ];
+        for file in &files {
+            file.check(self.allow_special_files)?;
+        }
 
         let mut output_type = OutputType::from_mode(self.paging_mode, self.pager.clone())?;
         let writer = output_type.handle()?;
+        self.render_side_by_side(&files, writer)
+    }
 
-        self.print_file(reader, &mut printer, writer, &input_file, header_overwrite)?;
-        Ok(())
+    /// Prints the line of a file which contains `byte_offset`, with `context_lines` lines
+    /// around it and a caret under the character at the offset, e.g. for the errors of a parser.
+    pub fn snippet<T: Into<String>>(
+        self,
+        path: T,
+        byte_offset: usize,
+        context_lines: usize,
+    ) -> Result<()> {
+        let path = path.into();
+        let printer = self.snippet_printer(&path, byte_offset, context_lines)?;
+        printer.file(path)
     }
 
-    fn get_output_components(&self) -> OutputComponents {
-        let mut components = HashSet::new();
-        if self.grid {
-            components.insert(OutputComponent::Grid);
-        }
-        if self.header {
-            components.insert(OutputComponent::Header);
-        }
-        if self.line_numbers {
-            components.insert(OutputComponent::Numbers);
-        }
-        OutputComponents(components)
+    /// A printer for the lines of `snippet` with the caret, as an annotation.
+    pub(crate) fn snippet_printer(
+        &self,
+        path: &str,
+        byte_offset: usize,
+        context_lines: usize,
+    ) -> Result<PrettyPrint> {
+        let content = fs::read(path).map_err(|error| unreadable(path, error))?;
+        let (line, column) = locate(&content, byte_offset).ok_or_else(|| {
+            format!(
+                "Byte offset {} is beyond the end of '{}'",
+                byte_offset, path
+            )
+        })?;
+
+        let mut annotations = self.annotations.clone();
+        annotations.push(Annotation::new(
+            line + self.first_line_number - 1,
+            column..column + 1,
+            "",
+            Level::Error,
+        ));
+        Ok(PrettyPrint {
+            line_ranges: LineRanges::from(vec![LineRange {
+                lower: line.saturating_sub(context_lines).max(1),
+                upper: line + context_lines,
+            }]),
+            annotations,
+            ..self.clone()
+        })
+    }
+
+    /// Prints the lines of a file which match the regex `pattern`, with `context` lines around
+    /// them, like `grep -C`. The matches are highlighted, and snip markers separate the groups
+    /// of lines.
+    pub fn grep<T: Into<String>>(self, path: T, pattern: &str, context: usize) -> Result<()> {
+        let path = path.into();
+        let printer = self.grep_printer(&path, pattern, context)?;
+        printer.file(path)
     }
 
-    fn print_file<'a, P: Printer>(
+    /// A printer for the lines of `grep`, with the matches highlighted.
+    pub(crate) fn grep_printer(
+        &self,
+        path: &str,
+        pattern: &str,
+        context: usize,
+    ) -> Result<PrettyPrint> {
+        let regex = Regex::new(pattern)
+            .map_err(|error| format!("Invalid grep pattern '{}': {}", pattern, error))?;
+        let content = fs::read(path).map_err(|error| unreadable(path, error))?;
+        let ranges: Vec<LineRange> = String::from_utf8_lossy(&content)
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| regex.is_match(line))
+            .map(|(index, _)| LineRange {
+                lower: (index + 1).saturating_sub(context).max(1),
+                upper: index + 1 + context,
+            })
+            .collect();
+        if ranges.is_empty() {
+            return Err(format!("Pattern '{}' not found in '{}'", pattern, path).into());
+        }
+
+        let mut highlight_patterns = self.highlight_patterns.clone();
+        highlight_patterns.push(pattern.to_string());
+        Ok(PrettyPrint {
+            line_ranges: LineRanges::from(ranges),
+            snip: true,
+            highlight_patterns,
+            ..self.clone()
+        })
+    }
+
+    /// Prints a file with a printer of the application, e.g. one which emits JSON, into the
+    /// pager if there is one. The printer gets the lines of the file (following the line
+    /// ranges, the sampling and the other options of the read loop) as bytes.
+    pub fn print_with<P: Printer>(&self, input_file: InputFile, printer: &mut P) -> Result<()> {
+        self.run_controller_with(input_file, None, |input_file, header, writer| {
+            self.render_with(input_file, printer, writer, header)
+        })
+    }
+
+    /// Renders a file with a printer of the application into `writer`, e.g. for a GUI.
+    pub fn render_with<P: Printer>(
+        &self,
+        input_file: &InputFile,
+        printer: &mut P,
+        writer: &mut dyn Write,
+        header_overwrite: Option<String>,
+    ) -> Result<()> {
+        let reader = input_file.get_reader()?;
+        self.print_file(reader, printer, writer, input_file, header_overwrite)
+    }
+
+    /// Renders a file into a string instead of printing it, e.g. an HTML page.
+    pub fn render_file_to_string<T: Into<String>>(&self, filename: T) -> Result<String> {
+        self.render_to_string(&InputFile::Ordinary(filename.into()))
+    }
+
+    /// Renders a string into a string instead of printing it.
+    pub fn render_string<T: Into<String>>(&self, input: T) -> Result<String> {
+        self.render_to_string(&InputFile::String(input.into()))
+    }
+
+    fn render_to_string(&self, input_file: &InputFile) -> Result<String> {
+        let mut output = Vec::new();
+        self.render(input_file, None, &mut output)?;
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+
+    /// The styled regions of each line of `content`, highlighted in `language` (e.g. `rust` or
+    /// `rs`) with the theme and the injections, for renderers of the application like TUI
+    /// widgets. Lines keep their line break.
+    pub fn highlight_regions(
+        &self,
+        content: &str,
+        language: &str,
+    ) -> Result<impl Iterator<Item = Vec<(Style, String)>>> {
+        let language = Language::find(self, language)?;
+        let assets = self.get_language_assets(vec![language.name().to_string()])?;
+        let syntax = assets
+            .syntax_set
+            .find_syntax_by_name(language.name())
+            .ok_or_else(|| format!("Unknown language '{}'", language.name()))?;
+        let theme = self.get_theme(&assets);
+
+        let mut highlighter =
+            LineHighlighter::new(syntax, theme, &self.injections, &assets.syntax_set);
+        let lines: Vec<Vec<(Style, String)>> = content
+            .split_inclusive('\n')
+            .map(|line| {
+                highlighter
+                    .highlight(line, &assets.syntax_set)
+                    .into_iter()
+                    .map(|(style, text)| (style, text.to_string()))
+                    .collect()
+            })
+            .collect();
+        Ok(lines.into_iter())
+    }
+
+    /// The stylesheet for HTML output with `HtmlStyle::Classes`, with the colors of the theme.
+    pub fn theme_stylesheet(&self) -> Result<String> {
+        let assets = self.get_assets()?;
+        let theme = self.get_theme(&assets);
+        Ok(
+            html::stylesheet(theme, self.html_tabs.tab_size(self.tab_width), false)
+                + &html::class_rules(theme),
+        )
+    }
+
+    /// List all available themes for syntax highlighting, including custom themes which could
+    /// be loaded
+    pub fn get_themes(&self) -> BTreeMap<String, Theme> {
+        let themes = if self.uses_integrated_base() {
+            HighlightingAssets::split().themes()
+        } else {
+            let assets = self
+                .get_base_assets()
+                .unwrap_or_else(|_| HighlightingAssets::cached());
+            assets.theme_set.themes.clone()
+        };
+        // Only the themes are copied, to add the custom ones to them.
+        let mut themes = HighlightingAssets {
+            syntax_set: SyntaxSet::new(),
+            theme_set: ThemeSet { themes },
+        };
+        for source in &self.custom_themes {
+            themes.add_theme_source(source).ok();
+        }
+        themes.theme_set.themes
+    }
+
+    /// List all languages available for syntax highlighting, including the ones of custom
+    /// syntaxes which could be loaded
+    pub fn get_languages(&self) -> Vec<LanguageInfo> {
+        let syntax_mapping = self
+            .get_syntax_mapping()
+            .unwrap_or_else(|_| self.syntax_mapping.clone());
+        if self.syntax_folders.is_empty() && self.uses_integrated_base() {
+            return HighlightingAssets::split()
+                .index()
+                .get_languages(&syntax_mapping);
+        }
+        let assets = self
+            .get_base_assets()
+            .unwrap_or_else(|_| HighlightingAssets::cached());
+        if self.syntax_folders.is_empty() {
+            return assets.get_languages(&syntax_mapping);
+        }
+        // Only the syntaxes are copied to add the custom ones to them.
+        let mut syntaxes = HighlightingAssets {
+            syntax_set: assets.syntax_set.clone(),
+            theme_set: ThemeSet::default(),
+        };
+        for folder in &self.syntax_folders {
+            syntaxes.add_syntax_from_folder(folder).ok();
+        }
+        syntaxes.get_languages(&syntax_mapping)
+    }
+
+    /// The syntax mapping including the mappings added with `.map_syntax()`.
+    pub(crate) fn get_syntax_mapping(&self) -> Result<SyntaxMapping> {
+        let mut syntax_mapping = self.syntax_mapping.clone();
+        for (from, to) in &self.syntax_patterns {
+            syntax_mapping.insert_pattern(from, to.clone())?;
+        }
+        Ok(syntax_mapping)
+    }
+
+    /// The assets without the syntaxes and themes added to the printer. The default ones are
+    /// loaded once, when they're first used.
+    fn get_base_assets(&self) -> Result<Arc<HighlightingAssets>> {
+        match self.asset_source {
+            Some(AssetSource::Shared(ref assets)) => Ok(Arc::clone(assets)),
+            None if self.detached => Ok(HighlightingAssets::integrated()),
+            None => Ok(HighlightingAssets::cached()),
+            Some(_) if self.detached => Err(DETACHED_ASSETS.into()),
+            Some(AssetSource::Path(ref dir)) => HighlightingAssets::from_dir(dir).map(Arc::new),
+            Some(AssetSource::Bat) => Ok(BAT_CACHE_DIR
+                .as_ref()
+                .and_then(|dir| HighlightingAssets::from_dir(dir).ok())
+                .map(Arc::new)
+                .unwrap_or_else(HighlightingAssets::cached)),
+        }
+    }
+
+    /// Whether the assets are the integrated ones, before adding the syntaxes and themes of the
+    /// printer. Then only the syntaxes and themes which are used get loaded.
+    fn uses_integrated_base(&self) -> bool {
+        self.asset_source.is_none()
+            && (self.detached || HighlightingAssets::cached_from_dir().is_none())
+    }
+
+    /// Whether the assets are the integrated ones, without syntaxes or themes added.
+    pub(crate) fn uses_integrated_assets(&self) -> bool {
+        self.uses_integrated_base()
+            && self.syntax_folders.is_empty()
+            && self.custom_themes.is_empty()
+    }
+
+    /// The assets for highlighting in the languages named `languages`. Of the integrated
+    /// assets, only their syntaxes (with the ones of the injections) and the themes of the
+    /// printer get loaded. Diffs need all of them, for the files they change.
+    fn get_language_assets(&self, mut languages: Vec<String>) -> Result<Arc<HighlightingAssets>> {
+        if !self.uses_integrated_assets() || languages.iter().any(|name| name == DIFF_SYNTAX) {
+            return self.get_assets();
+        }
+
+        let split = HighlightingAssets::split();
+        let syntax_set = &split.index().syntax_set;
+        for injection in &self.injections {
+            let tokens = injection.host.iter().chain(iter::once(&injection.language));
+            languages.extend(
+                tokens
+                    .filter_map(|token| syntax_set.find_syntax_by_token(token))
+                    .map(|syntax| syntax.name.clone()),
+            );
+        }
+        let mut themes = vec![self.theme_name()];
+        themes.extend(self.env_theme.as_deref());
+        Ok(split.subset(&languages, &themes))
+    }
+
+    /// The assets for printing `input_file`, with the syntax it's highlighted in, see
+    /// `get_language_assets()`.
+    fn get_file_assets(
+        &self,
+        input_file: &InputFile,
+        reader: &mut InputFileReader,
+    ) -> Result<Arc<HighlightingAssets>> {
+        if !self.uses_integrated_assets() {
+            return self.get_assets();
+        }
+        let syntax = HighlightingAssets::split().index().get_syntax(
+            self.syntax_language(),
+            input_file,
+            reader,
+            &self.get_syntax_mapping()?,
+            self.modelines,
+        );
+        self.get_language_assets(vec![syntax.name.clone()])
+    }
+
+    /// The language to highlight in instead of detecting it, if any.
+    pub(crate) fn syntax_language(&self) -> Option<String> {
+        match self.language.as_ref() {
+            _ if self.diff_mode => Some(DIFF_SYNTAX.to_string()),
+            "unknown" => None,
+            s => Some(s.to_string()),
+        }
+    }
+
+    pub(crate) fn get_assets(&self) -> Result<Arc<HighlightingAssets>> {
+        let theme_files = self
+            .custom_themes
+            .iter()
+            .any(|source| matches!(source, ThemeSource::File(_)));
+        if self.detached && (theme_files || !self.syntax_folders.is_empty()) {
+            return Err(DETACHED_ASSETS.into());
+        }
+
+        let assets = self.get_base_assets()?;
+        if self.syntax_folders.is_empty() && self.custom_themes.is_empty() {
+            return Ok(assets);
+        }
+        let mut assets = Arc::unwrap_or_clone(assets);
+        for folder in &self.syntax_folders {
+            assets.add_syntax_from_folder(folder)?;
+        }
+        for source in &self.custom_themes {
+            assets.add_theme_source(source)?;
+        }
+        Ok(Arc::new(assets))
+    }
+
+    fn run_controller(
+        &self,
+        input_file: InputFile,
+        header_overwrite: Option<String>,
+    ) -> Result<()> {
+        self.run_controller_with(
+            input_file,
+            header_overwrite,
+            |input_file, header, writer| self.render(input_file, header, writer),
+        )
+    }
+
+    /// Prints a file with the `render` function, into the pager if there is one.
+    fn run_controller_with<F>(
+        &self,
+        input_file: InputFile,
+        header_overwrite: Option<String>,
+        render: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(&InputFile, Option<String>, &mut dyn Write) -> Result<()>,
+    {
+        #[cfg(windows)]
+        let _ = ansi_term::enable_ansi_support();
+
+        let version = self.output_version();
+        if !(1..=OUTPUT_FORMAT_VERSION).contains(&version) {
+            return Err(format!(
+                "Output format version {} is not supported, only versions 1 to {} are.",
+                version, OUTPUT_FORMAT_VERSION
+            )
+            .into());
+        }
+
+        // Unless symlinks are followed, only the link target gets printed.
+        let (input_file, header_overwrite) = match (input_file.symlink_target()?, &input_file) {
+            (Some(target), InputFile::Ordinary(link)) if !self.follow_symlinks => (
+                InputFile::String(format!("{}\n", target.display())),
+                Some(format!(
+                    "{} (symbolic link)",
+                    header_overwrite.unwrap_or_else(|| link.clone())
+                )),
+            ),
+            _ => (input_file, header_overwrite),
+        };
+        input_file.check(self.allow_special_files)?;
+
+        let title = match (&header_overwrite, &input_file) {
+            (Some(title), _) | (None, InputFile::Ordinary(title)) => Some(title.clone()),
+            _ => None,
+        };
+
+        // Declared before the output, so that the title is only restored once the pager quit.
+        let _terminal_title = match title {
+            Some(ref title) if self.set_terminal_title => TerminalTitle::set(title),
+            _ => None,
+        };
+        let mut output_type = OutputType::from_mode(self.paging_mode, self.pager.clone())?;
+        let writer = output_type.handle()?;
+
+        if self.streaming {
+            let mut writer = BufWriter::with_capacity(STREAM_BUFFER_BYTES, writer);
+            render(&input_file, header_overwrite, &mut writer)?;
+            writer.flush()?;
+        } else {
+            render(&input_file, header_overwrite, writer)?;
+        }
+
+        // Waits for the pager to quit.
+        drop(output_type);
+        if let Some(notify) = self.notify_on_complete {
+            notify.send(&title.unwrap_or_else(|| "STDIN".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Renders a file into `writer`.
+    pub(crate) fn render(
+        &self,
+        input_file: &InputFile,
+        header_overwrite: Option<String>,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        #[cfg(feature = "notebook")]
+        {
+            if let InputFile::Ordinary(ref filename) = input_file {
+                if notebook::is_notebook(filename) {
+                    return self.print_notebook(&Notebook::from_path(filename)?, writer);
+                }
+            }
+        }
+
+        // The groups of the CI log would add lines, see `faithful()`.
+        let emitter = self.emitter.as_ref().filter(|_| !self.copy_fidelity);
+        if let Some(emitter) = emitter {
+            let title = match (&header_overwrite, input_file) {
+                (Some(title), _) | (None, InputFile::Ordinary(title)) => title.clone(),
+                (None, InputFile::StdIn) => "STDIN".to_string(),
+                (None, _) => "Output".to_string(),
+            };
+            writer.write_all(emitter.start_group(&title).as_bytes())?;
+            let result = self.render_file(input_file, header_overwrite, writer);
+            writer.write_all(emitter.end_group(&title).as_bytes())?;
+
+            let file = match input_file {
+                InputFile::Ordinary(file) => Some(file.as_str()),
+                _ => None,
+            };
+            let errors = self
+                .ci_errors
+                .iter()
+                .filter(|(path, _, _)| *path == file.unwrap_or(&title));
+            for (_, line, message) in errors {
+                writer.write_all(emitter.error(file, *line, message).as_bytes())?;
+            }
+            return result;
+        }
+
+        self.render_file(input_file, header_overwrite, writer)
+    }
+
+    fn render_file(
+        &self,
+        input_file: &InputFile,
+        header_overwrite: Option<String>,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        let faithful;
+        let config = if self.copy_fidelity {
+            faithful = self.faithful();
+            &faithful
+        } else {
+            self
+        };
+        let streamed;
+        let config = if config.streaming {
+            streamed = config.streamed()?;
+            &streamed
+        } else {
+            config
+        };
+
+        if config.loop_through {
+            return copy_lines(
+                input_file.get_reader()?,
+                writer,
+                config.line_filter_hook.as_ref(),
+                config.first_line_number,
+            );
+        }
+
+        let mut reader = input_file.get_reader()?;
+        if config.streaming {
+            reader.max_line_length = Some(STREAM_LINE_BYTES);
+        }
+        let assets = config.get_file_assets(input_file, &mut reader)?;
+
+        let mut printer = InteractivePrinter::new(config, &assets, input_file, &mut reader)?;
+
+        if config.output_format == OutputFormat::Html {
+            let mut printer = HtmlPrinter::new(printer);
+            config.print_file(reader, &mut printer, writer, input_file, header_overwrite)
+        } else if config.output_format == OutputFormat::Svg {
+            let mut printer = SvgPrinter::new(printer);
+            config.print_file(reader, &mut printer, writer, input_file, header_overwrite)
+        } else if config.output_format == OutputFormat::Latex {
+            let mut printer = LatexPrinter::new(printer);
+            config.print_file(reader, &mut printer, writer, input_file, header_overwrite)
+        } else if config.output_format == OutputFormat::Rtf {
+            let mut printer = RtfPrinter::new(printer);
+            config.print_file(reader, &mut printer, writer, input_file, header_overwrite)
+        } else if config.output_format == OutputFormat::BbCode {
+            let mut printer = BbCodePrinter::new(printer);
+            config.print_file(reader, &mut printer, writer, input_file, header_overwrite)
+        } else if config.output_format == OutputFormat::Json {
+            let mut printer = JsonPrinter::new(printer);
+            config.print_file(reader, &mut printer, writer, input_file, header_overwrite)
+        } else if config.shows_hexdump(reader.content_type) {
+            // Lines without an end, as binary content may have, are read up to the limit only.
+            if let Some(limit) = config.binary_preview_bytes {
+                reader.max_line_length = reader.max_line_length.or(Some(limit.max(1)));
+            }
+            let mut printer = HexdumpPrinter::new(printer);
+            config.print_file(reader, &mut printer, writer, input_file, header_overwrite)
+        } else {
+            config.print_file(reader, &mut printer, writer, input_file, header_overwrite)
+        }
+    }
+
+    /// Renders two files in two columns, each highlighted like a file of its own but cut at the
+    /// width of its column, in rows along the changes.
+    pub(crate) fn render_side_by_side(
+        &self,
+        files: &[InputFile; 2],
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        if self.output_format != OutputFormat::Terminal {
+            return Err("Files can only be shown side by side on the terminal".into());
+        }
+        let mut lines = Vec::with_capacity(files.len());
+        for file in files {
+            let reader = file.get_reader()?;
+            if reader.content_type.is_binary() {
+                return Err("Binary files can't be shown side by side".into());
+            }
+            let file_lines: Vec<String> = read_lines(reader)?
+                .iter()
+                .map(|line| {
+                    String::from_utf8_lossy(line)
+                        .trim_end_matches(&['\n', '\r'][..])
+                        .to_string()
+                })
+                .collect();
+            lines.push(file_lines);
+        }
+        let rows = side_by_side::pair_lines(&lines[0], &lines[1]);
+
+        let mut columns = Columns::new(
+            self.current_width(),
+            lines[0].len().max(lines[1].len()) + self.first_line_number - 1,
+            self.line_numbers,
+        );
+        let assets = self.get_assets()?;
+        columns.grid_chars = self.get_grid_charset();
+        columns.grid = self.grid;
+        columns.colored_output = self.use_colors();
+        columns.colors = if columns.colored_output {
+            Colors::colored(self.get_theme(&assets), self.true_color)
+        } else {
+            Colors::plain()
+        };
+
+        // Every line of the files becomes exactly one row.
+        let side = PrettyPrint {
+            header: false,
+            grid: false,
+            line_numbers: false,
+            changes: false,
+            custom_decorations: Vec::new(),
+            annotations: Vec::new(),
+            footer: None,
+            output_wrap: OutputWrap::Truncate,
+            term_width: Some(columns.text_width),
+            copy_fidelity: false,
+            use_background: false,
+            overview: false,
+            minimap: false,
+            literate: false,
+            table_of_contents: false,
+            identifier_index: false,
+            transforms: Vec::new(),
+            sample: None,
+            snip: false,
+            squeeze_blank: false,
+            dedup_repeats: false,
+            timestamp_pattern: String::new(),
+            line_ranges: LineRanges::default(),
+            symbol_query: None,
+            highlight_threads: 1,
+            streaming: false,
+            frame_title: false,
+            line_filter_hook: None,
+            ..self.clone()
+        };
+        let mut rendered = Vec::with_capacity(files.len());
+        for file in files {
+            let mut output = Vec::new();
+            side.render_file(file, None, &mut output)?;
+            let file_rows: Vec<String> = String::from_utf8_lossy(&output)
+                .split('\n')
+                .map(str::to_string)
+                .collect();
+            rendered.push(file_rows);
+        }
+
+        let grid = self.grid;
+        if self.header {
+            if grid {
+                writer.write_all(columns.rule(columns.grid_chars.horizontal).as_bytes())?;
+            }
+            let name = |file: &InputFile| match file {
+                InputFile::Ordinary(name) => name.clone(),
+                _ => String::new(),
+            };
+            let header = columns.header(&name(&files[0]), &name(&files[1]));
+            writer.write_all(header.as_bytes())?;
+        }
+        if grid {
+            writer.write_all(columns.rule(columns.grid_chars.top_cross).as_bytes())?;
+        }
+        for row in rows {
+            let row = columns.row(row, &rendered[0], &rendered[1], self.first_line_number);
+            writer.write_all(row.as_bytes())?;
+        }
+        if grid {
+            writer.write_all(columns.rule(columns.grid_chars.bottom_cross).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// The width for a print: `.term_width()`, or what the `.width_provider()` tells. Detached
+    /// renderings don't look at the terminal.
+    pub(crate) fn current_width(&self) -> usize {
+        self.term_width
+            .or_else(|| match self.width_source {
+                Some(ref provider) => provider.width(),
+                None if self.detached => None,
+                None => ConsoleWidth.width(),
+            })
+            .unwrap_or(DEFAULT_WIDTH)
+    }
+
+    /// The output format version to render in, the current one unless it's pinned.
+    pub(crate) fn output_version(&self) -> u32 {
+        self.output_format_version.unwrap_or(OUTPUT_FORMAT_VERSION)
+    }
+
+    /// Whether the input is shown as a hexdump, see `.hexdump()` and `.binary_hexdump()`.
+    pub(crate) fn shows_hexdump(&self, content_type: ContentType) -> bool {
+        self.hexdump || (self.binary_hexdump && content_type.is_binary())
+    }
+
+    /// The configuration for `copy_fidelity`: the decorations go away and the lines are printed
+    /// as they are, only highlighted.
+    fn faithful(&self) -> PrettyPrint {
+        PrettyPrint {
+            show_nonprintable: false,
+            strip_ansi: false,
+            encoding_label: None,
+            invalid_bytes: InvalidBytes::default(),
+            hexdump: false,
+            binary_hexdump: false,
+            binary_preview_bytes: None,
+            preprocessors: Vec::new(),
+            transforms: Vec::new(),
+            line_filter_hook: None,
+            emitter: None,
+            show_line_endings: false,
+            tab_width: 0,
+            grid: false,
+            header: false,
+            url_hyperlinks: false,
+            literate: false,
+            table_of_contents: false,
+            identifier_index: false,
+            footer: None,
+            line_numbers: false,
+            changes: false,
+            custom_decorations: Vec::new(),
+            annotations: Vec::new(),
+            overview: false,
+            minimap: false,
+            trailing_whitespace: TrailingWhitespace::None,
+            accessibility: Accessibility::Colors,
+            use_background: false,
+            output_wrap: OutputWrap::None,
+            sample: None,
+            snip: false,
+            squeeze_blank: false,
+            dedup_repeats: false,
+            timestamp_pattern: String::new(),
+            ..self.clone()
+        }
+    }
+
+    /// The configuration for `streaming`, without the options which read the whole file or
+    /// collect something about each line.
+    fn streamed(&self) -> Result<PrettyPrint> {
+        if self.output_format == OutputFormat::Svg {
+            return Err("SVG output needs the size of the whole file and can't be streamed".into());
+        }
+        Ok(PrettyPrint {
+            overview: false,
+            minimap: false,
+            table_of_contents: false,
+            identifier_index: false,
+            assert_max_width: None,
+            transforms: Vec::new(),
+            symbol_query: None,
+            highlight_threads: 1,
+            ..self.clone()
+        })
+    }
+
+    /// Prints each cell of a notebook like a file of its own, highlighted in the cell's language.
+    #[cfg(feature = "notebook")]
+    fn print_notebook(&self, notebook: &Notebook, writer: &mut dyn Write) -> Result<()> {
+        let assets = self.get_assets()?;
+
+        for (index, cell) in notebook.cells.iter().enumerate() {
+            let mut config = PrettyPrint {
+                language: cell.language(notebook).to_string(),
+                ..self.clone()
+            };
+            let number = CellNumber::new(index + 1, notebook.cells.len(), self.first_line_number);
+            config.custom_decorations.push(Arc::new(number));
+            let input_file = InputFile::String(cell.source.clone());
+            let mut reader = input_file.get_reader()?;
+            let mut printer = InteractivePrinter::new(&config, &assets, &input_file, &mut reader)?;
+
+            config.print_file(
+                reader,
+                &mut printer,
+                writer,
+                &input_file,
+                Some(cell.title()),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// The theme to use from `assets`, see `theme_name()`.
+    pub(crate) fn get_theme<'t>(&'t self, assets: &'t HighlightingAssets) -> &'t Theme {
+        if let Some(ref handle) = self.theme_handle {
+            return handle.theme();
+        }
+        let env_theme = self
+            .env_theme
+            .as_ref()
+            .and_then(|name| assets.theme_set.themes.get(name));
+        env_theme.unwrap_or_else(|| {
+            // Loaded by their names, the integrated assets may only have the needed themes.
+            if self.uses_integrated_assets() {
+                let names = HighlightingAssets::split().theme_names();
+                assets.get_theme_among(self.theme_name(), names)
+            } else {
+                assets.get_theme(self.theme_name())
+            }
+        })
+    }
+
+    /// Name of the theme to use, taking the terminal background into account.
+    pub(crate) fn theme_name(&self) -> &str {
+        match self.auto_themes {
+            Some((_, ref light)) if !self.detached && *BACKGROUND == Some(Background::Light) => {
+                light
+            }
+            Some((ref dark, _)) => dark,
+            None => &self.theme,
+        }
+    }
+
+    /// Whether the output gets colorized. Colors are only used for terminals, unless forced
+    /// by `CLICOLOR_FORCE`.
+    pub(crate) fn use_colors(&self) -> bool {
+        if self.detached {
+            return self.colored_output.unwrap_or(true);
+        }
+
+        let (no_color, clicolor_force) = if self.color_env {
+            (env::var_os("NO_COLOR"), env::var_os("CLICOLOR_FORCE"))
+        } else {
+            (None, None)
+        };
+
+        color_choice(
+            self.colored_output,
+            atty::is(Stream::Stdout),
+            no_color.is_some_and(|value| !value.is_empty()),
+            self.force_colors
+                || clicolor_force.is_some_and(|value| !value.is_empty() && value != "0"),
+        )
+    }
+
+    pub(crate) fn get_grid_charset(&self) -> GridCharset {
+        if let Some(charset) = self.grid_charset {
+            charset
+        } else if self.ascii_grid {
+            GridCharset::ASCII
+        } else {
+            GridCharset::UNICODE
+        }
+    }
+
+    pub(crate) fn get_output_components(&self) -> OutputComponents {
+        let mut components = HashSet::new();
+        if self.grid {
+            components.insert(OutputComponent::Grid);
+        }
+        if self.header {
+            components.insert(OutputComponent::Header);
+        }
+        if self.header_size {
+            components.insert(OutputComponent::Size);
+        }
+        if self.header_modified {
+            components.insert(OutputComponent::Modified);
+        }
+        if self.header_permissions {
+            components.insert(OutputComponent::Permissions);
+        }
+        if self.line_numbers {
+            components.insert(OutputComponent::Numbers);
+        }
+        if self.changes {
+            components.insert(OutputComponent::Changes);
+        }
+        OutputComponents(components)
+    }
+
+    /// Runs `line` through the stages of the preprocessing: `strip_ansi`, the stages given to
+    /// `.preprocessor()` and `show_nonprintable`, with tabs of `tab_width`. Printers which
+    /// don't show non-printable characters give no tab width.
+    pub(crate) fn preprocess(&self, line: String, tab_width: Option<usize>) -> String {
+        let stripper = Some(&AnsiStripper as &dyn Preprocessor).filter(|_| self.strip_ansi);
+        let replacer = tab_width
+            .filter(|_| self.show_nonprintable)
+            .map(|tab_width| NonprintableReplacer {
+                tab_width,
+                symbols: self.nonprintable_symbols,
+            });
+        stripper
+            .into_iter()
+            .chain(self.preprocessors.iter().map(|stage| &**stage))
+            .chain(
+                replacer
+                    .as_ref()
+                    .map(|replacer| replacer as &dyn Preprocessor),
+            )
+            .fold(line, |line, stage| stage.process(&line))
+    }
+
+    /// The lines after the `.line_filter()`, with the skipped lines left empty. `None` without
+    /// a filter.
+    fn filter_lines(&self, lines: &[Vec<u8>]) -> Option<Vec<Vec<u8>>> {
+        let filter = self.line_filter_hook.as_ref()?;
+        let shown = |index: usize| index + self.first_line_number;
+        Some(
+            lines
+                .iter()
+                .enumerate()
+                .map(|(index, line)| {
+                    filter
+                        .apply(shown(index), line)
+                        .unwrap_or_else(|| b"\n".to_vec())
+                })
+                .collect(),
+        )
+    }
+
+    fn print_file<P: Printer>(
         &self,
         reader: InputFileReader,
         printer: &mut P,
-        writer: &mut Write,
+        writer: &mut dyn Write,
         input_file: &InputFile,
         header_overwrite: Option<String>,
     ) -> Result<()> {
-        printer.print_header(writer, &input_file, header_overwrite)?;
-        self.print_file_ranges(printer, writer, reader, &self.line_ranges)?;
+        printer.print_header(writer, input_file, header_overwrite)?;
+
+        let transform = !self.transforms.is_empty() && transform::applies_to(reader.content_type);
+        let minimap = self.minimap && transform::applies_to(reader.content_type);
+        let contents = self.table_of_contents && transform::applies_to(reader.content_type);
+        let symbol = self.symbol_query.is_some() && transform::applies_to(reader.content_type);
+
+        let buffered;
+        let mut symbol_range = None;
+        let reader = if transform || minimap || contents || symbol {
+            let mut lines = read_lines(reader)?;
+            if transform {
+                for line in lines.iter_mut().filter(|line| line.last() != Some(&b'\n')) {
+                    line.push(b'\n');
+                }
+                lines = transform::apply(&self.transforms, lines);
+            }
+            // The table of contents and the minimap must not show what the filter hides.
+            let filtered = self.filter_lines(&lines);
+            let lines_shown = filtered.as_ref().unwrap_or(&lines);
+            if minimap {
+                let height = if self.detached {
+                    lines_shown.len()
+                } else {
+                    (Term::stdout().size().0 as usize).saturating_sub(4)
+                };
+                printer.prepare_minimap(lines_shown, height)?;
+            }
+            if contents {
+                printer.print_table_of_contents(writer, lines_shown)?;
+            }
+            if let Some(ref query) = self.symbol_query {
+                let range = symbol::find_symbol(lines_shown, query, self.symbol_context)
+                    .ok_or_else(|| format!("Symbol '{}' not found", query))?;
+                symbol_range = Some(LineRanges::from(vec![range]));
+            }
+
+            buffered = lines.concat();
+            InputFileReader::new(&buffered[..])
+        } else {
+            reader
+        };
+
+        let line_ranges = symbol_range.as_ref().unwrap_or(&self.line_ranges);
+        self.print_file_ranges(printer, writer, reader, line_ranges)?;
         printer.print_footer(writer)?;
 
         Ok(())
     }
 
-    fn print_file_ranges<'a, P: Printer>(
+    fn print_file_ranges<P: Printer>(
         &self,
         printer: &mut P,
-        writer: &mut Write,
+        writer: &mut dyn Write,
         mut reader: InputFileReader,
         line_ranges: &LineRanges,
     ) -> Result<()> {
+        let line_filter = self
+            .line_filter_hook
+            .as_ref()
+            .filter(|_| reader.content_type.is_text());
+        if let Some(sampling) = self.sample {
+            return print_sample(
+                printer,
+                writer,
+                reader,
+                line_ranges,
+                sampling,
+                self.first_line_number,
+                line_filter,
+            );
+        }
+
         let mut line_buffer = Vec::new();
         let mut line_number: usize = 1;
+        // The line number, contents and count of a run of identical lines.
+        let mut run: Option<(usize, Vec<u8>, usize)> = None;
+        let dedup_repeats = self.dedup_repeats && reader.content_type.is_text();
+        let squeeze_blank = self.squeeze_blank && reader.content_type.is_text();
+        let mut empty_lines = 0;
+        // Whether lines were printed, and lines were left out since, for the snip markers.
+        let (mut printed, mut left_out) = (false, false);
+        // Line ranges refer to the lines of the input, the printer gets the shown numbers.
+        let shown = |line_number: usize| line_number + self.first_line_number - 1;
+        // Filtered lines differ from the ones read ahead, so they're parsed as they're printed.
+        // Neither are the pieces of long lines read ahead, which share their line number, nor
+        // the lines again that the minimap read ahead.
+        let window_lines = if self.highlight_threads != 1
+            && !self.minimap
+            && line_filter.is_none()
+            && reader.max_line_length.is_none()
+            && transform::applies_to(reader.content_type)
+        {
+            parallel::thread_count(self.highlight_threads) * parallel::WINDOW_LINES_PER_THREAD
+        } else {
+            0
+        };
+        let mut window = VecDeque::new();
+
+        while !printer.finished()
+            && read_ahead(
+                &mut reader,
+                printer,
+                &mut window,
+                window_lines,
+                &mut line_buffer,
+            )?
+        {
+            if let Some(filter) = line_filter {
+                match filter.apply(shown(line_number), &line_buffer) {
+                    Some(line) => line_buffer = line,
+                    None => {
+                        // Skipped lines still go through the highlighter.
+                        printer.print_line(true, writer, shown(line_number), &line_buffer)?;
+                        line_number += 1;
+                        line_buffer.clear();
+                        continue;
+                    }
+                }
+            }
+            if same_line(&line_buffer, b"") {
+                empty_lines += 1;
+            } else {
+                empty_lines = 0;
+            }
+            let squeezed = squeeze_blank && empty_lines > self.squeeze_limit;
+
+            let range_check = line_ranges.check(line_number);
+            if self.snip {
+                match range_check {
+                    RangeCheckResult::InRange if left_out => {
+                        print_run(printer, writer, run.take())?;
+                        printer.print_snip(writer)?;
+                        left_out = false;
+                    }
+                    RangeCheckResult::InRange => printed = true,
+                    _ => left_out = printed,
+                }
+            }
 
-        while reader.read_line(&mut line_buffer)? {
-            match line_ranges.check(line_number) {
+            match range_check {
                 RangeCheckResult::OutsideRange => {
+                    print_run(printer, writer, run.take())?;
+
                     // Call the printer in case we need to call the syntax highlighter
                     // for this line. However, set `out_of_range` to `true`.
-                    printer.print_line(true, writer, line_number, &line_buffer)?;
+                    printer.print_line(true, writer, shown(line_number), &line_buffer)?;
+                }
+                RangeCheckResult::InRange if squeezed => {
+                    print_run(printer, writer, run.take())?;
+
+                    // Squeezed lines still go through the highlighter.
+                    printer.print_line(true, writer, shown(line_number), &line_buffer)?;
                 }
+                RangeCheckResult::InRange if dedup_repeats => match run {
+                    Some((_, ref contents, ref mut count)) if same_line(contents, &line_buffer) => {
+                        *count += 1;
+                    }
+                    _ => {
+                        print_run(printer, writer, run.take())?;
+                        run = Some((shown(line_number), line_buffer.clone(), 1));
+                    }
+                },
                 RangeCheckResult::InRange => {
-                    printer.print_line(false, writer, line_number, &line_buffer)?;
+                    printer.print_line(false, writer, shown(line_number), &line_buffer)?;
                 }
                 RangeCheckResult::AfterLastRange => {
                     break;
                 }
             }
 
+            if reader.continued {
+                line_buffer.clear();
+                continue;
+            }
+            if self.streaming && line_number.is_multiple_of(STREAM_FLUSH_LINES) {
+                writer.flush()?;
+            }
             line_number += 1;
             line_buffer.clear();
         }
-        Ok(())
+
+        print_run(printer, writer, run)
+    }
+}
+
+/// Reads the next line into `line_buffer`. Unless `window_lines` is zero, it's taken from a
+/// window of that many lines, which the printer parses ahead.
+fn read_ahead<P: Printer>(
+    reader: &mut InputFileReader,
+    printer: &mut P,
+    window: &mut VecDeque<Vec<u8>>,
+    window_lines: usize,
+    line_buffer: &mut Vec<u8>,
+) -> Result<bool> {
+    if window_lines == 0 {
+        return Ok(reader.read_line(line_buffer)?);
+    }
+    if window.is_empty() {
+        let mut line = Vec::new();
+        while window.len() < window_lines && reader.read_line(&mut line)? {
+            window.push_back(mem::take(&mut line));
+        }
+        printer.prepare_highlighting(window.make_contiguous())?;
+    }
+    match window.pop_front() {
+        Some(line) => {
+            line_buffer.extend(line);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+fn read_lines(mut reader: InputFileReader) -> Result<Vec<Vec<u8>>> {
+    let mut lines = Vec::new();
+    let mut line_buffer = Vec::new();
+
+    while reader.read_line(&mut line_buffer)? {
+        lines.push(line_buffer.clone());
+        line_buffer.clear();
+    }
+
+    Ok(lines)
+}
+
+/// Copies the lines of `reader` into `writer` unchanged, for `loop_through`. Only the
+/// `.line_filter()` still applies to text.
+fn copy_lines(
+    mut reader: InputFileReader,
+    writer: &mut dyn Write,
+    line_filter: Option<&LineFilter>,
+    first_line_number: usize,
+) -> Result<()> {
+    let line_filter = line_filter.filter(|_| reader.content_type.is_text());
+    let mut line_buffer = Vec::new();
+    let mut line_number = first_line_number;
+
+    while reader.read_line(&mut line_buffer)? {
+        match line_filter {
+            Some(filter) => {
+                if let Some(line) = filter.apply(line_number, &line_buffer) {
+                    writer.write_all(&line)?;
+                }
+            }
+            None => writer.write_all(&line_buffer)?,
+        }
+        if !reader.continued {
+            line_number += 1;
+        }
+        line_buffer.clear();
+    }
+
+    Ok(())
+}
+
+/// Prints the sampled lines of the ranges, with snip markers between lines which aren't
+/// adjacent. The `.line_filter()` applies to the sampled lines only.
+fn print_sample<P: Printer>(
+    printer: &mut P,
+    writer: &mut dyn Write,
+    mut reader: InputFileReader,
+    line_ranges: &LineRanges,
+    sampling: Sampling,
+    first_line_number: usize,
+    line_filter: Option<&LineFilter>,
+) -> Result<()> {
+    // Only the sampled lines are kept, as huge files don't fit into memory.
+    let mut lines = Vec::new();
+    let mut line_buffer = Vec::new();
+    let mut line_number: usize = 1;
+    let shown = |line_number: usize| line_number + first_line_number - 1;
+    let mut random = Random::from_time();
+    let mut index = 0;
+
+    while reader.read_line(&mut line_buffer)? {
+        match line_ranges.check(line_number) {
+            RangeCheckResult::InRange => match sampling {
+                Sampling::EveryNth(n) => {
+                    if (line_number - 1).is_multiple_of(n.max(1)) {
+                        lines.push((shown(line_number), line_buffer.clone()));
+                    }
+                }
+                Sampling::Random(n) => {
+                    match random.reservoir_slot(index, n) {
+                        Some(slot) if slot == lines.len() => {
+                            lines.push((shown(line_number), line_buffer.clone()))
+                        }
+                        Some(slot) => lines[slot] = (shown(line_number), line_buffer.clone()),
+                        None => {}
+                    }
+                    index += 1;
+                }
+            },
+            RangeCheckResult::OutsideRange => {}
+            RangeCheckResult::AfterLastRange => break,
+        }
+
+        if !reader.continued {
+            line_number += 1;
+        }
+        line_buffer.clear();
+    }
+    lines.sort_by_key(|&(line_number, _)| line_number);
+
+    let mut previous = None;
+    for (line_number, line) in lines {
+        let line = match line_filter {
+            Some(filter) => match filter.apply(line_number, &line) {
+                Some(line) => line,
+                None => continue,
+            },
+            None => line,
+        };
+        if previous.is_some_and(|previous| previous + 1 != line_number) {
+            printer.print_snip(writer)?;
+        }
+        printer.print_line(false, writer, line_number, &line)?;
+        previous = Some(line_number);
+    }
+
+    Ok(())
+}
+
+/// Prints a run of identical lines as one line. The repeats still go through the printer, so
+/// that the highlighter keeps track of them.
+fn print_run<P: Printer>(
+    printer: &mut P,
+    writer: &mut dyn Write,
+    run: Option<(usize, Vec<u8>, usize)>,
+) -> Result<()> {
+    if let Some((line_number, contents, count)) = run {
+        printer.print_repeated_line(writer, line_number, &contents, count)?;
+        for repeat in 1..count {
+            printer.print_line(true, writer, line_number + repeat, &contents)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The width of the widest range of line numbers of a run of identical `lines` (e.g. `12-15`),
+/// as `.dedup_repeats()` shows it. `None` if no line is repeated.
+pub(crate) fn widest_run<I: IntoIterator<Item = Vec<u8>>>(
+    lines: I,
+    first_line_number: usize,
+    format: Option<&LineNumberFormat>,
+) -> Option<usize> {
+    let label = |start: usize, count: usize| {
+        let last = format_line_number(format, start + count - 1);
+        format_line_number(format, start).width() + 1 + last.width()
+    };
+
+    let mut widest = None;
+    let mut run: Option<(usize, Vec<u8>, usize)> = None;
+    for (index, line) in lines.into_iter().enumerate() {
+        match run {
+            Some((_, ref contents, ref mut count)) if same_line(contents, &line) => *count += 1,
+            _ => {
+                if let Some((start, _, count)) = run.filter(|&(_, _, count)| count > 1) {
+                    widest = widest.max(Some(label(start, count)));
+                }
+                run = Some((first_line_number + index, line, 1));
+            }
+        }
+    }
+    if let Some((start, _, count)) = run.filter(|&(_, _, count)| count > 1) {
+        widest = widest.max(Some(label(start, count)));
+    }
+
+    widest
+}
+
+/// Compares lines, ignoring the line break (which the last line may not have).
+fn same_line(a: &[u8], b: &[u8]) -> bool {
+    fn trim(line: &[u8]) -> &[u8] {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        line.strip_suffix(b"\r").unwrap_or(line)
+    }
+
+    trim(a) == trim(b)
+}
+
+/// The line number and the column (in characters) of the byte at `offset`. The end of the
+/// content counts as a position, too.
+fn locate(content: &[u8], offset: usize) -> Option<(usize, usize)> {
+    if offset > content.len() {
+        return None;
+    }
+    let before = &content[..offset];
+    let line_start = before
+        .iter()
+        .rposition(|&byte| byte == b'\n')
+        .map_or(0, |index| index + 1);
+    let line = before.iter().filter(|&&byte| byte == b'\n').count() + 1;
+    let column = String::from_utf8_lossy(&before[line_start..])
+        .chars()
+        .count();
+    Some((line, column))
+}
+
+/// Colors are used for terminals, or everywhere if they're forced. `NO_COLOR` only turns them
+/// off if `colored_output` isn't set explicitly.
+fn color_choice(colored_output: Option<bool>, is_tty: bool, no_color: bool, force: bool) -> bool {
+    match colored_output {
+        Some(false) => false,
+        None if no_color => false,
+        _ => is_tty || force,
     }
 }
 
@@ -249,3 +2252,118 @@ fn is_truecolor_terminal() -> bool {
         .map(|colorterm| colorterm == "truecolor" || colorterm == "24bit")
         .unwrap_or(false)
 }
+
+#[test]
+fn widest_runs() {
+    let lines = |text: &str| -> Vec<Vec<u8>> {
+        text.split_inclusive('\n').map(|line| line.into()).collect()
+    };
+
+    assert_eq!(None, widest_run(lines("a\nb\na\n"), 1, None));
+    assert_eq!(Some(3), widest_run(lines("a\nb\nb\nc"), 1, None));
+    assert_eq!(Some(5), widest_run(lines("a\na\n\nb\nb\r\nb\n"), 9, None));
+}
+
+#[test]
+fn locate_offsets() {
+    let content = "fn main() {\n    println!(\"ü\");\n}\n".as_bytes();
+    assert_eq!(Some((1, 0)), locate(content, 0));
+    assert_eq!(Some((2, 4)), locate(content, 16));
+    assert_eq!(Some((2, 15)), locate(content, 28));
+    assert_eq!(Some((4, 0)), locate(content, content.len()));
+    assert_eq!(None, locate(content, content.len() + 1));
+}
+
+#[test]
+fn color_env() {
+    assert!(color_choice(None, true, false, false));
+    assert!(!color_choice(None, false, false, false));
+    assert!(!color_choice(None, true, true, false));
+    assert!(!color_choice(None, true, true, true));
+    assert!(color_choice(None, false, false, true));
+    assert!(color_choice(Some(true), true, true, false));
+    assert!(!color_choice(Some(false), true, false, true));
+    assert!(!color_choice(Some(false), false, false, true));
+}
+
+#[test]
+fn env_defaults() {
+    let var = |name: &str| match name {
+        "PRETTYPRINT_THEME" => Some("TwoDark".to_string()),
+        "PRETTYPRINT_STYLE" => Some("numbers, header".to_string()),
+        "PRETTYPRINT_TABS" => Some("eight".to_string()),
+        _ => None,
+    };
+
+    let mut builder = PrettyPrinter::default();
+    builder.header(false).apply_env_defaults(var);
+    let printer = builder.build();
+
+    assert_eq!(Some("TwoDark"), printer.env_theme.as_deref());
+    assert!(printer.line_numbers && !printer.grid);
+    assert!(!printer.header);
+    assert_eq!(0, printer.tab_width);
+    assert_eq!(None, printer.pager);
+}
+
+#[test]
+fn env_themes() {
+    let assets = HighlightingAssets::integrated();
+    let theme = |name: &str| {
+        let mut builder = PrettyPrinter::default();
+        builder.apply_env_defaults(|var| {
+            Some(name.to_string()).filter(|_| var == "PRETTYPRINT_THEME")
+        });
+        let printer = builder.build();
+        printer.get_theme(&assets).name.clone()
+    };
+
+    assert_eq!(Some("TwoDark".to_string()), theme("TwoDark"));
+    // Unknown themes are ignored, without the warning of an unknown `.theme()`.
+    assert_eq!(
+        assets.get_theme(PRETTYPRINT_THEME_DEFAULT).name,
+        theme("No such theme")
+    );
+}
+
+#[test]
+fn binary_previews_stop_reading() {
+    use std::io::{self, BufReader, Read};
+
+    /// Fails the print if it's read from.
+    struct Unread;
+
+    impl Read for Unread {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("read after the preview"))
+        }
+    }
+
+    let config = PrettyPrinter::default()
+        .colored_output(false)
+        .term_width(80usize)
+        .binary_hexdump(true)
+        .binary_preview_bytes(4usize)
+        .build();
+    let assets = config.get_assets().unwrap();
+    let input = b"\x00\x01\n\x02\x02\x02\x02\x02\x02".chain(Unread);
+    let mut reader = InputFileReader::new(BufReader::with_capacity(1, input));
+    let input_file = InputFile::StdIn;
+    let printer = InteractivePrinter::new(&config, &assets, &input_file, &mut reader).unwrap();
+    // Like `render_file()` does for lines without an end.
+    reader.max_line_length = Some(4);
+
+    let mut output = Vec::new();
+    config
+        .print_file(
+            reader,
+            &mut HexdumpPrinter::new(printer),
+            &mut output,
+            &input_file,
+            None,
+        )
+        .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("00000000 │ 00 01 0a 02   "));
+    assert!(output.contains("         │ … more bytes not shown\n"));
+}
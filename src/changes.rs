@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+/// How a line differs from the version of the file in the git index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineChange {
+    Added,
+    Modified,
+    /// Lines next to this one were removed
+    Removed,
+}
+
+/// The changed lines of a file, by line number.
+pub type LineChanges = HashMap<usize, LineChange>;
+
+/// The uncommitted changes of `filename`, from `git diff`. `None` if git isn't installed or the
+/// file isn't in a repository.
+pub fn get_git_diff(filename: &str) -> Option<LineChanges> {
+    let path = Path::new(filename);
+    let dir = match path.parent() {
+        Some(dir) if dir.as_os_str().is_empty() => Path::new("."),
+        Some(dir) => dir,
+        None => return None,
+    };
+    let output = Command::new("git")
+        .args(["diff", "--no-color", "--no-ext-diff", "--unified=0", "--"])
+        .arg(path.file_name()?)
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_diff(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// The changed lines from the hunk headers of a unified diff without context lines, e.g.
+/// `@@ -3,2 +3,4 @@`.
+pub fn parse_diff(diff: &str) -> LineChanges {
+    let mut changes = LineChanges::new();
+    for header in diff.lines().filter(|line| line.starts_with("@@ ")) {
+        let mut ranges = header.split(' ').skip(1);
+        let (old, new) = match (ranges.next(), ranges.next()) {
+            (Some(old), Some(new)) => (parse_range(old), parse_range(new)),
+            _ => continue,
+        };
+        let ((_, removed), (start, added)) = match (old, new) {
+            (Some(old), Some(new)) => (old, new),
+            _ => continue,
+        };
+
+        if added == 0 {
+            // The removed lines were below `start`, which is zero at the top of the file.
+            changes.insert(start.max(1), LineChange::Removed);
+            continue;
+        }
+        let modified = removed.min(added);
+        for line in start..start + added {
+            let change = if line < start + modified {
+                LineChange::Modified
+            } else {
+                LineChange::Added
+            };
+            changes.insert(line, change);
+        }
+    }
+    changes
+}
+
+/// A range of a hunk header, e.g. `-3,2`: the first line and the number of lines.
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let range = &range[1..];
+    let mut parts = range.splitn(2, ',');
+    let start = parts.next()?.parse().ok()?;
+    let count = match parts.next() {
+        Some(count) => count.parse().ok()?,
+        None => 1,
+    };
+    Some((start, count))
+}
+
+#[test]
+fn hunk_headers() {
+    let diff = "diff --git a/fib.rs b/fib.rs\n\
+                --- a/fib.rs\n\
+                +++ b/fib.rs\n\
+                @@ -0,0 +1 @@\n\
+                +// Fibonacci numbers\n\
+                @@ -3,2 +4,3 @@\n\
+                -a\n\
+                -b\n\
+                +c\n\
+                +d\n\
+                +e\n\
+                @@ -9 +11,0 @@\n\
+                -f\n";
+    let changes = parse_diff(diff);
+
+    assert_eq!(Some(&LineChange::Added), changes.get(&1));
+    assert_eq!(Some(&LineChange::Modified), changes.get(&4));
+    assert_eq!(Some(&LineChange::Modified), changes.get(&5));
+    assert_eq!(Some(&LineChange::Added), changes.get(&6));
+    assert_eq!(Some(&LineChange::Removed), changes.get(&11));
+    assert_eq!(5, changes.len());
+}
@@ -0,0 +1,200 @@
+use std::env;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Integrates the output with the log of a CI service: each file goes into a collapsible group
+/// and errors reported with `.ci_error()` become annotations, if the service supports them.
+pub trait CiEmitter: Debug + Send + Sync {
+    /// The marker which opens the group `title`.
+    fn start_group(&self, title: &str) -> String;
+
+    /// The marker which closes the group `title`.
+    fn end_group(&self, title: &str) -> String;
+
+    /// The message for an error at `line` of `file`.
+    fn error(&self, _file: Option<&str>, _line: usize, _message: &str) -> String {
+        String::new()
+    }
+}
+
+/// The CI service the process runs in, going by its environment variables.
+pub fn detect() -> Option<Arc<dyn CiEmitter>> {
+    if env::var("GITHUB_ACTIONS").is_ok_and(|value| value == "true") {
+        Some(Arc::new(GitHubActions))
+    } else if env::var_os("GITLAB_CI").is_some() {
+        Some(Arc::new(GitLabCi))
+    } else if env::var_os("TEAMCITY_VERSION").is_some() {
+        Some(Arc::new(TeamCity))
+    } else {
+        None
+    }
+}
+
+/// Groups and error annotations through workflow commands.
+#[derive(Debug, Clone, Copy)]
+pub struct GitHubActions;
+
+impl CiEmitter for GitHubActions {
+    fn start_group(&self, title: &str) -> String {
+        format!("::group::{}\n", title)
+    }
+
+    fn end_group(&self, _title: &str) -> String {
+        "::endgroup::\n".to_string()
+    }
+
+    fn error(&self, file: Option<&str>, line: usize, message: &str) -> String {
+        let data = message
+            .replace('%', "%25")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A");
+        match file {
+            Some(file) => {
+                let file = file
+                    .replace('%', "%25")
+                    .replace(':', "%3A")
+                    .replace(',', "%2C");
+                format!("::error file={},line={}::{}\n", file, line, data)
+            }
+            None => format!("::error::{}\n", data),
+        }
+    }
+}
+
+/// Collapsible sections. GitLab has no annotations for log lines.
+#[derive(Debug, Clone, Copy)]
+pub struct GitLabCi;
+
+impl GitLabCi {
+    fn section_start(title: &str, timestamp: u64) -> String {
+        format!(
+            "\x1b[0Ksection_start:{}:{}[collapsed=true]\r\x1b[0K{}\n",
+            timestamp,
+            section_name(title),
+            title
+        )
+    }
+
+    fn section_end(title: &str, timestamp: u64) -> String {
+        format!(
+            "\x1b[0Ksection_end:{}:{}\r\x1b[0K\n",
+            timestamp,
+            section_name(title)
+        )
+    }
+}
+
+impl CiEmitter for GitLabCi {
+    fn start_group(&self, title: &str) -> String {
+        GitLabCi::section_start(title, unix_time())
+    }
+
+    fn end_group(&self, title: &str) -> String {
+        GitLabCi::section_end(title, unix_time())
+    }
+}
+
+/// Blocks and error messages through service messages.
+#[derive(Debug, Clone, Copy)]
+pub struct TeamCity;
+
+impl CiEmitter for TeamCity {
+    fn start_group(&self, title: &str) -> String {
+        format!(
+            "##teamcity[blockOpened name='{}']\n",
+            teamcity_escape(title)
+        )
+    }
+
+    fn end_group(&self, title: &str) -> String {
+        format!(
+            "##teamcity[blockClosed name='{}']\n",
+            teamcity_escape(title)
+        )
+    }
+
+    fn error(&self, file: Option<&str>, line: usize, message: &str) -> String {
+        let text = match file {
+            Some(file) => format!("{}:{}: {}", file, line, message),
+            None => message.to_string(),
+        };
+        format!(
+            "##teamcity[message text='{}' status='ERROR']\n",
+            teamcity_escape(&text)
+        )
+    }
+}
+
+/// GitLab section names may only contain letters, digits, `_`, `.` and `-`.
+fn section_name(title: &str) -> String {
+    title
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '.' | '-' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+fn teamcity_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '|' | '\'' | '[' | ']' => {
+                escaped.push('|');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("|n"),
+            '\r' => escaped.push_str("|r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[test]
+fn github_actions() {
+    assert_eq!(
+        "::group::src/main.rs\n",
+        GitHubActions.start_group("src/main.rs")
+    );
+    assert_eq!("::endgroup::\n", GitHubActions.end_group("src/main.rs"));
+    assert_eq!(
+        "::error file=src/a%2Cb.rs,line=3::expected `;`%0Afound `}`\n",
+        GitHubActions.error(Some("src/a,b.rs"), 3, "expected `;`\nfound `}`")
+    );
+    assert_eq!("::error::100%25\n", GitHubActions.error(None, 1, "100%"));
+}
+
+#[test]
+fn gitlab_ci() {
+    assert_eq!(
+        "\x1b[0Ksection_start:10:src_main.rs[collapsed=true]\r\x1b[0Ksrc/main.rs\n",
+        GitLabCi::section_start("src/main.rs", 10)
+    );
+    assert_eq!(
+        "\x1b[0Ksection_end:12:src_main.rs\r\x1b[0K\n",
+        GitLabCi::section_end("src/main.rs", 12)
+    );
+    assert_eq!("", GitLabCi.error(None, 1, "error"));
+}
+
+#[test]
+fn teamcity() {
+    assert_eq!(
+        "##teamcity[blockOpened name='it|'s |[1|]']\n",
+        TeamCity.start_group("it's [1]")
+    );
+    assert_eq!(
+        "##teamcity[message text='a.rs:2: bad|nline' status='ERROR']\n",
+        TeamCity.error(Some("a.rs"), 2, "bad\nline")
+    );
+}
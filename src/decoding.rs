@@ -0,0 +1,89 @@
+use std::str;
+
+use encoding::EncodingRef;
+
+/// What to do with lines which aren't valid in the encoding of the input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidBytes {
+    /// Fail with an error
+    Strict,
+    /// Replace each invalid sequence
+    Lossy { replacement: String },
+    /// Leave the line out
+    SkipLine,
+}
+
+impl Default for InvalidBytes {
+    fn default() -> Self {
+        InvalidBytes::Lossy {
+            replacement: "\u{FFFD}".to_string(),
+        }
+    }
+}
+
+/// Decodes a line with `encoding`, or as UTF-8 without one. `None` if it's invalid.
+pub fn decode(bytes: &[u8], encoding: Option<EncodingRef>) -> Option<String> {
+    match encoding {
+        Some(encoding) => {
+            let mut decoder = encoding.raw_decoder();
+            let mut output = String::new();
+            let (_, error) = decoder.raw_feed(bytes, &mut output);
+            if error.is_some() || decoder.raw_finish(&mut output).is_some() {
+                None
+            } else {
+                Some(output)
+            }
+        }
+        None => str::from_utf8(bytes).ok().map(str::to_string),
+    }
+}
+
+/// Decodes a line like `decode`, with `replacement` for each invalid sequence.
+pub fn decode_lossy(bytes: &[u8], encoding: Option<EncodingRef>, replacement: &str) -> String {
+    let mut output = String::new();
+
+    match encoding {
+        Some(encoding) => {
+            let mut decoder = encoding.raw_decoder();
+            let mut remaining = bytes;
+            while let (_, Some(error)) = decoder.raw_feed(remaining, &mut output) {
+                output.push_str(replacement);
+                remaining = &remaining[error.upto as usize..];
+            }
+            if decoder.raw_finish(&mut output).is_some() {
+                output.push_str(replacement);
+            }
+        }
+        None => {
+            for chunk in bytes.utf8_chunks() {
+                output.push_str(chunk.valid());
+                if !chunk.invalid().is_empty() {
+                    output.push_str(replacement);
+                }
+            }
+        }
+    }
+
+    output
+}
+
+/// Name of the encoding for error messages.
+pub fn name(encoding: Option<EncodingRef>) -> &'static str {
+    encoding.map_or("UTF-8", |encoding| encoding.name())
+}
+
+#[test]
+fn lossy() {
+    use encoding::all::{ISO_8859_1, UTF_16LE};
+
+    assert_eq!(None, decode(b"a\xffb", None));
+    assert_eq!("a?b?", decode_lossy(b"a\xffb\xc3", None, "?"));
+
+    assert_eq!(Some("a\u{ff}".into()), decode(b"a\xff", Some(ISO_8859_1)));
+
+    assert_eq!(None, decode(b"a\x00\x00\xdc", Some(UTF_16LE)));
+    assert_eq!(
+        "a<?>b",
+        decode_lossy(b"a\x00\x00\xdcb\x00", Some(UTF_16LE), "<?>")
+    );
+}
@@ -1,14 +1,37 @@
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+use ansi_term::Colour::{self, Green, Red, Yellow};
 use ansi_term::Style;
+use unicode_width::UnicodeWidthStr;
 
+use crate::changes::{LineChange, LineChanges};
 use crate::printer::{Colors, InteractivePrinter};
+use crate::timestamps::format_delta;
+use crate::urls;
 
-#[derive(Clone)]
+/// The text of a decoration for a row.
+#[derive(Debug, Clone, PartialEq)]
 pub struct DecorationText {
+    /// Width of the text in columns, without its ANSI escape sequences
     pub width: usize,
     pub text: String,
 }
 
-pub trait Decoration {
+/// A column of the gutter next to the line numbers, e.g. for bookmarks, breakpoints or
+/// coverage, see `.decoration()`.
+pub trait Decoration: Debug + Send + Sync {
+    /// The text for the line with `line_number`, as numbered in the output. Rows which continue
+    /// a wrapped line (or show an annotation) are `continuation` rows. The text may contain ANSI
+    /// escape sequences and has to be `width()` columns wide.
+    fn generate(&self, line_number: usize, continuation: bool) -> DecorationText;
+
+    /// Width of the column.
+    fn width(&self) -> usize;
+}
+
+/// A column of the gutter, with access to the state of the printer.
+pub trait PanelDecoration {
     fn generate(
         &self,
         line_number: usize,
@@ -18,44 +41,177 @@ pub trait Decoration {
     fn width(&self) -> usize;
 }
 
+/// A `Decoration` given by the application.
+pub struct CustomDecoration(pub Arc<dyn Decoration>);
+
+impl PanelDecoration for CustomDecoration {
+    fn generate(
+        &self,
+        line_number: usize,
+        continuation: bool,
+        _printer: &InteractivePrinter,
+    ) -> DecorationText {
+        self.0.generate(line_number, continuation)
+    }
+
+    fn width(&self) -> usize {
+        self.0.width()
+    }
+}
+
+/// Formats line numbers for the gutter, e.g. as hex or zero-padded numbers.
+#[derive(Clone)]
+pub struct LineNumberFormat(pub Arc<LineNumberFn>);
+
+pub type LineNumberFn = dyn Fn(usize) -> String + Send + Sync;
+
+impl fmt::Debug for LineNumberFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("LineNumberFormat")
+    }
+}
+
 pub struct LineNumberDecoration {
     color: Style,
+    width: usize,
+    format: Option<LineNumberFormat>,
+    continuation_marker: String,
     cached_wrap: DecorationText,
     cached_wrap_invalid_at: usize,
 }
 
 impl LineNumberDecoration {
-    pub fn new(colors: &Colors) -> Self {
+    pub fn new(
+        colors: &Colors,
+        continuation_marker: &str,
+        width: usize,
+        format: Option<LineNumberFormat>,
+    ) -> Self {
         LineNumberDecoration {
             color: colors.line_number,
-            cached_wrap_invalid_at: 10000,
+            width,
+            format,
+            continuation_marker: continuation_marker.to_string(),
+            cached_wrap_invalid_at: 10usize.pow(width as u32),
             cached_wrap: DecorationText {
-                text: colors.line_number.paint(" ".repeat(4)).to_string(),
-                width: 4,
+                text: colors
+                    .line_number
+                    .paint(right_align(continuation_marker, width))
+                    .to_string(),
+                width,
             },
         }
     }
 }
 
-impl Decoration for LineNumberDecoration {
+impl PanelDecoration for LineNumberDecoration {
     fn generate(
         &self,
         line_number: usize,
         continuation: bool,
-        _printer: &InteractivePrinter,
+        printer: &InteractivePrinter,
     ) -> DecorationText {
         if continuation {
             if line_number > self.cached_wrap_invalid_at {
                 let new_width = self.cached_wrap.width + 1;
                 return DecorationText {
-                    text: self.color.paint(" ".repeat(new_width)).to_string(),
+                    text: self
+                        .color
+                        .paint(right_align(&self.continuation_marker, new_width))
+                        .to_string(),
                     width: new_width,
                 };
             }
 
             self.cached_wrap.clone()
         } else {
-            let plain: String = format!("{:4}", line_number);
+            let range = format_line_range(self.format.as_ref(), line_number, printer.repeats);
+            let plain = right_align(&range, self.width);
+            DecorationText {
+                width: plain.width(),
+                text: urls::hyperlink(
+                    printer.line_link(line_number).as_deref(),
+                    self.color.paint(plain).to_string(),
+                ),
+            }
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+}
+
+/// The continuation marker of wrapped lines, in a column of its own when there are no line
+/// numbers to show it in.
+pub struct ContinuationDecoration {
+    cached_marker: DecorationText,
+    cached_blank: DecorationText,
+}
+
+impl ContinuationDecoration {
+    pub fn new(colors: &Colors, continuation_marker: &str) -> Self {
+        let width = continuation_marker.width();
+        ContinuationDecoration {
+            cached_marker: DecorationText {
+                text: colors.line_number.paint(continuation_marker).to_string(),
+                width,
+            },
+            cached_blank: DecorationText {
+                text: " ".repeat(width),
+                width,
+            },
+        }
+    }
+}
+
+impl PanelDecoration for ContinuationDecoration {
+    fn generate(
+        &self,
+        _line_number: usize,
+        continuation: bool,
+        _printer: &InteractivePrinter,
+    ) -> DecorationText {
+        if continuation {
+            self.cached_marker.clone()
+        } else {
+            self.cached_blank.clone()
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.cached_marker.width
+    }
+}
+
+pub struct OffsetDecoration {
+    color: Style,
+    cached_wrap: DecorationText,
+}
+
+impl OffsetDecoration {
+    pub fn new(colors: &Colors) -> Self {
+        OffsetDecoration {
+            color: colors.line_number,
+            cached_wrap: DecorationText {
+                text: colors.line_number.paint(" ".repeat(8)).to_string(),
+                width: 8,
+            },
+        }
+    }
+}
+
+impl PanelDecoration for OffsetDecoration {
+    fn generate(
+        &self,
+        offset: usize,
+        continuation: bool,
+        _printer: &InteractivePrinter,
+    ) -> DecorationText {
+        if continuation {
+            self.cached_wrap.clone()
+        } else {
+            let plain: String = format!("{:08x}", offset);
             DecorationText {
                 width: plain.len(),
                 text: self.color.paint(plain).to_string(),
@@ -64,7 +220,111 @@ impl Decoration for LineNumberDecoration {
     }
 
     fn width(&self) -> usize {
-        4
+        8
+    }
+}
+
+/// Time elapsed since the previous timestamped line.
+pub struct TimestampDecoration {
+    color: Style,
+    cached_blank: DecorationText,
+}
+
+impl TimestampDecoration {
+    pub fn new(colors: &Colors) -> Self {
+        TimestampDecoration {
+            color: colors.line_number,
+            cached_blank: DecorationText {
+                text: " ".repeat(8),
+                width: 8,
+            },
+        }
+    }
+}
+
+impl PanelDecoration for TimestampDecoration {
+    fn generate(
+        &self,
+        _line_number: usize,
+        continuation: bool,
+        printer: &InteractivePrinter,
+    ) -> DecorationText {
+        match printer.timestamps.as_ref().and_then(|t| t.delta) {
+            Some(delta) if !continuation => DecorationText {
+                text: self
+                    .color
+                    .paint(right_align(&format_delta(delta), 8))
+                    .to_string(),
+                width: 8,
+            },
+            _ => self.cached_blank.clone(),
+        }
+    }
+
+    fn width(&self) -> usize {
+        8
+    }
+}
+
+/// Markers for the lines changed since the last commit: `+` for added, `~` for modified lines
+/// and `-` where lines were removed.
+pub struct LineChangesDecoration {
+    changes: LineChanges,
+    /// Shown line number of the first line of the file
+    first_line_number: usize,
+    cached_none: DecorationText,
+    cached_added: DecorationText,
+    cached_modified: DecorationText,
+    cached_removed: DecorationText,
+}
+
+impl LineChangesDecoration {
+    pub fn new(changes: LineChanges, first_line_number: usize, colored_output: bool) -> Self {
+        let marker = |color: Colour, marker: &str| DecorationText {
+            text: if colored_output {
+                color.paint(marker).to_string()
+            } else {
+                marker.to_string()
+            },
+            width: 1,
+        };
+        LineChangesDecoration {
+            changes,
+            first_line_number,
+            cached_none: DecorationText {
+                text: " ".to_string(),
+                width: 1,
+            },
+            cached_added: marker(Green, "+"),
+            cached_modified: marker(Yellow, "~"),
+            cached_removed: marker(Red, "-"),
+        }
+    }
+}
+
+impl PanelDecoration for LineChangesDecoration {
+    fn generate(
+        &self,
+        line_number: usize,
+        continuation: bool,
+        _printer: &InteractivePrinter,
+    ) -> DecorationText {
+        let change = if continuation {
+            None
+        } else {
+            self.changes
+                .get(&(line_number + 1).saturating_sub(self.first_line_number))
+        };
+        match change {
+            Some(LineChange::Added) => self.cached_added.clone(),
+            Some(LineChange::Modified) => self.cached_modified.clone(),
+            Some(LineChange::Removed) => self.cached_removed.clone(),
+            None => self.cached_none.clone(),
+        }
+    }
+
+    fn width(&self) -> usize {
+        1
     }
 }
 
@@ -73,17 +333,17 @@ pub struct GridBorderDecoration {
 }
 
 impl GridBorderDecoration {
-    pub fn new(colors: &Colors) -> Self {
+    pub fn new(colors: &Colors, vertical: char) -> Self {
         GridBorderDecoration {
             cached: DecorationText {
-                text: colors.grid.paint("│").to_string(),
+                text: colors.grid.paint(vertical.to_string()).to_string(),
                 width: 1,
             },
         }
     }
 }
 
-impl Decoration for GridBorderDecoration {
+impl PanelDecoration for GridBorderDecoration {
     fn generate(
         &self,
         _line_number: usize,
@@ -97,3 +357,30 @@ impl Decoration for GridBorderDecoration {
         self.cached.width
     }
 }
+
+pub fn format_line_number(format: Option<&LineNumberFormat>, line_number: usize) -> String {
+    match format {
+        Some(format) => (format.0)(line_number),
+        None => line_number.to_string(),
+    }
+}
+
+/// The line number, or the range of line numbers of a run of `repeats` identical lines, e.g.
+/// `12-15`.
+pub fn format_line_range(
+    format: Option<&LineNumberFormat>,
+    line_number: usize,
+    repeats: usize,
+) -> String {
+    let number = format_line_number(format, line_number);
+    if repeats > 1 {
+        let last = format_line_number(format, line_number + repeats - 1);
+        format!("{}-{}", number, last)
+    } else {
+        number
+    }
+}
+
+fn right_align(text: &str, width: usize) -> String {
+    format!("{}{}", " ".repeat(width.saturating_sub(text.width())), text)
+}
@@ -0,0 +1,345 @@
+use std::path::Path;
+
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, FontStyle, Style, Theme};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+
+use crate::injection::Regions;
+
+/// Name of the syntax which triggers diff rendering.
+pub const DIFF_SYNTAX: &str = "Diff";
+
+/// Added lines: their markers, and all of the line if the language of the file is unknown.
+const ADDED: Color = Color {
+    r: 0x5f,
+    g: 0xd7,
+    b: 0x5f,
+    a: 0xff,
+};
+
+/// Removed lines: their markers, and all of the line if the language of the file is unknown.
+const REMOVED: Color = Color {
+    r: 0xff,
+    g: 0x5f,
+    b: 0x5f,
+    a: 0xff,
+};
+
+/// Hunk headers, e.g. `@@ -1,2 +1,2 @@`.
+const HUNK_HEADER: Color = Color {
+    r: 0x5f,
+    g: 0xd7,
+    b: 0xd7,
+    a: 0xff,
+};
+
+/// Restyles all regions with the `color`.
+fn colored(regions: Regions, color: Color) -> Regions {
+    regions
+        .into_iter()
+        .map(|(style, text)| {
+            (
+                Style {
+                    foreground: color,
+                    ..style
+                },
+                text,
+            )
+        })
+        .collect()
+}
+
+/// Makes all regions bold.
+fn bold(regions: Regions) -> Regions {
+    regions
+        .into_iter()
+        .map(|(style, text)| {
+            (
+                Style {
+                    font_style: style.font_style | FontStyle::BOLD,
+                    ..style
+                },
+                text,
+            )
+        })
+        .collect()
+}
+
+/// Highlights the code inside the hunks of a unified diff with the language of the patched
+/// file. Markers of added and removed lines are green and red, as is all of the line if the
+/// language is unknown, hunk headers are cyan and file headers bold.
+pub struct DiffHighlighter<'a> {
+    theme: &'a Theme,
+    syntax_set: &'a SyntaxSet,
+    old_path: Option<String>,
+    syntax: Option<&'a SyntaxReference>,
+    // Removed and added lines are highlighted separately, as they belong to different versions
+    // of the file. Context lines are part of both.
+    old: Option<HighlightLines<'a>>,
+    new: Option<HighlightLines<'a>>,
+    in_hunk: bool,
+    /// The removed and added lines left in the hunk, from its header. Until they are used up,
+    /// lines starting with `---` or `+++` are removed or added lines, not file headers.
+    remaining: Option<(usize, usize)>,
+}
+
+impl<'a> DiffHighlighter<'a> {
+    pub fn new(theme: &'a Theme, syntax_set: &'a SyntaxSet) -> Self {
+        DiffHighlighter {
+            theme,
+            syntax_set,
+            old_path: None,
+            syntax: None,
+            old: None,
+            new: None,
+            in_hunk: false,
+            remaining: None,
+        }
+    }
+
+    /// Takes a line together with the `regions` of the diff syntax and highlights the code of
+    /// hunk lines in the language of the patched file.
+    pub fn highlight<'b>(&mut self, line: &'b str, regions: Regions<'b>) -> Regions<'b> {
+        let syntax_set = self.syntax_set;
+        let in_counted_hunk = self.in_hunk && self.remaining.is_some();
+
+        if in_counted_hunk {
+            self.count(line);
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            self.old_path = Some(patched_path(path));
+            self.in_hunk = false;
+            return bold(regions);
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            let path = match patched_path(path) {
+                ref path if path == "/dev/null" => self.old_path.take().unwrap_or_default(),
+                path => path,
+            };
+            self.syntax = syntax_for_path(&path, syntax_set);
+            self.in_hunk = false;
+            return bold(regions);
+        } else if line.starts_with("@@") {
+            // Hunks don't have to start at a syntactically sensible position, so every hunk
+            // starts fresh.
+            match self.syntax {
+                Some(syntax) => {
+                    self.old = Some(HighlightLines::new(syntax, self.theme));
+                    self.new = Some(HighlightLines::new(syntax, self.theme));
+                }
+                None => {
+                    self.old = None;
+                    self.new = None;
+                }
+            }
+            self.in_hunk = true;
+            self.remaining = hunk_lengths(line);
+            return colored(regions, HUNK_HEADER);
+        }
+
+        if !self.in_hunk || line.is_empty() {
+            return regions;
+        }
+
+        let (marker, code) = line.split_at(1);
+        let color = match marker {
+            "+" => Some(ADDED),
+            "-" => Some(REMOVED),
+            _ => None,
+        };
+        let (old, new) = match (self.old.as_mut(), self.new.as_mut()) {
+            (Some(old), Some(new)) => (old, new),
+            _ => {
+                if !matches!(marker, "+" | "-" | " " | "\\") {
+                    self.in_hunk = false;
+                }
+                return match color {
+                    Some(color) => colored(regions, color),
+                    None => regions,
+                };
+            }
+        };
+
+        let code_regions = match marker {
+            "+" => new.highlight(code, syntax_set),
+            "-" => old.highlight(code, syntax_set),
+            " " => {
+                old.highlight(code, syntax_set);
+                new.highlight(code, syntax_set)
+            }
+            "\\" => return regions,
+            _ => {
+                self.in_hunk = false;
+                return regions;
+            }
+        };
+
+        let mut marker_style = regions.first().map(|&(style, _)| style).unwrap_or_default();
+        if let Some(color) = color {
+            marker_style.foreground = color;
+        }
+        let mut output = Vec::with_capacity(code_regions.len() + 1);
+        output.push((marker_style, marker));
+        output.extend(code_regions);
+        output
+    }
+
+    /// Counts a line of the hunk against its lengths, and ends the hunk once they are used up.
+    fn count(&mut self, line: &str) {
+        if let Some((old, new)) = self.remaining.as_mut() {
+            match line.as_bytes().first() {
+                Some(b'-') => *old = old.saturating_sub(1),
+                Some(b'+') => *new = new.saturating_sub(1),
+                Some(b'\\') => {}
+                _ => {
+                    *old = old.saturating_sub(1);
+                    *new = new.saturating_sub(1);
+                }
+            }
+            if (*old, *new) == (0, 0) {
+                self.remaining = None;
+            }
+        }
+    }
+}
+
+/// The lengths of the old and new side of a hunk from its header, e.g. `@@ -1,3 +1,4 @@` →
+/// `(3, 4)`. A length that's left out is one.
+fn hunk_lengths(header: &str) -> Option<(usize, usize)> {
+    let mut ranges = header.strip_prefix("@@ ")?.split(' ');
+    let length = |range: Option<&str>, sign: char| -> Option<usize> {
+        match range?.strip_prefix(sign)?.split_once(',') {
+            Some((_, length)) => length.parse().ok(),
+            None => Some(1),
+        }
+    };
+    let old = length(ranges.next(), '-')?;
+    let new = length(ranges.next(), '+')?;
+    if (old, new) == (0, 0) {
+        return None;
+    }
+    Some((old, new))
+}
+
+/// Extracts the path from a `---`/`+++` header, e.g. `b/src/main.rs\t2019-01-01` → `src/main.rs`.
+fn patched_path(header: &str) -> String {
+    let path = header.trim_end_matches(['\r', '\n']);
+    let path = path.split('\t').next().unwrap_or(path);
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    path.to_string()
+}
+
+fn syntax_for_path<'a>(path: &str, syntax_set: &'a SyntaxSet) -> Option<&'a SyntaxReference> {
+    let path = Path::new(path);
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let extension = path.extension().and_then(|x| x.to_str()).unwrap_or("");
+
+    syntax_set
+        .find_syntax_by_extension(file_name)
+        .or_else(|| syntax_set.find_syntax_by_extension(extension))
+        .filter(|syntax| syntax.name != DIFF_SYNTAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::assets::HighlightingAssets;
+    use crate::injection::LineHighlighter;
+
+    #[test]
+    fn header_paths() {
+        assert_eq!("src/main.rs", patched_path("b/src/main.rs\n"));
+        assert_eq!("main.rs", patched_path("main.rs\t2019-01-01 10:00:00\n"));
+        assert_eq!("/dev/null", patched_path("/dev/null\n"));
+    }
+
+    #[test]
+    fn hunk_header_lengths() {
+        assert_eq!(Some((2, 3)), hunk_lengths("@@ -1,2 +1,3 @@ fn main() {\n"));
+        assert_eq!(Some((1, 1)), hunk_lengths("@@ -1 +1 @@\n"));
+        assert_eq!(Some((0, 1)), hunk_lengths("@@ -0,0 +1 @@\n"));
+        assert_eq!(None, hunk_lengths("@@ nonsense @@\n"));
+    }
+
+    #[test]
+    fn dashes_inside_hunks() {
+        let assets = HighlightingAssets::new();
+        let theme = assets.get_theme("Monokai Extended");
+        let syntax = assets.syntax_set.find_syntax_by_name(DIFF_SYNTAX).unwrap();
+        let mut highlighter = LineHighlighter::new(syntax, theme, &[], &assets.syntax_set);
+
+        // A removed `-- x` and an added `++ y` line of a Lua file look like file headers.
+        let lines = [
+            "--- a/main.lua\n",
+            "+++ b/main.lua\n",
+            "@@ -1,2 +1,2 @@\n",
+            "--- x\n",
+            "+++ y\n",
+            " print(1)\n",
+            "--- a/other.lua\n",
+        ];
+        let regions: Vec<_> = lines
+            .iter()
+            .map(|line| highlighter.highlight(line, &assets.syntax_set))
+            .collect();
+
+        assert_eq!(
+            ("-", REMOVED),
+            (regions[3][0].1, regions[3][0].0.foreground)
+        );
+        assert_eq!(("+", ADDED), (regions[4][0].1, regions[4][0].0.foreground));
+        assert!(!regions[3][1].0.font_style.contains(FontStyle::BOLD));
+        assert!(regions[6][0].0.font_style.contains(FontStyle::BOLD));
+    }
+
+    #[test]
+    fn highlight_hunks() {
+        let assets = HighlightingAssets::new();
+        let theme = assets.get_theme("Monokai Extended");
+        let syntax = assets.syntax_set.find_syntax_by_name(DIFF_SYNTAX).unwrap();
+        let mut highlighter = LineHighlighter::new(syntax, theme, &[], &assets.syntax_set);
+
+        let lines = [
+            "--- a/src/main.rs\n",
+            "+++ b/src/main.rs\n",
+            "@@ -1,2 +1,2 @@\n",
+            " fn main() {\n",
+            "-    println!(\"a\");\n",
+            "+    println!(\"b\");\n",
+        ];
+        let regions: Vec<_> = lines
+            .iter()
+            .map(|line| highlighter.highlight(line, &assets.syntax_set))
+            .collect();
+
+        assert_eq!("+", regions[5][0].1);
+        assert_eq!(ADDED, regions[5][0].0.foreground);
+        assert!(regions[5].len() > 3);
+        assert!(regions[3].len() > 2);
+        assert_eq!(HUNK_HEADER, regions[2][0].0.foreground);
+        assert!(regions[0][0].0.font_style.contains(FontStyle::BOLD));
+    }
+
+    #[test]
+    fn highlight_unknown_languages() {
+        let assets = HighlightingAssets::new();
+        let theme = assets.get_theme("Monokai Extended");
+        let syntax = assets.syntax_set.find_syntax_by_name(DIFF_SYNTAX).unwrap();
+        let mut highlighter = LineHighlighter::new(syntax, theme, &[], &assets.syntax_set);
+
+        let lines = ["@@ -1 +1 @@\n", "-a b\n", "+a c\n"];
+        let regions: Vec<_> = lines
+            .iter()
+            .map(|line| highlighter.highlight(line, &assets.syntax_set))
+            .collect();
+
+        assert!(regions[1]
+            .iter()
+            .all(|(style, _)| style.foreground == REMOVED));
+        assert!(regions[2]
+            .iter()
+            .all(|(style, _)| style.foreground == ADDED));
+    }
+}
@@ -1,6 +1,14 @@
+use std::env;
+use std::path::PathBuf;
+
 use crate::directories::ProjectDirs;
 
 lazy_static! {
     pub static ref PROJECT_DIRS: ProjectDirs =
         ProjectDirs::from("", "", crate_name!()).expect("Could not get home directory");
+
+    /// Cache of `bat`, which stores its assets in the same format.
+    pub static ref BAT_CACHE_DIR: Option<PathBuf> = env::var_os("BAT_CACHE_PATH")
+        .map(PathBuf::from)
+        .or_else(|| ProjectDirs::from("", "", "bat").map(|dirs| dirs.cache_dir().to_path_buf()));
 }
@@ -0,0 +1,85 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::decorations::LineNumberFormat;
+use crate::metadata;
+
+pub type SizeFn = dyn Fn(u64) -> String + Send + Sync;
+pub type TimeFn = dyn Fn(SystemTime) -> String + Send + Sync;
+
+/// Formats the numbers and dates of the decorations, e.g. to localize them: the line numbers in
+/// the gutter and the size and modification time of files in the header. Hooks which aren't set
+/// keep the built-in formats.
+#[derive(Clone, Default)]
+pub struct Formatting {
+    pub(crate) line_number: Option<LineNumberFormat>,
+    size: Option<Arc<SizeFn>>,
+    modified: Option<Arc<TimeFn>>,
+}
+
+impl Formatting {
+    pub fn new() -> Self {
+        Formatting::default()
+    }
+
+    /// Line numbers, like `.line_number_format()`.
+    pub fn line_number<F>(mut self, format: F) -> Self
+    where
+        F: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        self.line_number = Some(LineNumberFormat(Arc::new(format)));
+        self
+    }
+
+    /// File sizes in bytes, e.g. `1.5 KiB` by default.
+    pub fn size<F>(mut self, format: F) -> Self
+    where
+        F: Fn(u64) -> String + Send + Sync + 'static,
+    {
+        self.size = Some(Arc::new(format));
+        self
+    }
+
+    /// Modification times, e.g. `2019-01-01 10:00:00` (UTC) by default.
+    pub fn modified<F>(mut self, format: F) -> Self
+    where
+        F: Fn(SystemTime) -> String + Send + Sync + 'static,
+    {
+        self.modified = Some(Arc::new(format));
+        self
+    }
+
+    pub(crate) fn format_size(&self, bytes: u64) -> String {
+        match self.size {
+            Some(ref format) => format(bytes),
+            None => metadata::format_size(bytes),
+        }
+    }
+
+    pub(crate) fn format_modified(&self, modified: SystemTime) -> String {
+        match self.modified {
+            Some(ref format) => format(modified),
+            None => metadata::format_modified(modified),
+        }
+    }
+}
+
+impl fmt::Debug for Formatting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Formatting")
+    }
+}
+
+#[test]
+fn hooks() {
+    use std::time::UNIX_EPOCH;
+
+    let formatting = Formatting::new().size(|bytes| format!("{} octets", bytes));
+    assert_eq!("1536 octets", formatting.format_size(1536));
+    assert_eq!(
+        "1970-01-01 00:00:00",
+        formatting.format_modified(UNIX_EPOCH)
+    );
+    assert!(formatting.line_number.is_none());
+}
@@ -0,0 +1,97 @@
+const BYTES_PER_ROW: usize = 16;
+
+/// Collects binary input and splits it into fixed-size hexdump rows.
+#[derive(Default)]
+pub struct Hexdump {
+    offset: usize,
+    pending: Vec<u8>,
+}
+
+impl Hexdump {
+    pub fn new() -> Self {
+        Hexdump::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+    }
+
+    /// Skips `len` bytes of the input, which moves the offset of the rows after them. The
+    /// pending bytes have to be taken as rows first.
+    pub fn skip(&mut self, len: usize) {
+        debug_assert!(self.pending.is_empty());
+        self.offset += len;
+    }
+
+    /// Returns the offset and content of the next complete row. If `flush` is set, a trailing
+    /// partial row is returned as well.
+    pub fn next_row(&mut self, flush: bool) -> Option<(usize, Vec<u8>)> {
+        if self.pending.len() < BYTES_PER_ROW && (!flush || self.pending.is_empty()) {
+            return None;
+        }
+
+        let len = self.pending.len().min(BYTES_PER_ROW);
+        let row: Vec<u8> = self.pending.drain(..len).collect();
+        let offset = self.offset;
+        self.offset += len;
+
+        Some((offset, row))
+    }
+}
+
+/// Formats a row as hex bytes followed by an ASCII column, like `hexdump -C`.
+pub fn format_row(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(BYTES_PER_ROW * 3 + 1);
+    for i in 0..BYTES_PER_ROW {
+        if i == BYTES_PER_ROW / 2 {
+            hex.push(' ');
+        }
+        match bytes.get(i) {
+            Some(b) => hex.push_str(&format!("{:02x} ", b)),
+            None => hex.push_str("   "),
+        }
+    }
+
+    let ascii: String = bytes
+        .iter()
+        .map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        })
+        .collect();
+
+    format!("{} {}", hex, ascii)
+}
+
+#[test]
+fn rows() {
+    let mut hexdump = Hexdump::new();
+    hexdump.push(&[0; 20]);
+
+    assert_eq!(Some((0, vec![0; 16])), hexdump.next_row(false));
+    assert_eq!(None, hexdump.next_row(false));
+    assert_eq!(Some((16, vec![0; 4])), hexdump.next_row(true));
+    assert_eq!(None, hexdump.next_row(true));
+}
+
+#[test]
+fn skipped_bytes() {
+    let mut hexdump = Hexdump::new();
+    hexdump.push(&[0; 4]);
+    assert_eq!(Some((0, vec![0; 4])), hexdump.next_row(true));
+
+    hexdump.skip(10);
+    hexdump.push(&[1; 2]);
+    assert_eq!(Some((14, vec![1; 2])), hexdump.next_row(true));
+}
+
+#[test]
+fn format() {
+    assert_eq!(
+        "48 69 00 0a                                       Hi..",
+        format_row(b"Hi\x00\n")
+    );
+}
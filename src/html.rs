@@ -0,0 +1,258 @@
+use std::fmt::Write;
+
+use std::str::FromStr;
+
+use syntect::highlighting::{Color, FontStyle, Highlighter, Style, Theme};
+use syntect::parsing::Scope;
+
+/// What the HTML output is wrapped in, see `.html_wrapper()`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum HtmlWrapper {
+    /// A standalone page
+    #[default]
+    Page,
+    /// A `<style>` element and the table, to be embedded into another page
+    Table,
+    /// Nothing, just the highlighted lines, e.g. for a `<pre>` of another page
+    None,
+}
+
+/// How the highlighted regions of HTML output are styled, see `.html_style()`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum HtmlStyle {
+    /// `style` attributes with the colors of the theme
+    #[default]
+    Inline,
+    /// Classes like `pp-keyword`, styled by the stylesheet of `.theme_stylesheet()`. Embedded
+    /// tables come without a `<style>` element, the page has to link the stylesheet.
+    Classes,
+}
+
+/// What happens to the tabs of HTML output, see `.html_tabs()`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum HtmlTabs {
+    /// Tabs stay tabs, which the stylesheet shows `tab_width` wide with `tab-size`, so that
+    /// copied code keeps them
+    #[default]
+    Keep,
+    /// Tabs are expanded to spaces, like on the terminal
+    Expand,
+}
+
+impl HtmlTabs {
+    /// The `tab-size` of the stylesheet, if tabs are kept.
+    pub(crate) fn tab_size(self, tab_width: usize) -> Option<usize> {
+        match self {
+            HtmlTabs::Keep => Some(tab_width_or_default(tab_width)),
+            HtmlTabs::Expand => None,
+        }
+    }
+}
+
+/// The width of tabs in HTML output, where a `tab_width` of zero (keeping tabs on the terminal)
+/// means the default of browsers.
+pub(crate) fn tab_width_or_default(tab_width: usize) -> usize {
+    if tab_width == 0 {
+        8
+    } else {
+        tab_width
+    }
+}
+
+/// The classes of `HtmlStyle::Classes`: the class, the scopes it is given to and the scope whose
+/// style in the theme it gets. The first matching class wins.
+pub const CLASSES: &[(&str, &str, &str)] = &[
+    ("pp-comment", "comment", "comment"),
+    ("pp-string", "string", "string"),
+    ("pp-number", "constant.numeric", "constant.numeric"),
+    ("pp-constant", "constant", "constant"),
+    ("pp-operator", "keyword.operator", "keyword.operator"),
+    ("pp-keyword", "keyword", "keyword"),
+    ("pp-storage-type", "storage.type", "storage.type"),
+    ("pp-storage", "storage", "storage"),
+    (
+        "pp-function",
+        "entity.name.function, support.function, variable.function",
+        "entity.name.function",
+    ),
+    (
+        "pp-type",
+        "entity.name.type, entity.name.class, support.type, support.class",
+        "entity.name.type",
+    ),
+    ("pp-tag", "entity.name.tag", "entity.name.tag"),
+    (
+        "pp-attribute",
+        "entity.other.attribute-name",
+        "entity.other.attribute-name",
+    ),
+    ("pp-parameter", "variable.parameter", "variable.parameter"),
+    ("pp-variable", "variable", "variable"),
+    ("pp-inserted", "markup.inserted", "markup.inserted"),
+    ("pp-deleted", "markup.deleted", "markup.deleted"),
+    ("pp-heading", "markup.heading", "markup.heading"),
+    ("pp-punctuation", "punctuation", "punctuation"),
+];
+
+/// The part of a standalone page in front of its title and stylesheet.
+pub const START_HTML: &str = "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n";
+
+/// The end of a standalone page.
+pub const END_HTML: &str = "</body>\n</html>\n";
+
+/// Grid lines, like the gutter color of themes without one.
+const DEFAULT_GRID_COLOR: &str = "#444444";
+
+/// Escapes the characters with a meaning in HTML text and attribute values.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A CSS color. Colors with an alpha of zero encode terminal colors (e.g. in the `ansi` theme),
+/// which have no counterpart in HTML.
+pub fn css_color(color: Color) -> Option<String> {
+    if color.a == 0 {
+        None
+    } else {
+        Some(format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b))
+    }
+}
+
+/// The inline style of a highlighted region, e.g. `color:#f92672;font-weight:bold`.
+pub fn inline_style(style: Style) -> String {
+    let mut css = String::new();
+    if let Some(color) = css_color(style.foreground) {
+        write!(css, "color:{};", color).ok();
+    }
+    if style.font_style.contains(FontStyle::BOLD) {
+        css.push_str("font-weight:bold;");
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        css.push_str("font-style:italic;");
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        css.push_str("text-decoration:underline;");
+    }
+    css.pop();
+    css
+}
+
+/// The stylesheet of a page: the colors of `theme` and the layout of the table with the header,
+/// the line numbers and the grid. Kept tabs are shown `tab_size` wide. The colors apply to the
+/// whole page, or only to the table if it's embedded into another page.
+pub fn stylesheet(theme: &Theme, tab_size: Option<usize>, page: bool) -> String {
+    let settings = &theme.settings;
+    let mut css = String::from(if page {
+        "body {"
+    } else {
+        "table.prettyprint {"
+    });
+    if let Some(color) = settings.background.and_then(css_color) {
+        write!(css, " background-color: {};", color).ok();
+    }
+    if let Some(color) = settings.foreground.and_then(css_color) {
+        write!(css, " color: {};", color).ok();
+    }
+    css.push_str(" }\n");
+
+    let grid = settings
+        .gutter_foreground
+        .and_then(css_color)
+        .unwrap_or_else(|| DEFAULT_GRID_COLOR.to_string());
+    let tab_size = tab_size.map_or_else(String::new, |size| format!(" tab-size: {};", size));
+    write!(
+        css,
+        "table.prettyprint {{ border-collapse: collapse; font-family: monospace; }}\n\
+         table.prettyprint th {{ font-weight: normal; text-align: left; padding: 0 0.5em; }}\n\
+         table.prettyprint td {{ padding: 0 0.5em; vertical-align: top; white-space: pre;{tab} \
+         }}\n\
+         table.prettyprint td.pp-number {{ color: {grid}; text-align: right; \
+         user-select: none; }}\n\
+         table.prettyprint td.pp-number a {{ color: inherit; text-decoration: none; }}\n\
+         table.prettyprint .pp-filename {{ font-weight: bold; }}\n\
+         table.prettyprint .pp-snip {{ color: {grid}; }}\n\
+         table.pp-grid {{ border-top: 1px solid {grid}; border-bottom: 1px solid {grid}; }}\n\
+         table.pp-grid thead, table.pp-grid tfoot {{ border-bottom: 1px solid {grid}; \
+         border-top: 1px solid {grid}; }}\n\
+         table.pp-grid td.pp-number {{ border-right: 1px solid {grid}; }}\n",
+        tab = tab_size,
+        grid = grid
+    )
+    .ok();
+    css
+}
+
+/// The rules for the classes of `HtmlStyle::Classes`, with the styles `theme` gives their scopes.
+pub fn class_rules(theme: &Theme) -> String {
+    let highlighter = Highlighter::new(theme);
+    let default = highlighter.get_default();
+
+    let mut css = String::new();
+    for &(class, _, scope) in CLASSES {
+        let style = highlighter.style_for_stack(&[Scope::from_str(scope).unwrap()]);
+        if style == default {
+            continue;
+        }
+        writeln!(css, ".{} {{ {}; }}", class, inline_style(style)).ok();
+    }
+    css
+}
+
+#[test]
+fn escapes() {
+    assert_eq!(
+        "a &lt;b&gt; &amp; &quot;c&quot; &#39;d&#39;",
+        escape("a <b> & \"c\" 'd'")
+    );
+}
+
+#[test]
+fn inline_styles() {
+    let style = Style {
+        foreground: Color {
+            r: 0xf9,
+            g: 0x26,
+            b: 0x72,
+            a: 0xff,
+        },
+        font_style: FontStyle::BOLD | FontStyle::ITALIC,
+        ..Style::default()
+    };
+    assert_eq!(
+        "color:#f92672;font-weight:bold;font-style:italic",
+        inline_style(style)
+    );
+
+    let ansi = Style {
+        foreground: Color {
+            r: 2,
+            g: 0,
+            b: 0,
+            a: 0,
+        },
+        font_style: FontStyle::empty(),
+        ..Style::default()
+    };
+    assert_eq!("", inline_style(ansi));
+}
+
+#[test]
+fn classes() {
+    use syntect::highlighting::ThemeSet;
+
+    let themes = ThemeSet::load_defaults();
+    let css = class_rules(&themes.themes["InspiredGitHub"]);
+    assert!(css.contains(".pp-comment { color:#969896;font-style:italic; }\n"));
+    assert!(!css.contains(".pp-punctuation"));
+}
@@ -0,0 +1,575 @@
+use std::ops::Range;
+use std::str::FromStr;
+
+use syntect::easy::{HighlightLines, ScopeRegionIterator};
+use syntect::highlighting::{
+    HighlightIterator, HighlightState, Highlighter, ScopeSelectors, Style, Theme,
+};
+use syntect::parsing::{ParseState, ScopeStack, ScopeStackOp, SyntaxReference, SyntaxSet};
+
+use crate::diff::{DiffHighlighter, DIFF_SYNTAX};
+use crate::errors::*;
+use crate::parallel::{self, Ops, ParsedLines};
+
+pub type Regions<'b> = Vec<(Style, &'b str)>;
+
+/// Scopes that get highlighted by an injection if no selector is given: raw/code blocks that
+/// the host syntax didn't already hand over to an embedded grammar.
+pub const DEFAULT_INJECTION_SELECTOR: &str = "markup.raw - markup.raw.inline - source";
+
+const FENCE_SELECTOR: &str = "markup.raw.code-fence";
+const FENCE_TAG_SELECTOR: &str = "constant.other.language-name";
+
+const HEREDOC_SELECTOR: &str =
+    "string.unquoted.heredoc - keyword.control.heredoc-token - keyword.operator";
+const HEREDOC_TAG_SELECTOR: &str = "keyword.control.heredoc-token";
+
+const TEMPLATE_SELECTOR: &str = "string.template - punctuation.definition.string \
+                                 - variable.function.tagged-template \
+                                 - entity.name.function.tagged-template";
+const TEMPLATE_TAG_SELECTOR: &str =
+    "variable.function.tagged-template, entity.name.function.tagged-template";
+
+/// Highlights regions of a host language with another language.
+#[derive(Debug, Clone)]
+pub struct Injection {
+    /// Language whose regions get highlighted, or any language if `None`
+    pub host: Option<String>,
+    /// Scope selector for the embedded regions
+    pub selector: String,
+    /// Language used for the embedded regions
+    pub language: String,
+    /// Token which has to precede a region for it to be highlighted
+    pub tag: Option<Tag>,
+}
+
+/// A token like a heredoc delimiter (`<<SQL`) or a template literal tag (sql`...`).
+#[derive(Debug, Clone)]
+pub struct Tag {
+    /// Scope selector for the token
+    pub selector: String,
+    /// Text of the token
+    pub text: String,
+}
+
+impl Injection {
+    pub fn new(host: String, selector: String, language: String) -> Self {
+        Injection {
+            host: Some(host),
+            selector,
+            language,
+            tag: None,
+        }
+    }
+
+    /// Highlights the code fences of `host` tagged with `language` (e.g. ```` ```rust ````) as
+    /// `language`, even if the host has a grammar of its own for them.
+    pub fn code_fence(host: String, language: String) -> Self {
+        Injection {
+            host: Some(host),
+            selector: FENCE_SELECTOR.to_string(),
+            tag: Some(Tag {
+                selector: FENCE_TAG_SELECTOR.to_string(),
+                text: language.clone(),
+            }),
+            language,
+        }
+    }
+
+    /// Highlights the bodies of heredocs opened with `delimiter` as `language`.
+    pub fn heredoc(delimiter: String, language: String) -> Self {
+        Injection {
+            host: None,
+            selector: HEREDOC_SELECTOR.to_string(),
+            language,
+            tag: Some(Tag {
+                selector: HEREDOC_TAG_SELECTOR.to_string(),
+                text: delimiter,
+            }),
+        }
+    }
+
+    /// Highlights template literals tagged with `tag` as `language`.
+    pub fn tagged_template(tag: String, language: String) -> Self {
+        Injection {
+            host: None,
+            selector: TEMPLATE_SELECTOR.to_string(),
+            language,
+            tag: Some(Tag {
+                selector: TEMPLATE_TAG_SELECTOR.to_string(),
+                text: tag,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    /// Waiting for the tag
+    Idle,
+    /// The next matching region gets highlighted
+    Armed,
+    /// Inside of a highlighted region
+    Inside,
+}
+
+struct ActiveInjection<'a> {
+    selector: ScopeSelectors,
+    tag: Option<(ScopeSelectors, String)>,
+    syntax: &'a SyntaxReference,
+    theme: &'a Theme,
+    state: State,
+    // `None` while we are outside of an embedded region, so every region starts fresh.
+    lines: Option<HighlightLines<'a>>,
+}
+
+impl<'a> ActiveInjection<'a> {
+    fn leave(&mut self) {
+        self.lines = None;
+        self.state = if self.tag.is_some() {
+            State::Idle
+        } else {
+            State::Armed
+        };
+    }
+}
+
+/// A line-by-line highlighter like `HighlightLines`, which hands regions matched by an
+/// injection over to the injected language.
+pub struct LineHighlighter<'a> {
+    highlighter: Highlighter<'a>,
+    syntax: &'a SyntaxReference,
+    parse_state: ParseState,
+    highlight_state: HighlightState,
+    injections: Vec<ActiveInjection<'a>>,
+    diff: Option<DiffHighlighter<'a>>,
+    tracked: Vec<ScopeSelectors>,
+    /// Byte ranges of the last highlighted line matched by each tracked selector
+    pub tracked_spans: Vec<Vec<Range<usize>>>,
+    /// The following lines, parsed ahead
+    parsed: Option<ParsedLines>,
+}
+
+impl<'a> LineHighlighter<'a> {
+    pub fn new(
+        syntax: &'a SyntaxReference,
+        theme: &'a Theme,
+        injections: &[Injection],
+        syntax_set: &'a SyntaxSet,
+    ) -> Self {
+        let highlighter = Highlighter::new(theme);
+        let highlight_state = HighlightState::new(&highlighter, ScopeStack::new());
+
+        let injections = injections
+            .iter()
+            .filter(|injection| match injection.host {
+                Some(ref host) => syntax_set
+                    .find_syntax_by_token(host)
+                    .is_some_and(|host| host.name == syntax.name),
+                None => true,
+            })
+            // Injections of unknown languages, or with invalid selectors, are left out.
+            .filter_map(|injection| {
+                let syntax = syntax_set.find_syntax_by_token(&injection.language);
+                let selector = ScopeSelectors::from_str(&injection.selector).ok();
+                let tag = match injection.tag {
+                    Some(ref tag) => ScopeSelectors::from_str(&tag.selector)
+                        .ok()
+                        .map(|selector| Some((selector, tag.text.clone()))),
+                    None => Some(None),
+                };
+                let tag = tag?;
+                Some(ActiveInjection {
+                    selector: selector?,
+                    syntax: syntax?,
+                    theme,
+                    state: if tag.is_some() {
+                        State::Idle
+                    } else {
+                        State::Armed
+                    },
+                    tag,
+                    lines: None,
+                })
+            })
+            .collect();
+
+        let diff = if syntax.name == DIFF_SYNTAX {
+            Some(DiffHighlighter::new(theme, syntax_set))
+        } else {
+            None
+        };
+
+        LineHighlighter {
+            highlighter,
+            syntax,
+            parse_state: ParseState::new(syntax),
+            highlight_state,
+            injections,
+            diff,
+            tracked: Vec::new(),
+            tracked_spans: Vec::new(),
+            parsed: None,
+        }
+    }
+
+    /// Keeps track of the parts of each line matched by `selector`, e.g. the comments. Returns
+    /// the index of their spans in `tracked_spans`.
+    pub fn track(&mut self, selector: ScopeSelectors) -> usize {
+        self.tracked.push(selector);
+        self.tracked_spans.push(Vec::new());
+        self.tracked.len() - 1
+    }
+
+    /// Parses the following `lines` on `threads` threads. Their ops are used for as long as
+    /// the lines are highlighted in order.
+    pub fn parse_ahead(
+        &mut self,
+        lines: Vec<String>,
+        syntax_set: &SyntaxSet,
+        threads: usize,
+    ) -> Result<()> {
+        if let Some(parsed) = self.parsed.take() {
+            self.parse_state = parsed.state(syntax_set);
+        }
+        let parsed =
+            parallel::parse_lines(self.syntax, syntax_set, &self.parse_state, lines, threads)?;
+        self.parsed = Some(parsed);
+        Ok(())
+    }
+
+    /// Highlights the lines parsed ahead without consuming them, leaving out the injections.
+    pub fn regions_ahead(&self) -> Vec<Regions<'_>> {
+        let parsed = match self.parsed {
+            Some(ref parsed) => parsed,
+            None => return Vec::new(),
+        };
+        let mut state = self.highlight_state.clone();
+        parsed
+            .ahead()
+            .map(|(line, ops)| {
+                HighlightIterator::new(&mut state, ops, line, &self.highlighter).collect()
+            })
+            .collect()
+    }
+
+    pub fn highlight<'b>(&mut self, line: &'b str, syntax_set: &SyntaxSet) -> Regions<'b> {
+        let regions = self.highlight_injected(line, syntax_set);
+
+        match self.diff {
+            Some(ref mut diff) => diff.highlight(line, regions),
+            None => regions,
+        }
+    }
+
+    fn highlight_injected<'b>(&mut self, line: &'b str, syntax_set: &SyntaxSet) -> Regions<'b> {
+        let ops = self.parse_line(line, syntax_set);
+        for (spans, selector) in self.tracked_spans.iter_mut().zip(&self.tracked) {
+            *spans = matching_spans(line, &ops, self.highlight_state.path.clone(), selector);
+        }
+        let spans = self.injected_spans(line, &ops, self.highlight_state.path.clone());
+
+        let regions: Regions<'b> =
+            HighlightIterator::new(&mut self.highlight_state, &ops, line, &self.highlighter)
+                .collect();
+
+        if spans.is_empty() {
+            return regions;
+        }
+
+        let injected = spans
+            .into_iter()
+            .map(|(range, index)| {
+                let injection = &mut self.injections[index];
+                let (syntax, theme) = (injection.syntax, injection.theme);
+                let regions = injection
+                    .lines
+                    .get_or_insert_with(|| HighlightLines::new(syntax, theme))
+                    .highlight(&line[range.clone()], syntax_set);
+                (range, regions)
+            })
+            .collect();
+
+        splice(line, regions, injected)
+    }
+
+    fn parse_line(&mut self, line: &str, syntax_set: &SyntaxSet) -> Ops {
+        if let Some(ref mut parsed) = self.parsed {
+            if let Some(ops) = parsed.next(line) {
+                return ops;
+            }
+            self.parse_state = parsed.state(syntax_set);
+            self.parsed = None;
+        }
+        self.parse_state.parse_line(line, syntax_set)
+    }
+
+    /// Finds the byte ranges of `line` whose scopes are matched by an injection (and the index
+    /// of that injection). This also keeps track of tags and region boundaries.
+    fn injected_spans(
+        &mut self,
+        line: &str,
+        ops: &[(usize, ScopeStackOp)],
+        mut stack: ScopeStack,
+    ) -> Vec<(Range<usize>, usize)> {
+        let mut spans: Vec<(Range<usize>, usize)> = Vec::new();
+        if self.injections.is_empty() {
+            return spans;
+        }
+
+        let mut start = 0;
+        for (text, op) in ScopeRegionIterator::new(ops, line) {
+            let end = start + text.len();
+            stack.apply(op);
+
+            if !text.is_empty() {
+                let mut claimed = None;
+
+                for (index, injection) in self.injections.iter_mut().enumerate() {
+                    let scopes = stack.as_slice();
+                    let tag = injection.tag.as_ref().and_then(|(selector, tag)| {
+                        selector.does_match(scopes).map(|_| tag == trim_tag(text))
+                    });
+
+                    match tag {
+                        Some(true) if injection.state != State::Inside => {
+                            injection.state = State::Armed
+                        }
+                        Some(_) => injection.leave(),
+                        None if claimed.is_none()
+                            && injection.state != State::Idle
+                            && injection.selector.does_match(scopes).is_some() =>
+                        {
+                            injection.state = State::Inside;
+                            claimed = Some(index);
+                        }
+                        None if injection.state == State::Inside => injection.leave(),
+                        None => {}
+                    }
+                }
+
+                if let Some(index) = claimed {
+                    match spans.last_mut() {
+                        Some((range, i)) if *i == index && range.end == start => range.end = end,
+                        _ => spans.push((start..end, index)),
+                    }
+                }
+            }
+
+            start = end;
+        }
+
+        spans
+    }
+}
+
+/// The byte ranges of `line` whose scopes are matched by `selector`, adjacent ones merged.
+fn matching_spans(
+    line: &str,
+    ops: &[(usize, ScopeStackOp)],
+    mut stack: ScopeStack,
+    selector: &ScopeSelectors,
+) -> Vec<Range<usize>> {
+    let mut spans: Vec<Range<usize>> = Vec::new();
+    let mut start = 0;
+
+    for (text, op) in ScopeRegionIterator::new(ops, line) {
+        let end = start + text.len();
+        stack.apply(op);
+
+        if !text.is_empty() && selector.does_match(stack.as_slice()).is_some() {
+            match spans.last_mut() {
+                Some(range) if range.end == start => range.end = end,
+                _ => spans.push(start..end),
+            }
+        }
+
+        start = end;
+    }
+
+    spans
+}
+
+/// Strips quotes and heredoc modifiers, so that `<<-'SQL'` is tagged `SQL`.
+fn trim_tag(text: &str) -> &str {
+    text.trim_matches(|c: char| c.is_whitespace() || c == '\'' || c == '"' || c == '-' || c == '~')
+}
+
+/// Replaces the parts of `regions` covered by the injected ranges with their own regions.
+fn splice<'b>(
+    line: &'b str,
+    regions: Regions<'b>,
+    injected: Vec<(Range<usize>, Regions<'b>)>,
+) -> Regions<'b> {
+    let mut output = Vec::with_capacity(regions.len());
+    let mut injected = injected.into_iter().peekable();
+    let mut cursor = 0;
+    let mut end = 0;
+
+    for (style, text) in regions {
+        end += text.len();
+
+        while cursor < end {
+            match injected.peek() {
+                Some((range, _)) if range.start < end => {
+                    if cursor < range.start {
+                        output.push((style, &line[cursor..range.start]));
+                    }
+                    let (range, inner) = injected.next().unwrap();
+                    output.extend(inner);
+                    cursor = range.end;
+                }
+                _ => {
+                    output.push((style, &line[cursor..end]));
+                    cursor = end;
+                }
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::assets::HighlightingAssets;
+
+    fn texts<'b>(regions: &[(Style, &'b str)]) -> Vec<&'b str> {
+        regions.iter().map(|&(_, text)| text).collect()
+    }
+
+    /// Highlights `lines` with and without `injections`, returning the number of regions of
+    /// each line.
+    fn region_counts(
+        language: &str,
+        injections: &[Injection],
+        lines: &[&str],
+    ) -> Vec<(usize, usize)> {
+        let assets = HighlightingAssets::new();
+        let theme = assets.get_theme("Monokai Extended");
+        let syntax = assets.syntax_set.find_syntax_by_token(language).unwrap();
+
+        let mut plain = LineHighlighter::new(syntax, theme, &[], &assets.syntax_set);
+        let mut injected = LineHighlighter::new(syntax, theme, injections, &assets.syntax_set);
+
+        lines
+            .iter()
+            .map(|line| {
+                let plain_regions = plain.highlight(line, &assets.syntax_set);
+                let injected_regions = injected.highlight(line, &assets.syntax_set);
+                assert_eq!(line.to_string(), texts(&injected_regions).concat());
+                (plain_regions.len(), injected_regions.len())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn splice_regions() {
+        let line = "abcdef";
+        let style = Style::default();
+        let regions = vec![(style, "abc"), (style, "def")];
+        let injected = vec![(2..5, vec![(style, "cd"), (style, "e")])];
+
+        assert_eq!(
+            vec!["ab", "cd", "e", "f"],
+            texts(&splice(line, regions, injected))
+        );
+    }
+
+    #[test]
+    fn inject_into_markdown_code_block() {
+        let injections = vec![Injection::new(
+            "markdown".into(),
+            DEFAULT_INJECTION_SELECTOR.into(),
+            "rust".into(),
+        )];
+        let counts = region_counts(
+            "markdown",
+            &injections,
+            &["```\n", "fn main() {}\n", "```\n"],
+        );
+
+        assert_eq!(1, counts[1].0);
+        assert!(counts[1].1 > 1);
+    }
+
+    #[test]
+    fn inject_into_tagged_code_fence() {
+        let injections = vec![Injection::code_fence("markdown".into(), "rs".into())];
+        let counts = region_counts(
+            "markdown",
+            &injections,
+            &["```rs\n", "let x;\n", "```\n", "```\n", "let x;\n", "```\n"],
+        );
+
+        assert_eq!(1, counts[1].0);
+        assert!(counts[1].1 > 1);
+        assert_eq!(counts[4].0, counts[4].1);
+    }
+
+    #[test]
+    fn unknown_injected_languages() {
+        let injections = vec![Injection::new(
+            "markdown".into(),
+            DEFAULT_INJECTION_SELECTOR.into(),
+            "no such language".into(),
+        )];
+        let counts = region_counts("markdown", &injections, &["```\n", "let x;\n", "```\n"]);
+
+        assert_eq!(counts[1].0, counts[1].1);
+    }
+
+    #[test]
+    fn inject_into_heredoc() {
+        let injections = vec![Injection::heredoc("SQL".into(), "sql".into())];
+        let counts = region_counts(
+            "bash",
+            &injections,
+            &[
+                "cat <<EOF\n",
+                "SELECT 1;\n",
+                "EOF\n",
+                "cat <<SQL\n",
+                "SELECT 1;\n",
+                "SQL\n",
+            ],
+        );
+
+        assert_eq!(counts[1].0, counts[1].1);
+        assert_eq!(1, counts[4].0);
+        assert!(counts[4].1 > 1);
+    }
+
+    #[test]
+    fn inject_into_tagged_template() {
+        let injections = vec![Injection::tagged_template("sql".into(), "sql".into())];
+        let counts = region_counts(
+            "js",
+            &injections,
+            &["html`SELECT 1`;\n", "sql`SELECT 1`;\n"],
+        );
+
+        assert_eq!(counts[0].0, counts[0].1);
+        assert!(counts[1].1 > counts[1].0);
+    }
+
+    #[test]
+    fn tracked_spans() {
+        let assets = HighlightingAssets::new();
+        let theme = assets.get_theme("Monokai Extended");
+        let syntax = assets.syntax_set.find_syntax_by_token("rs").unwrap();
+        let selector = ScopeSelectors::from_str("comment, string").unwrap();
+        let mut highlighter = LineHighlighter::new(syntax, theme, &[], &assets.syntax_set);
+        let index = highlighter.track(selector);
+
+        let line = "let s = \"teh\"; // wrnog\n";
+        highlighter.highlight(line, &assets.syntax_set);
+        let spans: Vec<&str> = highlighter.tracked_spans[index]
+            .iter()
+            .map(|range| &line[range.clone()])
+            .collect();
+        assert_eq!(vec!["\"teh\"", "// wrnog\n"], spans);
+    }
+}
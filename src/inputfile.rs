@@ -1,20 +1,43 @@
-use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::mem;
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+use std::path::PathBuf;
 
 use content_inspector::{self, ContentType};
 
 use crate::errors::*;
 
+/// The first line is read up to this many bytes to detect the content type and the syntax, as
+/// the input may have no line breaks at all.
+const FIRST_LINE_BYTES: u64 = 64 * 1024;
+
 pub struct InputFileReader<'a> {
     inner: Box<dyn BufRead + 'a>,
     pub first_line: Vec<u8>,
     pub content_type: ContentType,
+    /// Longer lines are read in pieces of at most this many bytes, which end at a character of
+    /// text
+    pub max_line_length: Option<usize>,
+    /// Whether the line goes on in the next piece
+    pub continued: bool,
+    /// Whether the first line goes on after `FIRST_LINE_BYTES`
+    first_line_cut: bool,
+    /// The bytes of the line which are read but not returned yet, e.g. the start of a character
+    /// that didn't fit into the last piece
+    cut_off: Vec<u8>,
 }
 
 impl<'a> InputFileReader<'a> {
-    fn new<R: BufRead + 'a>(mut reader: R) -> InputFileReader<'a> {
+    pub fn new<R: BufRead + 'a>(mut reader: R) -> InputFileReader<'a> {
         let mut first_line = vec![];
-        reader.read_until(b'\n', &mut first_line).ok();
+        (&mut reader)
+            .take(FIRST_LINE_BYTES)
+            .read_until(b'\n', &mut first_line)
+            .ok();
+        let first_line_cut =
+            first_line.len() as u64 == FIRST_LINE_BYTES && first_line.last() != Some(&b'\n');
 
         let content_type = content_inspector::inspect(&first_line[..]);
 
@@ -26,12 +49,30 @@ impl<'a> InputFileReader<'a> {
             inner: Box::new(reader),
             first_line,
             content_type,
+            max_line_length: None,
+            continued: false,
+            first_line_cut,
+            cut_off: vec![],
         }
     }
 
     pub fn read_line(&mut self, buf: &mut Vec<u8>) -> io::Result<bool> {
+        if self.first_line_cut {
+            // The first line is returned with the rest of it, or in pieces.
+            self.first_line_cut = false;
+            self.cut_off = mem::take(&mut self.first_line);
+        }
+
         if self.first_line.is_empty() {
-            let res = self.inner.read_until(b'\n', buf).map(|size| size > 0)?;
+            let res = match self.max_line_length {
+                Some(length) => self.read_piece(buf, length)?,
+                None => {
+                    let start = buf.len();
+                    buf.append(&mut self.cut_off);
+                    self.inner.read_until(b'\n', buf)?;
+                    buf.len() > start
+                }
+            };
 
             if self.content_type == ContentType::UTF_16LE {
                 self.inner.read_until(0x00, buf).ok();
@@ -43,33 +84,194 @@ impl<'a> InputFileReader<'a> {
             Ok(true)
         }
     }
+
+    /// The bytes after the first line which are in the buffer, without using them up. If the
+    /// buffer is empty, it's filled first.
+    pub fn peek(&mut self) -> &[u8] {
+        self.inner.fill_buf().unwrap_or_default()
+    }
+
+    /// Reads the next piece of at most `length` bytes of a line, see `max_line_length`.
+    fn read_piece(&mut self, buf: &mut Vec<u8>, length: usize) -> io::Result<bool> {
+        let start = buf.len();
+        let carried = self.cut_off.len().min(length);
+        buf.extend(self.cut_off.drain(..carried));
+        if self.cut_off.is_empty() && buf.last() != Some(&b'\n') {
+            let left = length - carried;
+            (&mut self.inner).take(left as u64).read_until(b'\n', buf)?;
+        }
+
+        self.continued = buf.len() - start >= length && buf.last() != Some(&b'\n');
+        if self.continued && self.next_byte()? == Some(b'\n') {
+            // The line ends right after the piece.
+            if self.cut_off.is_empty() {
+                self.inner.consume(1);
+            } else {
+                self.cut_off.remove(0);
+            }
+            buf.push(b'\n');
+            self.continued = false;
+        }
+        // Binary content is cut anywhere.
+        if self.continued && self.content_type.is_text() {
+            let end = start + char_boundary(&buf[start..]);
+            let mut rest = buf.split_off(end);
+            rest.append(&mut self.cut_off);
+            self.cut_off = rest;
+        }
+
+        Ok(buf.len() > start)
+    }
+
+    /// The byte after the last piece, without using it up.
+    fn next_byte(&mut self) -> io::Result<Option<u8>> {
+        match self.cut_off.first() {
+            Some(&byte) => Ok(Some(byte)),
+            None => Ok(self.inner.fill_buf()?.first().copied()),
+        }
+    }
 }
 
+/// The length of `piece` without the UTF-8 character it ends in the middle of, if any.
+fn char_boundary(piece: &[u8]) -> usize {
+    let lead = piece
+        .iter()
+        .rev()
+        .take(4)
+        .position(|&byte| byte & 0xc0 != 0x80);
+    let start = match lead {
+        Some(back) if back + 1 < piece.len() => piece.len() - back - 1,
+        _ => return piece.len(),
+    };
+    let char_length = match piece[start] {
+        byte if byte >= 0xf0 => 4,
+        byte if byte >= 0xe0 => 3,
+        byte if byte >= 0xc0 => 2,
+        _ => 1,
+    };
+    if start + char_length > piece.len() {
+        start
+    } else {
+        piece.len()
+    }
+}
+
+/// The input of a print.
 #[derive(Debug, Clone, PartialEq)]
 pub enum InputFile {
     StdIn,
+    /// A file, by its path
     Ordinary(String),
     String(String),
+    Bytes(Vec<u8>),
 }
 
 impl InputFile {
-    pub fn get_reader(&self) -> Result<InputFileReader> {
+    /// The size of the input in bytes, if it's known before reading it.
+    pub fn size(&self) -> Option<usize> {
         match self {
-            InputFile::Ordinary(filename) => {
-                let file = File::open(filename)?;
+            InputFile::Ordinary(filename) => fs::metadata(filename)
+                .ok()
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| metadata.len() as usize),
+            InputFile::String(s) => Some(s.len()),
+            InputFile::Bytes(b) => Some(b.len()),
+            InputFile::StdIn => None,
+        }
+    }
+
+    /// Makes sure that an ordinary file can be read without surprises. Directories are always
+    /// refused. FIFOs, devices and sockets are refused unless `allow_special_files` is set, as
+    /// reading them may block or never end.
+    pub fn check(&self, allow_special_files: bool) -> Result<()> {
+        let filename = match self {
+            InputFile::Ordinary(filename) => filename,
+            _ => return Ok(()),
+        };
+
+        let file_type = fs::metadata(filename)
+            .map_err(|error| unreadable(filename, error))?
+            .file_type();
+        if file_type.is_dir() {
+            return Err(ErrorKind::UnreadableFile(
+                filename.to_string(),
+                "is a directory".to_string(),
+                Some("Print the files inside it one by one"),
+            )
+            .into());
+        }
+        if !file_type.is_file() && !allow_special_files {
+            return Err(ErrorKind::UnreadableFile(
+                filename.to_string(),
+                format!("is {}", special_file_kind(&file_type)),
+                Some("Use `.allow_special_files(true)` to read it anyway"),
+            )
+            .into());
+        }
+
+        Ok(())
+    }
 
-                if file.metadata()?.is_dir() {
-                    return Err(format!("'{}' is a directory.", filename).into());
+    /// The target of an ordinary file which is a symbolic link.
+    pub fn symlink_target(&self) -> Result<Option<PathBuf>> {
+        match self {
+            InputFile::Ordinary(filename) => {
+                let metadata =
+                    fs::symlink_metadata(filename).map_err(|error| unreadable(filename, error))?;
+                if metadata.file_type().is_symlink() {
+                    let target =
+                        fs::read_link(filename).map_err(|error| unreadable(filename, error))?;
+                    Ok(Some(target))
+                } else {
+                    Ok(None)
                 }
+            }
+            _ => Ok(None),
+        }
+    }
 
+    pub fn get_reader(&self) -> Result<InputFileReader<'_>> {
+        match self {
+            InputFile::Ordinary(filename) => {
+                let file = File::open(filename).map_err(|error| unreadable(filename, error))?;
                 Ok(InputFileReader::new(BufReader::new(file)))
             }
             InputFile::String(s) => Ok(InputFileReader::new(s.as_bytes())),
-            _ => unimplemented!(), // Used to be InputFile::Stdin
+            InputFile::Bytes(b) => Ok(InputFileReader::new(&b[..])),
+            InputFile::StdIn => Ok(InputFileReader::new(io::stdin().lock())),
         }
     }
 }
 
+/// Wraps an IO error on `filename` with the path and, if possible, a suggestion how to fix it.
+pub(crate) fn unreadable(filename: &str, error: io::Error) -> Error {
+    let suggestion = match error.kind() {
+        io::ErrorKind::NotFound => Some("Check the path and the working directory"),
+        io::ErrorKind::PermissionDenied => Some("Check the permissions of the file"),
+        _ => None,
+    };
+    let kind = ErrorKind::UnreadableFile(filename.to_string(), error.to_string(), suggestion);
+    Error::with_chain(error, kind)
+}
+
+#[cfg(unix)]
+fn special_file_kind(file_type: &fs::FileType) -> &'static str {
+    if file_type.is_fifo() {
+        "a FIFO"
+    } else if file_type.is_socket() {
+        "a socket"
+    } else if file_type.is_block_device() || file_type.is_char_device() {
+        "a device"
+    } else {
+        "not a regular file"
+    }
+}
+
+#[cfg(not(unix))]
+fn special_file_kind(_file_type: &fs::FileType) -> &'static str {
+    "not a regular file"
+}
+
 #[test]
 fn basic() {
     let content = b"#!/bin/bash\necho hello";
@@ -81,21 +283,21 @@ fn basic() {
 
     let res = reader.read_line(&mut buffer);
     assert!(res.is_ok());
-    assert_eq!(true, res.unwrap());
+    assert!(res.unwrap());
     assert_eq!(b"#!/bin/bash\n", &buffer[..]);
 
     buffer.clear();
 
     let res = reader.read_line(&mut buffer);
     assert!(res.is_ok());
-    assert_eq!(true, res.unwrap());
+    assert!(res.unwrap());
     assert_eq!(b"echo hello", &buffer[..]);
 
     buffer.clear();
 
     let res = reader.read_line(&mut buffer);
     assert!(res.is_ok());
-    assert_eq!(false, res.unwrap());
+    assert!(!res.unwrap());
     assert!(buffer.is_empty());
 }
 
@@ -110,20 +312,148 @@ fn utf16le() {
 
     let res = reader.read_line(&mut buffer);
     assert!(res.is_ok());
-    assert_eq!(true, res.unwrap());
+    assert!(res.unwrap());
     assert_eq!(b"\xFF\xFE\x73\x00\x0A\x00", &buffer[..]);
 
     buffer.clear();
 
     let res = reader.read_line(&mut buffer);
     assert!(res.is_ok());
-    assert_eq!(true, res.unwrap());
+    assert!(res.unwrap());
     assert_eq!(b"\x64\x00", &buffer[..]);
 
     buffer.clear();
 
     let res = reader.read_line(&mut buffer);
     assert!(res.is_ok());
-    assert_eq!(false, res.unwrap());
+    assert!(!res.unwrap());
     assert!(buffer.is_empty());
 }
+
+#[test]
+fn long_lines() {
+    let content = "first\nabcé\nabcd\nef".as_bytes();
+    let mut reader = InputFileReader::new(content);
+    reader.max_line_length = Some(4);
+
+    let mut pieces = vec![];
+    let mut buffer = vec![];
+    while reader.read_line(&mut buffer).unwrap() {
+        pieces.push((String::from_utf8(buffer.clone()).unwrap(), reader.continued));
+        buffer.clear();
+    }
+
+    let expected = [
+        ("first\n", false),
+        // `é` takes two bytes and is put into the next piece.
+        ("abc", true),
+        ("é\n", false),
+        ("abcd\n", false),
+        ("ef", false),
+    ];
+    let expected: Vec<_> = expected
+        .iter()
+        .map(|&(piece, continued)| (piece.to_string(), continued))
+        .collect();
+    assert_eq!(expected, pieces);
+}
+
+#[test]
+fn binary_pieces() {
+    let content = b"\x00\n\x00\xc3\x00";
+    let mut reader = InputFileReader::new(&content[..]);
+    reader.max_line_length = Some(2);
+
+    let mut pieces = vec![];
+    let mut buffer = vec![];
+    while reader.read_line(&mut buffer).unwrap() {
+        pieces.push(buffer.clone());
+        buffer.clear();
+    }
+
+    // Binary content is cut anywhere, even before what would be a UTF-8 character.
+    let expected: [&[u8]; 3] = [b"\x00\n", b"\x00\xc3", b"\x00"];
+    assert_eq!(&expected[..], &pieces[..]);
+}
+
+#[test]
+fn first_line_without_newline() {
+    let content = vec![b'a'; FIRST_LINE_BYTES as usize * 2 + 1];
+    let reader = InputFileReader::new(&content[..]);
+    assert_eq!(FIRST_LINE_BYTES as usize, reader.first_line.len());
+
+    let mut reader = InputFileReader::new(&content[..]);
+    let mut buffer = vec![];
+    assert!(reader.read_line(&mut buffer).unwrap());
+    assert_eq!(content, buffer);
+    buffer.clear();
+    assert!(!reader.read_line(&mut buffer).unwrap());
+
+    // The pieces go on across the end of the first read.
+    let piece = FIRST_LINE_BYTES as usize / 2 + 1;
+    let mut reader = InputFileReader::new(&content[..]);
+    reader.max_line_length = Some(piece);
+    let mut lengths = vec![];
+    while reader.read_line(&mut buffer).unwrap() {
+        lengths.push((buffer.len(), reader.continued));
+        buffer.clear();
+    }
+    let last = content.len() - 3 * piece;
+    assert_eq!(
+        vec![(piece, true), (piece, true), (piece, true), (last, false)],
+        lengths
+    );
+}
+
+#[test]
+fn special_files() {
+    let device = InputFile::Ordinary("/dev/null".to_string());
+    assert!(device.check(false).is_err());
+    assert!(device.check(true).is_ok());
+
+    assert!(InputFile::Ordinary("src".to_string()).check(true).is_err());
+    assert!(InputFile::Ordinary("src/lib.rs".to_string())
+        .check(false)
+        .is_ok());
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks() {
+    let link = std::env::temp_dir().join("prettyprint-symlink-test");
+    let _ = fs::remove_file(&link);
+    std::os::unix::fs::symlink("/etc/hostname", &link).unwrap();
+
+    let file = InputFile::Ordinary(link.to_string_lossy().into_owned());
+    assert_eq!(
+        Some(PathBuf::from("/etc/hostname")),
+        file.symlink_target().unwrap()
+    );
+    assert_eq!(
+        None,
+        InputFile::Ordinary("src/lib.rs".to_string())
+            .symlink_target()
+            .unwrap()
+    );
+
+    fs::remove_file(&link).unwrap();
+}
+
+#[test]
+fn unreadable_files() {
+    let error = InputFile::Ordinary("does/not/exist.rs".to_string())
+        .check(false)
+        .unwrap_err()
+        .to_string();
+    assert!(error.starts_with("Can not read 'does/not/exist.rs'"));
+    assert!(error.ends_with("Check the path and the working directory"));
+
+    let error = InputFile::Ordinary("src".to_string())
+        .check(false)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        "Can not read 'src': is a directory. Print the files inside it one by one",
+        error
+    );
+}
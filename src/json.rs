@@ -0,0 +1,89 @@
+use std::fmt::Write;
+
+use syntect::highlighting::{FontStyle, Style};
+
+use crate::html::css_color;
+
+/// A JSON string, with the characters which need it escaped.
+pub fn string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                write!(escaped, "\\u{:04x}", c as u32).ok();
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// A highlighted region, e.g. `{"text":"fn","foreground":"#f92672","font_style":["bold"]}`.
+/// Terminal colors (e.g. of the `ansi` theme) have no CSS color and give a `null` foreground.
+pub fn region(style: Style, text: &str) -> String {
+    let foreground = css_color(style.foreground)
+        .map(|color| string(&color))
+        .unwrap_or_else(|| "null".to_string());
+    let font_style: Vec<&str> = [
+        (FontStyle::BOLD, "\"bold\""),
+        (FontStyle::ITALIC, "\"italic\""),
+        (FontStyle::UNDERLINE, "\"underline\""),
+    ]
+    .iter()
+    .filter(|(flag, _)| style.font_style.contains(*flag))
+    .map(|&(_, name)| name)
+    .collect();
+    format!(
+        "{{\"text\":{},\"foreground\":{},\"font_style\":[{}]}}",
+        string(text),
+        foreground,
+        font_style.join(",")
+    )
+}
+
+/// The object of a line: its number, how often it repeats (if it does) and its regions.
+pub fn line(line_number: usize, repeats: usize, regions: &[String]) -> String {
+    let repeats = if repeats > 1 {
+        format!(",\"repeats\":{}", repeats)
+    } else {
+        String::new()
+    };
+    format!(
+        "{{\"line\":{}{},\"regions\":[{}]}}",
+        line_number,
+        repeats,
+        regions.join(",")
+    )
+}
+
+#[test]
+fn strings() {
+    assert_eq!(r#""a \"b\" \\ \n\t\u001b""#, string("a \"b\" \\ \n\t\x1b"));
+}
+
+#[test]
+fn regions() {
+    use syntect::highlighting::Color;
+
+    let style = Style {
+        foreground: Color {
+            r: 0xf9,
+            g: 0x26,
+            b: 0x72,
+            a: 0xff,
+        },
+        font_style: FontStyle::BOLD | FontStyle::ITALIC,
+        ..Style::default()
+    };
+    assert_eq!(
+        r##"{"line":3,"repeats":2,"regions":[{"text":"fn","foreground":"#f92672","font_style":["bold","italic"]}]}"##,
+        line(3, 2, &[region(style, "fn")])
+    );
+}
@@ -0,0 +1,113 @@
+use syntect::highlighting::{Color, FontStyle, Style, Theme};
+
+/// Grid and line numbers, like the gutter color of themes without one.
+const DEFAULT_GRID_COLOR: &str = "444444";
+
+/// Escapes the characters which are commands in the `Verbatim` environment of the output: with
+/// `commandchars=\\\{\}`, everything else is set as it is.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\char92{}"),
+            '{' => escaped.push_str("\\char123{}"),
+            '}' => escaped.push_str("\\char125{}"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A color for the `HTML` model of `xcolor`. Colors with an alpha of zero encode terminal colors
+/// (e.g. in the `ansi` theme), which have no counterpart in LaTeX.
+pub fn color(color: Color) -> Option<String> {
+    if color.a == 0 {
+        None
+    } else {
+        Some(format!("{:02X}{:02X}{:02X}", color.r, color.g, color.b))
+    }
+}
+
+/// A highlighted region, e.g. `\textcolor[HTML]{F92672}{\textbf{fn}}`.
+pub fn command(style: Style, text: &str) -> String {
+    let mut command = escape(text);
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        command = format!("\\underline{{{}}}", command);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        command = format!("\\textit{{{}}}", command);
+    }
+    if style.font_style.contains(FontStyle::BOLD) {
+        command = format!("\\textbf{{{}}}", command);
+    }
+    match color(style.foreground) {
+        Some(color) => format!("\\textcolor[HTML]{{{}}}{{{}}}", color, command),
+        None => command,
+    }
+}
+
+/// The color of the line numbers, the snip and, for the `grid`, the frame.
+pub fn grid_color(theme: &Theme) -> String {
+    theme
+        .settings
+        .gutter_foreground
+        .and_then(color)
+        .unwrap_or_else(|| DEFAULT_GRID_COLOR.to_string())
+}
+
+/// The start of the listing, in the foreground color of `theme` and, for the `grid`, between
+/// lines.
+pub fn begin(theme: &Theme, grid: bool) -> String {
+    let mut options = String::from("commandchars=\\\\\\{\\}");
+    if let Some(color) = theme.settings.foreground.and_then(color) {
+        options.push_str(&format!(",formatcom=\\color[HTML]{{{}}}", color));
+    }
+    if grid {
+        options.push_str(&format!(
+            ",frame=lines,rulecolor=\\color[HTML]{{{}}}",
+            grid_color(theme)
+        ));
+    }
+    format!("\\begin{{Verbatim}}[{}]\n", options)
+}
+
+/// The end of the listing.
+pub const END: &str = "\\end{Verbatim}\n";
+
+#[test]
+fn escapes() {
+    assert_eq!(
+        "\\char92{}n \\char123{}$x\\char125{} 100%",
+        escape("\\n {$x} 100%")
+    );
+}
+
+#[test]
+fn commands() {
+    let style = Style {
+        foreground: Color {
+            r: 0xf9,
+            g: 0x26,
+            b: 0x72,
+            a: 0xff,
+        },
+        font_style: FontStyle::BOLD | FontStyle::ITALIC,
+        ..Style::default()
+    };
+    assert_eq!(
+        "\\textcolor[HTML]{F92672}{\\textbf{\\textit{a\\char123{}}}}",
+        command(style, "a{")
+    );
+
+    let ansi = Style {
+        foreground: Color {
+            r: 2,
+            g: 0,
+            b: 0,
+            a: 0,
+        },
+        font_style: FontStyle::empty(),
+        ..Style::default()
+    };
+    assert_eq!("fn", command(ansi, "fn"));
+}
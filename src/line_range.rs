@@ -9,8 +9,8 @@ pub struct LineRange {
 impl Default for LineRange {
     fn default() -> Self {
         LineRange {
-            lower: usize::min_value(),
-            upper: usize::max_value(),
+            lower: usize::MIN,
+            upper: usize::MAX,
         }
     }
 }
@@ -27,7 +27,7 @@ impl LineRange {
     pub fn parse_range(range_raw: &str) -> Result<LineRange> {
         let mut new_range = LineRange::new();
 
-        if range_raw.bytes().nth(0).ok_or("Empty line range")? == b':' {
+        if range_raw.bytes().next().ok_or("Empty line range")? == b':' {
             new_range.upper = range_raw[1..].parse()?;
             return Ok(new_range);
         } else if range_raw.bytes().last().ok_or("Empty line range")? == b':' {
@@ -60,7 +60,7 @@ fn test_parse_full() {
 #[test]
 fn test_parse_partial_min() {
     let range = LineRange::from(":50").expect("Shouldn't fail on test!");
-    assert_eq!(usize::min_value(), range.lower);
+    assert_eq!(usize::MIN, range.lower);
     assert_eq!(50, range.upper);
 }
 
@@ -68,7 +68,7 @@ fn test_parse_partial_min() {
 fn test_parse_partial_max() {
     let range = LineRange::from("40:").expect("Shouldn't fail on test!");
     assert_eq!(40, range.lower);
-    assert_eq!(usize::max_value(), range.upper);
+    assert_eq!(usize::MAX, range.upper);
 }
 
 #[test]
@@ -84,6 +84,7 @@ fn test_parse_fail() {
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub enum RangeCheckResult {
     // Within one of the given ranges
     InRange,
@@ -105,18 +106,14 @@ impl Default for LineRanges {
     fn default() -> Self {
         LineRanges {
             ranges: vec![LineRange::default()],
-            largest_upper_bound: usize::max_value(),
+            largest_upper_bound: usize::MAX,
         }
     }
 }
 
 impl LineRanges {
     pub fn from(ranges: Vec<LineRange>) -> LineRanges {
-        let largest_upper_bound = ranges
-            .iter()
-            .map(|r| r.upper)
-            .max()
-            .unwrap_or(usize::max_value());
+        let largest_upper_bound = ranges.iter().map(|r| r.upper).max().unwrap_or(usize::MAX);
         LineRanges {
             ranges,
             largest_upper_bound,
@@ -124,18 +121,12 @@ impl LineRanges {
     }
 
     pub fn check(&self, line: usize) -> RangeCheckResult {
-        if self.ranges.is_empty() {
+        if self.ranges.is_empty() || self.ranges.iter().any(|r| r.is_inside(line)) {
             RangeCheckResult::InRange
+        } else if line < self.largest_upper_bound {
+            RangeCheckResult::OutsideRange
         } else {
-            if self.ranges.iter().any(|r| r.is_inside(line)) {
-                RangeCheckResult::InRange
-            } else {
-                if line < self.largest_upper_bound {
-                    RangeCheckResult::OutsideRange
-                } else {
-                    RangeCheckResult::AfterLastRange
-                }
-            }
+            RangeCheckResult::AfterLastRange
         }
     }
 }
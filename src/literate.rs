@@ -0,0 +1,265 @@
+use unicode_width::UnicodeWidthStr;
+
+/// How a language marks documentation comments.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DocSyntax {
+    /// Prefixes of documentation lines, e.g. `///`
+    lines: &'static [&'static str],
+    /// Delimiters of documentation blocks, e.g. `/**` and `*/`
+    block: Option<(&'static str, &'static str)>,
+}
+
+const C_LIKE: DocSyntax = DocSyntax {
+    lines: &["///", "//!"],
+    block: Some(("/**", "*/")),
+};
+
+const HASH: DocSyntax = DocSyntax {
+    lines: &["#"],
+    block: None,
+};
+
+/// The documentation comments of a language, by syntax name. Languages with `#` comments use
+/// all of them as prose, like literate programming tools do.
+pub fn doc_syntax(syntax_name: &str) -> Option<DocSyntax> {
+    match syntax_name {
+        "Rust" | "C" | "C++" | "C#" | "Java" | "JavaScript" | "JavaScript (Babel)"
+        | "TypeScript" | "TypeScriptReact" | "Go" | "Swift" | "Kotlin" | "Scala" | "PHP"
+        | "Objective-C" | "D" => Some(C_LIKE),
+        "Python"
+        | "Ruby"
+        | "Bourne Again Shell (bash)"
+        | "Perl"
+        | "R"
+        | "YAML"
+        | "TOML"
+        | "Makefile"
+        | "CoffeeScript"
+        | "Elixir"
+        | "Nim"
+        | "Julia" => Some(HASH),
+        "Haskell" | "Elm" => Some(DocSyntax {
+            lines: &["-- |", "--"],
+            block: Some(("{-|", "-}")),
+        }),
+        "Lua" => Some(DocSyntax {
+            lines: &["---"],
+            block: None,
+        }),
+        "Lisp" | "Clojure" => Some(DocSyntax {
+            lines: &[";;;", ";;"],
+            block: None,
+        }),
+        _ => None,
+    }
+}
+
+/// Collects the documentation comments of a file into paragraphs of prose.
+#[derive(Debug)]
+pub struct Prose {
+    syntax: DocSyntax,
+    in_block: bool,
+    in_fence: bool,
+    /// Lines of prose waiting to be printed, fenced code blocks verbatim
+    lines: Vec<ProseLine>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProseLine {
+    Text(String),
+    Verbatim(String),
+    Break,
+}
+
+impl Prose {
+    pub fn new(syntax: DocSyntax) -> Self {
+        Prose {
+            syntax,
+            in_block: false,
+            in_fence: false,
+            lines: Vec::new(),
+        }
+    }
+
+    /// Takes `line` if it is documentation, and returns whether it did.
+    pub fn take(&mut self, line: &str, line_number: usize) -> bool {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let trimmed = line.trim_start();
+
+        let text = if let (true, Some((_, end))) = (self.in_block, self.syntax.block) {
+            let text = match trimmed.find(end) {
+                Some(index) => {
+                    self.in_block = false;
+                    &trimmed[..index]
+                }
+                None => trimmed,
+            };
+            let text = text.strip_prefix('*').unwrap_or(text);
+            text.strip_prefix(' ').unwrap_or(text)
+        } else if let Some(text) = self.block_start(trimmed) {
+            text
+        } else {
+            // A shebang is no documentation.
+            if line_number == 1 && trimmed.starts_with("#!") {
+                return false;
+            }
+            match self
+                .syntax
+                .lines
+                .iter()
+                .find_map(|prefix| trimmed.strip_prefix(prefix))
+            {
+                Some(text) => text.strip_prefix(' ').unwrap_or(text),
+                None => return false,
+            }
+        };
+
+        let text = text.trim_end();
+        if text.trim_start().starts_with("```") {
+            self.in_fence = !self.in_fence;
+            self.lines.push(ProseLine::Verbatim(text.to_string()));
+        } else if self.in_fence {
+            self.lines.push(ProseLine::Verbatim(text.to_string()));
+        } else if text.is_empty() {
+            self.lines.push(ProseLine::Break);
+        } else {
+            self.lines.push(ProseLine::Text(text.to_string()));
+        }
+        true
+    }
+
+    /// The text after the start of a documentation block, which may end on the same line.
+    fn block_start<'l>(&mut self, trimmed: &'l str) -> Option<&'l str> {
+        let (start, end) = self.syntax.block?;
+        let text = trimmed.strip_prefix(start)?;
+        // An empty comment like `/**/` isn't documentation.
+        if text.starts_with('/') {
+            return None;
+        }
+        match text.find(end) {
+            Some(index) => Some(text[..index].trim_start()),
+            None => {
+                self.in_block = true;
+                Some(text.trim_start())
+            }
+        }
+    }
+
+    /// The collected prose as rows at most `width` columns wide. Paragraphs are separated by
+    /// empty rows.
+    pub fn flush(&mut self, width: usize) -> Vec<String> {
+        let mut rows = Vec::new();
+        let mut paragraph = String::new();
+        self.in_fence = false;
+
+        for line in self.lines.drain(..) {
+            match line {
+                ProseLine::Text(text) => {
+                    if !paragraph.is_empty() {
+                        paragraph.push(' ');
+                    }
+                    paragraph.push_str(&text);
+                }
+                ProseLine::Verbatim(text) => {
+                    rows.extend(wrap(&paragraph, width));
+                    paragraph.clear();
+                    rows.push(text);
+                }
+                ProseLine::Break => {
+                    rows.extend(wrap(&paragraph, width));
+                    paragraph.clear();
+                    if rows.last().is_some_and(|row| !row.is_empty()) {
+                        rows.push(String::new());
+                    }
+                }
+            }
+        }
+        rows.extend(wrap(&paragraph, width));
+
+        while rows.last().is_some_and(String::is_empty) {
+            rows.pop();
+        }
+        rows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+/// Wraps `text` at word boundaries into rows of at most `width` columns. Words longer than a
+/// row get a row of their own.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut row = String::new();
+
+    for word in text.split_whitespace() {
+        if !row.is_empty() && row.width() + 1 + word.width() > width {
+            rows.push(std::mem::take(&mut row));
+        }
+        if !row.is_empty() {
+            row.push(' ');
+        }
+        row.push_str(word);
+    }
+    if !row.is_empty() {
+        rows.push(row);
+    }
+
+    rows
+}
+
+#[test]
+fn wrap_words() {
+    assert_eq!(vec!["a bb", "ccc"], wrap("a  bb ccc", 5));
+    assert_eq!(vec!["abcdefg", "h"], wrap("abcdefg h", 5));
+    assert!(wrap("", 5).is_empty());
+}
+
+#[test]
+fn doc_comments() {
+    let mut prose = Prose::new(doc_syntax("Rust").unwrap());
+    let lines = [
+        "/// Adds two",
+        "/// numbers.",
+        "///",
+        "/// ```",
+        "/// add(1, 2);",
+        "/// ```",
+        "fn add() {}",
+        "/** A block",
+        " * comment. */",
+        "// not documentation",
+    ];
+    let taken: Vec<bool> = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| prose.take(line, i + 1))
+        .collect();
+
+    assert_eq!(
+        vec![true, true, true, true, true, true, false, true, true, false],
+        taken
+    );
+    assert_eq!(
+        vec![
+            "Adds two numbers.",
+            "",
+            "```",
+            "add(1, 2);",
+            "```",
+            "A block comment."
+        ],
+        prose.flush(40)
+    );
+    assert!(prose.is_empty());
+}
+
+#[test]
+fn hash_comments() {
+    let mut prose = Prose::new(doc_syntax("Python").unwrap());
+    assert!(!prose.take("#!/usr/bin/env python\n", 1));
+    assert!(prose.take("# Prints a greeting.\n", 2));
+    assert!(!prose.take("print('hi')\n", 3));
+    assert_eq!(vec!["Prints a greeting."], prose.flush(40));
+}
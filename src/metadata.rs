@@ -0,0 +1,76 @@
+use std::fs::Metadata;
+use std::time::SystemTime;
+
+/// File size with a binary unit, e.g. `512 B` or `1.5 KiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Modification time in UTC, e.g. `2019-01-01 10:00:00`.
+pub fn format_modified(modified: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(modified)
+        .to_string()
+        .trim_end_matches('Z')
+        .replace('T', " ")
+}
+
+/// Permissions like `ls -l`, e.g. `-rw-r--r--`.
+#[cfg(unix)]
+pub fn format_permissions(metadata: &Metadata) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = metadata.permissions().mode();
+    // The metadata follows symbolic links, so there are only files and directories.
+    let kind = if metadata.is_dir() { 'd' } else { '-' };
+
+    let mut permissions = kind.to_string();
+    for shift in [6, 3, 0] {
+        let bits = mode >> shift;
+        permissions.push(if bits & 4 != 0 { 'r' } else { '-' });
+        permissions.push(if bits & 2 != 0 { 'w' } else { '-' });
+        permissions.push(if bits & 1 != 0 { 'x' } else { '-' });
+    }
+    Some(permissions)
+}
+
+#[cfg(not(unix))]
+pub fn format_permissions(_metadata: &Metadata) -> Option<String> {
+    None
+}
+
+#[test]
+fn sizes() {
+    assert_eq!("0 B", format_size(0));
+    assert_eq!("1023 B", format_size(1023));
+    assert_eq!("1.5 KiB", format_size(1536));
+    assert_eq!("3.0 GiB", format_size(3 * 1024 * 1024 * 1024));
+}
+
+#[test]
+fn modification_times() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let modified = UNIX_EPOCH + Duration::from_secs(1_546_336_800);
+    assert_eq!("2019-01-01 10:00:00", format_modified(modified));
+}
+
+#[cfg(unix)]
+#[test]
+fn permissions() {
+    let metadata = std::fs::metadata("fixtures/fib.rs").unwrap();
+    let permissions = format_permissions(&metadata).unwrap();
+    assert_eq!(10, permissions.len());
+    assert!(permissions.starts_with("-r"));
+}
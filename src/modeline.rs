@@ -0,0 +1,143 @@
+use std::fs;
+
+use regex::Regex;
+
+use crate::inputfile::{InputFile, InputFileReader};
+
+/// Modelines are only looked for in this many lines at the top and at the bottom, like vim does.
+const MODELINE_LINES: usize = 5;
+
+lazy_static! {
+    static ref VIM: Regex = Regex::new(r"(?:^|\s)(?:vim?|ex):\s*(?:set?\s+)?(.*)").unwrap();
+    static ref EMACS: Regex = Regex::new(r"-\*-(.*)-\*-").unwrap();
+}
+
+/// The language token of a vim (`# vim: ft=yaml`) or emacs (`-*- mode: ruby -*-`) modeline near
+/// the top or the bottom of the input. Files are only read once, so this looks at the lines in
+/// the buffer of the `reader`, which hold the bottom of the file only if it's short. For STDIN
+/// only the first line is known.
+pub fn modeline_token(input: &InputFile, reader: &mut InputFileReader) -> Option<String> {
+    let (content, complete) = match input {
+        InputFile::String(string) => (string.as_bytes().to_vec(), true),
+        InputFile::Bytes(bytes) => (bytes.clone(), true),
+        InputFile::Ordinary(filename) => {
+            let mut content = reader.first_line.clone();
+            content.extend_from_slice(reader.peek());
+            let length = fs::metadata(filename)
+                .ok()
+                .filter(|metadata| metadata.is_file());
+            let complete = length.is_some_and(|metadata| metadata.len() == content.len() as u64);
+            (content, complete)
+        }
+        InputFile::StdIn => (reader.first_line.clone(), false),
+    };
+    let content = String::from_utf8_lossy(&content);
+
+    let bottom: Vec<&str> = if complete {
+        content.lines().rev().take(MODELINE_LINES).collect()
+    } else {
+        Vec::new()
+    };
+
+    content
+        .lines()
+        .take(MODELINE_LINES)
+        .chain(bottom.into_iter().rev())
+        .find_map(line_token)
+}
+
+fn line_token(line: &str) -> Option<String> {
+    let token = if let Some(captures) = EMACS.captures(line) {
+        let variables = captures.get(1)?.as_str().trim();
+        if variables.contains(':') {
+            variables
+                .split(';')
+                .filter_map(|variable| {
+                    let mut parts = variable.splitn(2, ':');
+                    match (parts.next()?.trim(), parts.next()?.trim()) {
+                        (key, value) if key.eq_ignore_ascii_case("mode") => Some(value),
+                        _ => None,
+                    }
+                })
+                .next()?
+        } else {
+            variables
+        }
+    } else {
+        let options = VIM.captures(line)?.get(1)?.as_str();
+        // Later options win.
+        options
+            .rsplit(|c: char| c == ':' || c.is_whitespace())
+            .filter_map(|option| {
+                let mut parts = option.splitn(2, '=');
+                match (parts.next()?, parts.next()?) {
+                    ("ft", value) | ("filetype", value) | ("syn", value) | ("syntax", value) => {
+                        Some(value)
+                    }
+                    _ => None,
+                }
+            })
+            .next()?
+    };
+
+    let token = token.trim().to_lowercase();
+    Some(match token.as_str() {
+        "" => return None,
+        "sh" | "zsh" | "shell-script" => "bash".to_string(),
+        "c++" => "cpp".to_string(),
+        _ => token,
+    })
+}
+
+#[test]
+fn modelines() {
+    assert_eq!(Some("yaml".into()), line_token("# vim: ft=yaml"));
+    assert_eq!(
+        Some("python".into()),
+        line_token("# vim: set ts=4 filetype=python :")
+    );
+    assert_eq!(Some("cpp".into()), line_token("// vi:sw=2:ft=cpp"));
+    assert_eq!(
+        Some("ruby".into()),
+        line_token("# -*- mode: ruby; coding: utf-8 -*-")
+    );
+    assert_eq!(Some("bash".into()), line_token("# -*- sh -*-"));
+    assert_eq!(None, line_token("# -*- coding: utf-8 -*-"));
+    assert_eq!(None, line_token("let environment = vim: 3;"));
+}
+
+#[test]
+fn modelines_at_the_bottom() {
+    let token = |input: InputFile| {
+        let mut reader = input.get_reader().unwrap();
+        modeline_token(&input, &mut reader)
+    };
+    let filler = "x\n".repeat(20);
+
+    let input = InputFile::String(format!("{}# vim: ft=yaml\n", filler));
+    assert_eq!(Some("yaml".into()), token(input));
+
+    let input = InputFile::String(format!("{}# vim: ft=yaml\n{}", filler, filler));
+    assert_eq!(None, token(input));
+
+    let content = b"#!/bin/sh\n# -*- ruby -*-\nputs 1\n";
+    let mut reader = InputFileReader::new(&content[..]);
+    assert_eq!(None, modeline_token(&InputFile::StdIn, &mut reader));
+}
+
+#[test]
+fn modelines_of_files() {
+    let path = std::env::temp_dir().join("prettyprint-modeline-test");
+    fs::write(&path, "x = 1\n".repeat(20) + "# vim: ft=python\n").unwrap();
+    let input = InputFile::Ordinary(path.to_string_lossy().into_owned());
+
+    let mut reader = input.get_reader().unwrap();
+    assert_eq!(Some("python".into()), modeline_token(&input, &mut reader));
+    // Nothing of the file was used up.
+    let mut line = Vec::new();
+    reader.read_line(&mut line).unwrap();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(b"x = 1\nx = 1\n", &line[..]);
+
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,169 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use serde_json::Value;
+use unicode_width::UnicodeWidthStr;
+
+use crate::decorations::{Decoration, DecorationText};
+use crate::errors::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellKind {
+    Code,
+    Markdown,
+    Raw,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cell {
+    pub kind: CellKind,
+    pub source: String,
+    pub execution_count: Option<u64>,
+}
+
+/// The cells of a Jupyter notebook (`.ipynb`).
+#[derive(Debug, Clone)]
+pub struct Notebook {
+    /// Language of the code cells, as given by the kernel
+    pub language: String,
+    pub cells: Vec<Cell>,
+}
+
+pub fn is_notebook(filename: &str) -> bool {
+    Path::new(filename)
+        .extension()
+        .is_some_and(|extension| extension == "ipynb")
+}
+
+impl Notebook {
+    pub fn from_path(filename: &str) -> Result<Self> {
+        let file = File::open(filename)?;
+        let json: Value = serde_json::from_reader(BufReader::new(file))
+            .chain_err(|| format!("Could not parse notebook '{}'", filename))?;
+
+        Notebook::from_json(&json).chain_err(|| format!("Invalid notebook '{}'", filename))
+    }
+
+    pub fn from_json(json: &Value) -> Result<Self> {
+        let metadata = &json["metadata"];
+        let language = metadata["kernelspec"]["language"]
+            .as_str()
+            .or_else(|| metadata["language_info"]["name"].as_str())
+            .unwrap_or("python")
+            .to_string();
+
+        let cells = json["cells"]
+            .as_array()
+            .ok_or("Missing 'cells'")?
+            .iter()
+            .map(|cell| {
+                let kind = match cell["cell_type"].as_str() {
+                    Some("code") => CellKind::Code,
+                    Some("markdown") => CellKind::Markdown,
+                    _ => CellKind::Raw,
+                };
+
+                // The source is either a string or a list of lines.
+                let source = match cell["source"] {
+                    Value::String(ref source) => source.clone(),
+                    Value::Array(ref lines) => lines.iter().filter_map(Value::as_str).collect(),
+                    _ => String::new(),
+                };
+
+                Cell {
+                    kind,
+                    source,
+                    execution_count: cell["execution_count"].as_u64(),
+                }
+            })
+            .collect();
+
+        Ok(Notebook { language, cells })
+    }
+}
+
+impl Cell {
+    /// Language used to highlight the cell.
+    pub fn language<'a>(&self, notebook: &'a Notebook) -> &'a str {
+        match self.kind {
+            CellKind::Code => &notebook.language,
+            CellKind::Markdown => "markdown",
+            CellKind::Raw => "txt",
+        }
+    }
+
+    /// Header of the cell, e.g. `In [7]:` for a code cell which ran seventh.
+    pub fn title(&self) -> String {
+        match (self.kind, self.execution_count) {
+            (CellKind::Code, Some(count)) => format!("In [{}]:", count),
+            (CellKind::Code, None) => "In [ ]:".to_string(),
+            (CellKind::Markdown, _) => "Markdown".to_string(),
+            (CellKind::Raw, _) => "Raw".to_string(),
+        }
+    }
+}
+
+/// The number of a cell in the gutter, e.g. `[3]`, shown next to its first line.
+#[derive(Debug)]
+pub struct CellNumber {
+    label: String,
+    first_line: usize,
+    width: usize,
+}
+
+impl CellNumber {
+    /// The column for cell `number` of `cells`, whose first line is numbered `first_line`.
+    pub fn new(number: usize, cells: usize, first_line: usize) -> Self {
+        CellNumber {
+            label: format!("[{}]", number),
+            first_line,
+            width: format!("[{}]", cells).width(),
+        }
+    }
+}
+
+impl Decoration for CellNumber {
+    fn generate(&self, line_number: usize, continuation: bool) -> DecorationText {
+        let label = if line_number == self.first_line && !continuation {
+            &self.label
+        } else {
+            ""
+        };
+        DecorationText {
+            text: format!("{:>width$}", label, width = self.width),
+            width: self.width,
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+}
+
+#[test]
+fn parse_cells() {
+    let json = serde_json::json!({
+        "metadata": { "kernelspec": { "language": "julia" } },
+        "cells": [
+            { "cell_type": "markdown", "source": ["# Title\n", "Text"] },
+            { "cell_type": "code", "execution_count": 2, "source": "x = 1" }
+        ]
+    });
+    let notebook = Notebook::from_json(&json).unwrap();
+
+    assert_eq!("julia", notebook.language);
+    assert_eq!("# Title\nText", notebook.cells[0].source);
+    assert_eq!("markdown", notebook.cells[0].language(&notebook));
+    assert_eq!("julia", notebook.cells[1].language(&notebook));
+    assert_eq!("In [2]:", notebook.cells[1].title());
+}
+
+#[test]
+fn cell_numbers() {
+    let number = CellNumber::new(3, 12, 1);
+    assert_eq!(4, number.width());
+    assert_eq!(" [3]", number.generate(1, false).text);
+    assert_eq!("    ", number.generate(1, true).text);
+    assert_eq!("    ", number.generate(2, false).text);
+}
@@ -4,8 +4,6 @@ use std::io::{self, Write};
 use std::path::PathBuf;
 use std::process::{Child, Command, Stdio};
 
-use shell_words;
-
 use crate::builder::PagingMode;
 use crate::errors::*;
 
@@ -76,7 +74,7 @@ impl OutputType {
         OutputType::Stdout(io::stdout())
     }
 
-    pub fn handle(&mut self) -> Result<&mut Write> {
+    pub fn handle(&mut self) -> Result<&mut dyn Write> {
         Ok(match *self {
             OutputType::Pager(ref mut command) => command
                 .stdin
@@ -94,3 +92,88 @@ impl Drop for OutputType {
         }
     }
 }
+
+/// How to signal that printing is complete.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Notify {
+    /// Ring the terminal bell
+    Bell,
+    /// Send a desktop notification (OSC 9), supported by e.g. iTerm2, kitty and Windows Terminal
+    Osc9,
+}
+
+impl Notify {
+    /// Notifies that `title` has been printed. Does nothing unless STDOUT is an interactive
+    /// terminal.
+    pub(crate) fn send(self, title: &str) {
+        if !is_interactive() {
+            return;
+        }
+
+        let mut stdout = io::stdout();
+        let _ = write!(stdout, "{}", self.sequence(title));
+        let _ = stdout.flush();
+    }
+
+    fn sequence(self, title: &str) -> String {
+        match self {
+            Notify::Bell => "\x07".to_string(),
+            Notify::Osc9 => format!("\x1b]9;Printed {}\x07", strip_controls(title)),
+        }
+    }
+}
+
+fn is_interactive() -> bool {
+    atty::is(atty::Stream::Stdout) && env::var("TERM").is_ok_and(|term| term != "dumb")
+}
+
+/// Sets the title of the terminal window (OSC 0) while alive and restores the previous title
+/// when dropped, using the title stack of xterm compatible terminals.
+pub struct TerminalTitle(());
+
+impl TerminalTitle {
+    /// Does nothing unless STDOUT is an interactive terminal.
+    pub fn set(title: &str) -> Option<Self> {
+        if !is_interactive() {
+            return None;
+        }
+
+        let mut stdout = io::stdout();
+        write!(stdout, "\x1b[22;0t{}", title_sequence(title)).ok()?;
+        stdout.flush().ok()?;
+        Some(TerminalTitle(()))
+    }
+}
+
+impl Drop for TerminalTitle {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = write!(stdout, "\x1b[23;0t");
+        let _ = stdout.flush();
+    }
+}
+
+/// The OSC sequence setting `title`.
+fn title_sequence(title: &str) -> String {
+    format!("\x1b]0;{}\x07", strip_controls(title))
+}
+
+/// Removes control characters, which would end an OSC sequence early.
+fn strip_controls(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+
+#[test]
+fn title_sequences() {
+    assert_eq!("\x1b]0;src/main.rs\x07", title_sequence("src/main.rs"));
+    assert_eq!("\x1b]0;a]0;b\x07", title_sequence("a\x07\x1b]0;b"));
+}
+
+#[test]
+fn notifications() {
+    assert_eq!("\x07", Notify::Bell.sequence("main.rs"));
+    assert_eq!(
+        "\x1b]9;Printed main.rs\x07",
+        Notify::Osc9.sequence("main.rs\x07")
+    );
+}
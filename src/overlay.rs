@@ -0,0 +1,237 @@
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+use regex::Regex;
+use syntect::highlighting::{Color, FontStyle, Style};
+
+use crate::injection::Regions;
+
+/// Restyles the parts of `line` covered by `ranges` (sorted and not overlapping), on top of the
+/// syntax highlighting. Regions are split at the boundaries of the ranges.
+pub fn overlay<'b, F>(
+    line: &'b str,
+    regions: Regions<'b>,
+    ranges: &[Range<usize>],
+    restyle: F,
+) -> Regions<'b>
+where
+    F: Fn(Style) -> Style,
+{
+    if ranges.is_empty() {
+        return regions;
+    }
+
+    let mut output = Vec::with_capacity(regions.len() + 2 * ranges.len());
+    let mut ranges = ranges.iter().peekable();
+    let mut start = 0;
+
+    for (style, text) in regions {
+        let end = start + text.len();
+        let mut cursor = start;
+
+        while cursor < end {
+            // Skip ranges which ended before this region.
+            while ranges.peek().is_some_and(|range| range.end <= cursor) {
+                ranges.next();
+            }
+
+            match ranges.peek() {
+                Some(range) if range.start <= cursor => {
+                    let until = range.end.min(end);
+                    output.push((restyle(style), &line[cursor..until]));
+                    cursor = until;
+                }
+                Some(range) if range.start < end => {
+                    output.push((style, &line[cursor..range.start]));
+                    cursor = range.start;
+                }
+                _ => {
+                    output.push((style, &line[cursor..end]));
+                    cursor = end;
+                }
+            }
+        }
+
+        start = end;
+    }
+
+    output
+}
+
+/// Scopes of the text given to a `ProseChecker`.
+pub const PROSE_SELECTOR: &str = "comment, string";
+
+/// Scopes underlined by `Accessibility::Markers`.
+pub const KEYWORD_SELECTOR: &str = "keyword - keyword.operator, storage";
+
+/// A callback which gets the text of each comment and string, and returns the byte ranges of
+/// it to underline, e.g. misspelled words.
+#[derive(Clone)]
+pub struct ProseChecker(pub Arc<ProseCheck>);
+
+pub type ProseCheck = dyn Fn(&str) -> Vec<Range<usize>> + Send + Sync;
+
+impl fmt::Debug for ProseChecker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ProseChecker")
+    }
+}
+
+impl ProseChecker {
+    /// The ranges of `line` to underline, for the prose found at `spans`. Ranges of the callback
+    /// outside of the text or inside of a character are left out.
+    pub fn check(&self, line: &str, spans: &[Range<usize>]) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = spans
+            .iter()
+            .flat_map(|span| {
+                (self.0)(&line[span.clone()])
+                    .into_iter()
+                    .map(move |range| span.start + range.start..span.start + range.end)
+                    .filter(move |range| range.start < range.end && range.end <= span.end)
+                    .filter(|range| {
+                        line.is_char_boundary(range.start) && line.is_char_boundary(range.end)
+                    })
+            })
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+        ranges
+    }
+}
+
+/// The byte ranges of all matches of `regex` in `line`.
+pub fn match_ranges(regex: &Regex, line: &str) -> Vec<Range<usize>> {
+    regex
+        .find_iter(line)
+        .filter(|m| !m.as_str().is_empty())
+        .map(|m| m.range())
+        .collect()
+}
+
+/// A regex matching any of `words` as a whole word, or `None` if there are no words.
+pub fn words_regex<S: AsRef<str>>(words: &[S]) -> Option<Regex> {
+    let alternatives: Vec<String> = words
+        .iter()
+        .map(AsRef::as_ref)
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            // Word boundaries only make sense next to word characters, e.g. not after `TODO:`.
+            let boundary = |c: Option<char>| match c {
+                Some(c) if c.is_alphanumeric() || c == '_' => r"\b",
+                _ => "",
+            };
+            format!(
+                "{}{}{}",
+                boundary(word.chars().next()),
+                regex::escape(word),
+                boundary(word.chars().next_back())
+            )
+        })
+        .collect();
+
+    if alternatives.is_empty() {
+        None
+    } else {
+        Regex::new(&alternatives.join("|")).ok()
+    }
+}
+
+/// The style of emphasized words: bold, in `color` if the theme has one for this.
+pub fn emphasize(style: Style, color: Option<Color>) -> Style {
+    Style {
+        foreground: color.unwrap_or(style.foreground),
+        font_style: style.font_style | FontStyle::BOLD,
+        ..style
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts<'b>(regions: &Regions<'b>) -> Vec<(bool, &'b str)> {
+        regions
+            .iter()
+            .map(|(style, text)| (style.font_style.contains(FontStyle::BOLD), *text))
+            .collect()
+    }
+
+    #[test]
+    fn split_regions() {
+        let line = "// TODO: fix";
+        let regions = vec![(Style::default(), "// "), (Style::default(), "TODO: fix")];
+        let regex = words_regex(&["TODO", "fix"]).unwrap();
+
+        let regions = overlay(line, regions, &match_ranges(&regex, line), |style| {
+            emphasize(style, None)
+        });
+        assert_eq!(
+            vec![(false, "// "), (true, "TODO"), (false, ": "), (true, "fix")],
+            texts(&regions)
+        );
+    }
+
+    #[test]
+    fn ranges_across_regions() {
+        let line = "unsafe";
+        let regions = vec![(Style::default(), "un"), (Style::default(), "safe")];
+
+        let ranges = [1..3, 5..6];
+        let regions = overlay(line, regions, &ranges, |style| emphasize(style, None));
+        assert_eq!(
+            vec![
+                (false, "u"),
+                (true, "n"),
+                (true, "s"),
+                (false, "af"),
+                (true, "e")
+            ],
+            texts(&regions)
+        );
+    }
+
+    #[test]
+    fn prose_checks() {
+        let checker = ProseChecker(Arc::new(|text: &str| {
+            text.match_indices("teh")
+                .map(|(i, m)| i..i + m.len())
+                .collect()
+        }));
+        let line = "f(\"teh\"); // teh end";
+        assert_eq!(vec![3..6, 13..16], checker.check(line, &[2..7, 10..20]));
+    }
+
+    #[test]
+    fn prose_checks_inside_characters() {
+        // Counts characters instead of bytes, so the range ends inside of the `ü` of `grün`.
+        let checker = ProseChecker(Arc::new(|text: &str| {
+            let end = text.chars().count() - 1;
+            std::iter::once(0..end).collect()
+        }));
+        let line = "// grün da";
+        let spans = [0..3, 3..8, 8..11];
+        assert_eq!(vec![0..2, 8..10], checker.check(line, &spans));
+
+        let regions = vec![(Style::default(), "// "), (Style::default(), "grün da")];
+        let ranges = checker.check(line, &spans);
+        let regions = overlay(line, regions, &ranges, |style| emphasize(style, None));
+        assert_eq!(
+            vec![
+                (true, "//"),
+                (false, " "),
+                (false, "grün"),
+                (true, " d"),
+                (false, "a")
+            ],
+            texts(&regions)
+        );
+    }
+
+    #[test]
+    fn whole_words() {
+        let regex = words_regex(&["unsafe", "TODO:"]).unwrap();
+        assert_eq!(vec![0..6], match_ranges(&regex, "unsafe unsafely"));
+        assert_eq!(vec![3..8], match_ranges(&regex, "// TODO: x"));
+        assert!(words_regex::<&str>(&[]).is_none());
+    }
+}
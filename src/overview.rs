@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use syntect::highlighting::Color;
+use unicode_width::UnicodeWidthStr;
+
+use crate::injection::Regions;
+
+const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Draws the line lengths as a sparkline of at most `width` characters. If there are more lines
+/// than characters, each character shows the longest line of its bucket.
+pub fn sparkline(lengths: &[usize], width: usize) -> String {
+    if lengths.is_empty() || width == 0 {
+        return String::new();
+    }
+
+    let buckets = lengths.len().min(width);
+    let longest = lengths.iter().cloned().max().unwrap_or(0).max(1);
+
+    (0..buckets)
+        .map(|bucket| {
+            let start = bucket * lengths.len() / buckets;
+            let end = ((bucket + 1) * lengths.len() / buckets).max(start + 1);
+            let length = lengths[start..end].iter().cloned().max().unwrap_or(0);
+
+            if length == 0 {
+                ' '
+            } else {
+                BLOCKS[(length * BLOCKS.len() - 1) / longest]
+            }
+        })
+        .collect()
+}
+
+/// Eighths of a cell, from narrow to full.
+const PARTIAL_BLOCKS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+
+/// An editor-style minimap: each cell stands for a number of consecutive lines. Its width shows
+/// the length of the lines and its color the most common foreground color.
+pub struct Minimap {
+    pub cells: Vec<(Color, char)>,
+}
+
+impl Minimap {
+    /// Summarizes the highlighted `lines` in at most `height` cells.
+    pub fn new(lines: &[Regions], height: usize) -> Self {
+        let lines_per_cell = lines.len().div_ceil(height.max(1)).max(1);
+        let longest = lines
+            .iter()
+            .map(|line| line_length(line))
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        let cells = lines
+            .chunks(lines_per_cell)
+            .map(|chunk| {
+                let mut colors: HashMap<(u8, u8, u8, u8), usize> = HashMap::new();
+                for &(style, text) in chunk.iter().flatten() {
+                    let c = style.foreground;
+                    *colors.entry((c.r, c.g, c.b, c.a)).or_insert(0) +=
+                        text.chars().filter(|c| !c.is_whitespace()).count();
+                }
+                let color = colors
+                    .into_iter()
+                    .max_by_key(|&(color, count)| (count, color))
+                    .map(|((r, g, b, a), _)| Color { r, g, b, a })
+                    .unwrap_or(Color::WHITE);
+
+                let length = chunk
+                    .iter()
+                    .map(|line| line_length(line))
+                    .max()
+                    .unwrap_or(0);
+                let block = if length == 0 {
+                    ' '
+                } else {
+                    PARTIAL_BLOCKS[(length * PARTIAL_BLOCKS.len() - 1) / longest]
+                };
+
+                (color, block)
+            })
+            .collect();
+
+        Minimap { cells }
+    }
+}
+
+fn line_length(regions: &Regions) -> usize {
+    regions
+        .iter()
+        .map(|&(_, text)| text.trim_end_matches(['\r', '\n']).width())
+        .sum()
+}
+
+#[test]
+fn sparklines() {
+    assert_eq!("▁▄ █", sparkline(&[1, 4, 0, 8], 10));
+    assert_eq!("▄█", sparkline(&[1, 4, 0, 8], 2));
+    assert_eq!("", sparkline(&[], 10));
+}
+
+#[test]
+fn minimap() {
+    use syntect::highlighting::Style;
+
+    let red = Style {
+        foreground: Color {
+            r: 255,
+            g: 0,
+            b: 0,
+            a: 255,
+        },
+        ..Style::default()
+    };
+    let lines: Vec<Regions> = vec![
+        vec![(red, "fn main() {\n")],
+        vec![(Style::default(), "\n")],
+        vec![(Style::default(), "}"), (red, "//\n")],
+    ];
+
+    let minimap = Minimap::new(&lines, 10);
+    assert_eq!(3, minimap.cells.len());
+    assert_eq!((red.foreground, '█'), minimap.cells[0]);
+    assert_eq!(' ', minimap.cells[1].1);
+
+    assert_eq!(2, Minimap::new(&lines, 2).cells.len());
+}
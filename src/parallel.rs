@@ -0,0 +1,207 @@
+use std::mem;
+use std::num::NonZeroUsize;
+use std::thread;
+
+use syntect::parsing::{ParseState, ScopeStackOp, SyntaxReference, SyntaxSet};
+
+use crate::errors::*;
+
+/// Lines read ahead for each thread, see `.highlight_threads()`.
+pub const WINDOW_LINES_PER_THREAD: usize = 4096;
+
+/// No thread parses fewer lines than this.
+const MIN_CHUNK_LINES: usize = 256;
+
+pub type Ops = Vec<(usize, ScopeStackOp)>;
+
+/// The lines of a window of the file, parsed by several threads, whose ops are handed out in
+/// order.
+pub struct ParsedLines {
+    lines: Vec<String>,
+    ops: Vec<Ops>,
+    /// The first line of each chunk and the parse state in front of it
+    starts: Vec<(usize, ParseState)>,
+    /// The parse state after the last line
+    end: ParseState,
+    next: usize,
+}
+
+/// The number of threads for `threads`, one per CPU core if it's zero.
+pub fn thread_count(threads: usize) -> usize {
+    match threads {
+        0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        threads => threads,
+    }
+}
+
+/// Parses `lines`, which follow the parse state `start`, with `threads` threads.
+///
+/// Parse states can't be sent to other threads, so each thread parses a chunk of lines as if it
+/// was at the top level of the file, and notes the lines in front of which it was. These lines
+/// are parsed again in this thread, from the state the chunk before ends in, until it's at the
+/// top level in front of a line where the thread was too. From there on, the thread parsed all
+/// lines right, and the state at the end of the chunk is found by parsing the lines after the
+/// last line in front of which the thread was at the top level.
+pub fn parse_lines(
+    syntax: &SyntaxReference,
+    syntax_set: &SyntaxSet,
+    start: &ParseState,
+    lines: Vec<String>,
+    threads: usize,
+) -> Result<ParsedLines> {
+    let chunk_lines = lines
+        .len()
+        .div_ceil(thread_count(threads))
+        .max(MIN_CHUNK_LINES);
+    let starts: Vec<usize> = (0..lines.len()).step_by(chunk_lines).collect();
+
+    let results = thread::scope(|scope| {
+        let workers: Vec<_> = starts
+            .iter()
+            .map(|&start| {
+                let chunk = &lines[start..(start + chunk_lines).min(lines.len())];
+                scope.spawn(move || parse_chunk(syntax, syntax_set, chunk))
+            })
+            .collect();
+        // All threads are joined, so that the scope doesn't panic.
+        workers
+            .into_iter()
+            .map(|worker| worker.join())
+            .collect::<Vec<_>>()
+    });
+    let chunks = results
+        .into_iter()
+        .collect::<thread::Result<Vec<_>>>()
+        .map_err(|_| "A highlighting thread panicked")?;
+
+    let top_level = top_level_state(syntax, syntax_set);
+    let mut ops = Vec::with_capacity(lines.len());
+    let mut chunk_starts = Vec::with_capacity(starts.len());
+    let mut state = start.clone();
+    for (&start, (guessed, tops)) in starts.iter().zip(chunks) {
+        chunk_starts.push((start, state.clone()));
+        let end = start + guessed.len();
+        let mut guessed = guessed.into_iter();
+        let mut index = start;
+        while index < end && !(tops[index - start] && state == top_level) {
+            ops.push(state.parse_line(&lines[index], syntax_set));
+            guessed.next();
+            index += 1;
+        }
+        if index < end {
+            ops.extend(guessed);
+            let last = (index..end)
+                .rev()
+                .find(|&line| tops[line - start])
+                .unwrap_or(index);
+            state = top_level.clone();
+            for line in &lines[last..end] {
+                state.parse_line(line, syntax_set);
+            }
+        }
+    }
+
+    Ok(ParsedLines {
+        lines,
+        ops,
+        starts: chunk_starts,
+        end: state,
+        next: 0,
+    })
+}
+
+impl ParsedLines {
+    /// The ops of `line`, if it's the next line.
+    pub fn next(&mut self, line: &str) -> Option<Ops> {
+        if self.lines.get(self.next).map(String::as_str) != Some(line) {
+            return None;
+        }
+        self.next += 1;
+        Some(mem::take(&mut self.ops[self.next - 1]))
+    }
+
+    /// The lines from the next one on, with their ops.
+    pub fn ahead(&self) -> impl Iterator<Item = (&str, &Ops)> {
+        self.lines[self.next..]
+            .iter()
+            .map(String::as_str)
+            .zip(&self.ops[self.next..])
+    }
+
+    /// The parse state in front of the next line, for the lines that don't come in order.
+    pub fn state(&self, syntax_set: &SyntaxSet) -> ParseState {
+        if self.next == self.lines.len() {
+            return self.end.clone();
+        }
+        let (start, ref state) = *self
+            .starts
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= self.next)
+            .expect("the first chunk starts at the first line");
+        let mut state = state.clone();
+        for line in &self.lines[start..self.next] {
+            state.parse_line(line, syntax_set);
+        }
+        state
+    }
+}
+
+/// The parse state at the top level of a file, after its first line.
+fn top_level_state(syntax: &SyntaxReference, syntax_set: &SyntaxSet) -> ParseState {
+    let mut state = ParseState::new(syntax);
+    state.parse_line("", syntax_set);
+    state
+}
+
+/// Parses the lines of a chunk from the top level, noting in front of which lines it was at the
+/// top level.
+fn parse_chunk(
+    syntax: &SyntaxReference,
+    syntax_set: &SyntaxSet,
+    chunk: &[String],
+) -> (Vec<Ops>, Vec<bool>) {
+    let top_level = top_level_state(syntax, syntax_set);
+    let mut state = top_level.clone();
+    let mut ops = Vec::with_capacity(chunk.len());
+    let mut tops = Vec::with_capacity(chunk.len());
+    for line in chunk {
+        tops.push(state == top_level);
+        ops.push(state.parse_line(line, syntax_set));
+    }
+    (ops, tops)
+}
+
+#[test]
+fn parses_chunks_like_one_thread() {
+    use crate::assets::HighlightingAssets;
+
+    let assets = HighlightingAssets::integrated();
+    let syntax = assets.syntax_set.find_syntax_by_token("rs").unwrap();
+    // The threads parse 500 lines each, and the second and the third start in a block comment.
+    let lines: Vec<String> = (0..2000)
+        .map(|index| match index % 500 {
+            498 => "/* a comment\n".to_string(),
+            20 => "   until here */ fn f() {}\n".to_string(),
+            _ => format!("fn f{}() {{}}\n", index),
+        })
+        .collect();
+
+    let start = ParseState::new(syntax);
+    let mut parsed = parse_lines(syntax, &assets.syntax_set, &start, lines.clone(), 4).unwrap();
+    let mut state = start.clone();
+    for line in &lines[..1000] {
+        assert_eq!(
+            Some(state.parse_line(line, &assets.syntax_set)),
+            parsed.next(line)
+        );
+    }
+    assert_eq!(None, parsed.next("fn g() {}\n"));
+    assert_eq!(state, parsed.state(&assets.syntax_set));
+
+    for line in &lines[1000..] {
+        state.parse_line(line, &assets.syntax_set);
+    }
+    parsed.next = lines.len();
+    assert_eq!(state, parsed.state(&assets.syntax_set));
+}
@@ -1,4 +1,8 @@
+use std::fmt::Debug;
+use std::ops::Range;
+
 use console::AnsiCodeIterator;
+use unicode_width::UnicodeWidthStr;
 
 /// Expand tabs like an ANSI-enabled expand(1).
 pub fn expand_tabs(line: &str, width: usize, cursor: &mut usize) -> String {
@@ -11,20 +15,20 @@ pub fn expand_tabs(line: &str, width: usize, cursor: &mut usize) -> String {
                 while let Some(index) = text.find('\t') {
                     // Add previous text.
                     if index > 0 {
-                        *cursor += index;
+                        *cursor += text[0..index].width();
                         buffer.push_str(&text[0..index]);
                     }
 
                     // Add tab.
                     let spaces = width - (*cursor % width);
                     *cursor += spaces;
-                    buffer.push_str(&*" ".repeat(spaces));
+                    buffer.push_str(&" ".repeat(spaces));
 
                     // Next.
                     text = &text[index + 1..text.len()];
                 }
 
-                *cursor += text.len();
+                *cursor += text.width();
                 buffer.push_str(text);
             }
         }
@@ -33,7 +37,70 @@ pub fn expand_tabs(line: &str, width: usize, cursor: &mut usize) -> String {
     buffer
 }
 
-pub fn replace_nonprintable(input: &str, tab_width: usize) -> String {
+/// The symbols shown for non-printable characters by `show_nonprintable`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NonprintableSymbols {
+    pub space: char,
+    /// Followed by spaces up to the tab width. Tabs are drawn as `├──┤` if there is none.
+    pub tab: Option<char>,
+    pub line_feed: char,
+    pub carriage_return: char,
+    /// Show every other control character as its control picture (`␁`, `␡`, ...), not only
+    /// NUL, BEL, BS and ESC
+    pub all_controls: bool,
+}
+
+impl NonprintableSymbols {
+    pub const DEFAULT: NonprintableSymbols = NonprintableSymbols {
+        space: '•',
+        tab: None,
+        line_feed: '␊',
+        carriage_return: '␍',
+        all_controls: false,
+    };
+
+    /// The Unicode control pictures (`␠`, `␉`, `␊`, ...) for all control characters.
+    pub const CONTROL_PICTURES: NonprintableSymbols = NonprintableSymbols {
+        space: '␠',
+        tab: Some('␉'),
+        line_feed: '␊',
+        carriage_return: '␍',
+        all_controls: true,
+    };
+
+    fn is_whitespace(&self, c: char) -> bool {
+        c == self.space
+            || match self.tab {
+                Some(tab) => c == tab,
+                None => matches!(c, '↹' | '├' | '─' | '┤'),
+            }
+    }
+
+    fn is_line_break(&self, c: char) -> bool {
+        c == self.line_feed || c == self.carriage_return
+    }
+}
+
+impl Default for NonprintableSymbols {
+    fn default() -> Self {
+        NonprintableSymbols::DEFAULT
+    }
+}
+
+/// The control picture of an ASCII control character, e.g. `␛` for ESC.
+fn control_picture(c: char) -> Option<char> {
+    match c {
+        '\x00'..='\x1F' => char::from_u32(0x2400 + c as u32),
+        '\x7F' => Some('␡'),
+        _ => None,
+    }
+}
+
+pub fn replace_nonprintable(
+    input: &str,
+    tab_width: usize,
+    symbols: &NonprintableSymbols,
+) -> String {
     let mut output = String::new();
 
     let tab_width = if tab_width == 0 { 4 } else { tab_width };
@@ -41,33 +108,232 @@ pub fn replace_nonprintable(input: &str, tab_width: usize) -> String {
     for chr in input.chars() {
         match chr {
             // space
-            ' ' => output.push('•'),
+            ' ' => output.push(symbols.space),
             // tab
-            '\t' => {
-                if tab_width == 1 {
-                    output.push('↹');
-                } else {
+            '\t' => match symbols.tab {
+                Some(tab) => {
+                    output.push(tab);
+                    output.push_str(&" ".repeat(tab_width - 1));
+                }
+                None if tab_width == 1 => output.push('↹'),
+                None => {
                     output.push('├');
                     output.push_str(&"─".repeat(tab_width - 2));
                     output.push('┤');
                 }
-            }
+            },
             // line feed
-            '\x0A' => output.push('␊'),
+            '\x0A' => output.push(symbols.line_feed),
             // carriage return
-            '\x0D' => output.push('␍'),
-            // null
-            '\x00' => output.push('␀'),
-            // bell
-            '\x07' => output.push('␇'),
-            // backspace
-            '\x08' => output.push('␈'),
-            // escape
-            '\x1B' => output.push('␛'),
+            '\x0D' => output.push(symbols.carriage_return),
+            // null, bell, backspace and escape
+            '\x00' | '\x07' | '\x08' | '\x1B' => output.extend(control_picture(chr)),
             // anything else
-            _ => output.push(chr),
+            _ => match control_picture(chr) {
+                Some(picture) if symbols.all_controls => output.push(picture),
+                _ => output.push(chr),
+            },
         }
     }
 
     output
 }
+
+/// Removes ANSI escape sequences: CSI sequences like the colors of `ESC[1;31m` and OSC sequences
+/// like hyperlinks, which end with BEL or `ESC \`. An unterminated sequence is removed up to the
+/// end of the line.
+pub fn strip_ansi(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1B' {
+            output.push(c);
+            continue;
+        }
+        match chars.peek() {
+            Some('[') => {
+                chars.next();
+                // Parameter and intermediate bytes, then one final byte.
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7E').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                chars.next();
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1B' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => output.push(c),
+        }
+    }
+
+    output
+}
+
+/// A stage of the preprocessing, which transforms the text of each line before it is
+/// highlighted, e.g. to normalize Unicode or to strip a BOM, see `.preprocessor()`. Lines end
+/// with their line break, except for the last line of a file.
+pub trait Preprocessor: Debug + Send + Sync {
+    fn process(&self, line: &str) -> String;
+}
+
+/// Expands tabs to spaces, up to the next multiple of `width` columns. It leaves the tabs of
+/// lines alone if `width` is zero.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TabExpander {
+    pub width: usize,
+}
+
+impl TabExpander {
+    /// Expands the tabs of `text` which starts at column `cursor`, and moves the cursor to its
+    /// end. Highlighted regions are expanded one after the other this way.
+    pub fn expand(&self, text: &str, cursor: &mut usize) -> String {
+        if self.width > 0 {
+            expand_tabs(text, self.width, cursor)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+impl Preprocessor for TabExpander {
+    fn process(&self, line: &str) -> String {
+        self.expand(line, &mut 0)
+    }
+}
+
+/// Replaces spaces, tabs, line breaks and control characters by visible `symbols`, see
+/// `show_nonprintable`. Tabs take `tab_width` columns (4 if it's zero).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NonprintableReplacer {
+    pub tab_width: usize,
+    pub symbols: NonprintableSymbols,
+}
+
+impl Preprocessor for NonprintableReplacer {
+    fn process(&self, line: &str) -> String {
+        replace_nonprintable(line, self.tab_width, &self.symbols)
+    }
+}
+
+/// Removes ANSI escape sequences, see `strip_ansi`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnsiStripper;
+
+impl Preprocessor for AnsiStripper {
+    fn process(&self, line: &str) -> String {
+        strip_ansi(line)
+    }
+}
+
+#[test]
+fn expand_tabs_after_wide_characters() {
+    let mut cursor = 0;
+    assert_eq!("日本    x", expand_tabs("日本\tx", 8, &mut cursor));
+    assert_eq!(9, cursor);
+}
+
+/// The whitespace at the end of `line`, in front of the line break. With `nonprintable`
+/// symbols, the markers of `replace_nonprintable` count as whitespace, too.
+pub fn trailing_whitespace(
+    line: &str,
+    nonprintable: Option<&NonprintableSymbols>,
+) -> Option<Range<usize>> {
+    let is_line_break =
+        |c| matches!(c, '\r' | '\n') || nonprintable.is_some_and(|s| s.is_line_break(c));
+    let is_whitespace =
+        |c| matches!(c, ' ' | '\t') || nonprintable.is_some_and(|s| s.is_whitespace(c));
+
+    let end = line.trim_end_matches(is_line_break).len();
+    let start = line[..end].trim_end_matches(is_whitespace).len();
+    if start < end {
+        Some(start..end)
+    } else {
+        None
+    }
+}
+
+/// Replaces spaces and tabs by visible markers.
+pub fn mark_whitespace(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            ' ' => '·',
+            '\t' => '→',
+            c => c,
+        })
+        .collect()
+}
+
+#[test]
+fn trailing_whitespaces() {
+    assert_eq!(Some(3..6), trailing_whitespace("abc \t \r\n", None));
+    assert_eq!(None, trailing_whitespace("abc\n", None));
+    assert_eq!(None, trailing_whitespace("a•b\n", None));
+
+    let symbols = NonprintableSymbols::default();
+    let line = replace_nonprintable("a  \n", 4, &symbols);
+    assert_eq!(Some(1..7), trailing_whitespace(&line, Some(&symbols)));
+
+    assert_eq!("a··→", mark_whitespace("a  \t"));
+}
+
+#[test]
+fn nonprintable_symbols() {
+    let input = "a \tb\x01\x1b\r\n";
+    let symbols = NonprintableSymbols::default();
+    assert_eq!("a•├──┤b\x01␛␍␊", replace_nonprintable(input, 4, &symbols));
+
+    let symbols = NonprintableSymbols::CONTROL_PICTURES;
+    assert_eq!("a␠␉   b␁␛␍␊", replace_nonprintable(input, 4, &symbols));
+
+    let symbols = NonprintableSymbols {
+        space: '␣',
+        tab: Some('⇥'),
+        ..NonprintableSymbols::DEFAULT
+    };
+    assert_eq!("␣⇥ ", replace_nonprintable(" \t", 2, &symbols));
+}
+
+#[test]
+fn ansi_sequences() {
+    assert_eq!(
+        "error: x\n",
+        strip_ansi("\x1b[1;31merror\x1b[0m: x\x1b[K\n")
+    );
+    assert_eq!(
+        "see docs",
+        strip_ansi("see \x1b]8;;https://example.com\x07docs\x1b]8;;\x1b\\")
+    );
+    assert_eq!("a\x1b(B", strip_ansi("a\x1b(B"));
+    assert_eq!("a", strip_ansi("a\x1b[1;3"));
+}
+
+#[test]
+fn preprocessors() {
+    let stages: Vec<Box<dyn Preprocessor>> = vec![
+        Box::new(AnsiStripper),
+        Box::new(TabExpander { width: 4 }),
+        Box::new(NonprintableReplacer {
+            tab_width: 4,
+            symbols: NonprintableSymbols::default(),
+        }),
+    ];
+    let line = stages
+        .iter()
+        .fold("\x1b[1ma\tb\x1b[0m\n".to_string(), |line, stage| {
+            stage.process(&line)
+        });
+    assert_eq!("a•••b␊", line);
+
+    assert_eq!("\tx", TabExpander { width: 0 }.process("\tx"));
+}
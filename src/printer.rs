@@ -1,95 +1,271 @@
+use std::fs;
 use std::io::Write;
+use std::iter::{self, Peekable};
+use std::str::{Chars, FromStr};
 use std::vec::Vec;
 
-use ansi_term::Colour::Fixed;
+use ansi_term::Colour::{Fixed, Red};
 use ansi_term::Style;
 
 use console::AnsiCodeIterator;
 
-use syntect::easy::HighlightLines;
-use syntect::highlighting::Theme;
+use syntect::highlighting::{self, FontStyle, ScopeSelectors, Theme};
 use syntect::parsing::SyntaxSet;
 
 use content_inspector::ContentType;
 
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
 use encoding::all::{UTF_16BE, UTF_16LE};
-use encoding::{DecoderTrap, Encoding};
+use encoding::label::encoding_from_whatwg_label;
+use encoding::EncodingRef;
+
+use regex::Regex;
 
 use crate::assets::HighlightingAssets;
-use crate::decorations::{Decoration, GridBorderDecoration, LineNumberDecoration};
+use crate::bbcode;
+use crate::builder::{widest_run, PrettyPrint};
+use crate::changes;
+use crate::decoding::{self, InvalidBytes};
+use crate::decorations::{
+    format_line_number, format_line_range, ContinuationDecoration, CustomDecoration,
+    GridBorderDecoration, LineChangesDecoration, LineNumberDecoration, OffsetDecoration,
+    PanelDecoration, TimestampDecoration,
+};
 use crate::errors::*;
+use crate::hexdump::{format_row, Hexdump};
+use crate::html::{self, HtmlStyle, HtmlTabs, HtmlWrapper, END_HTML, START_HTML};
+use crate::injection::LineHighlighter;
 use crate::inputfile::{InputFile, InputFileReader};
-use crate::preprocessor::{expand_tabs, replace_nonprintable};
-use crate::style::OutputComponents;
+use crate::json;
+use crate::latex;
+use crate::literate::{self, Prose};
+use crate::metadata;
+use crate::overlay;
+use crate::overview::{sparkline, Minimap};
+use crate::preprocessor::{expand_tabs, mark_whitespace, trailing_whitespace, TabExpander};
+use crate::rtf;
 use crate::style::OutputWrap;
-use crate::syntax_mapping::SyntaxMapping;
+use crate::style::{Accessibility, GridCharset, OutputComponents, TrailingWhitespace};
+use crate::svg;
 use crate::terminal::{as_terminal_escaped, to_ansi_color};
+use crate::timestamps::Timestamps;
+use crate::transform::{self, Transform};
+use crate::urls::{self, LinkRule};
+use crate::xref::{self, IdentifierIndex, TableOfContents};
 
+/// Prints a file, driven by `PrettyPrint::print_with()`: first the header, then each line (in
+/// the line ranges or not) and the markers between them and finally the footer. Lines are
+/// passed as read, with their line break.
 pub trait Printer {
     fn print_header(
         &mut self,
-        handle: &mut Write,
+        handle: &mut dyn Write,
         file: &InputFile,
         header_overwrite: Option<String>,
     ) -> Result<()>;
-    fn print_footer(&mut self, handle: &mut Write) -> Result<()>;
+    fn print_footer(&mut self, handle: &mut dyn Write) -> Result<()>;
+    /// Prints a line, unless it's `out_of_range`. Lines out of the line ranges are passed as
+    /// well, so that the state of a highlighter can follow the whole file.
     fn print_line(
         &mut self,
         out_of_range: bool,
-        handle: &mut Write,
+        handle: &mut dyn Write,
         line_number: usize,
         line_buffer: &[u8],
     ) -> Result<()>;
+    /// Prints the definitions found in all lines of the file, with their line numbers.
+    fn print_table_of_contents(
+        &mut self,
+        _handle: &mut dyn Write,
+        _lines: &[Vec<u8>],
+    ) -> Result<()> {
+        Ok(())
+    }
+    /// Computes the minimap, from all lines of the file, to be shown in at most `height` rows.
+    fn prepare_minimap(&mut self, _lines: &[Vec<u8>], _height: usize) -> Result<()> {
+        Ok(())
+    }
+    /// Parses the following lines of the file ahead of printing them, on several threads.
+    fn prepare_highlighting(&mut self, _lines: &[Vec<u8>]) -> Result<()> {
+        Ok(())
+    }
+    /// Whether the printer won't print any more lines, so that the rest of the input isn't read.
+    fn finished(&self) -> bool {
+        false
+    }
+    /// Prints a marker for skipped lines.
+    fn print_snip(&mut self, _handle: &mut dyn Write) -> Result<()> {
+        Ok(())
+    }
+    /// Sets the number of times the lines printed next occur in a row, for the range of their
+    /// line numbers. Printers which don't show it ignore it.
+    fn set_repeats(&mut self, _repeats: usize) {}
+    /// Prints a line which occurs `repeats` times in a row, starting at `line_number`. By
+    /// default, the line is printed once, with the `repeats` set for it.
+    fn print_repeated_line(
+        &mut self,
+        handle: &mut dyn Write,
+        line_number: usize,
+        line_buffer: &[u8],
+        repeats: usize,
+    ) -> Result<()> {
+        self.set_repeats(repeats);
+        let result = self.print_line(false, handle, line_number, line_buffer);
+        self.set_repeats(1);
+        result
+    }
 }
 
 pub struct InteractivePrinter<'a> {
+    config: &'a PrettyPrint,
+    colored_output: bool,
     colors: Colors,
-    decorations: Vec<Box<dyn Decoration>>,
+    decorations: Vec<Box<dyn PanelDecoration>>,
     panel_width: usize,
     ansi_prefix_sgr: String,
     content_type: ContentType,
-    highlighter: Option<HighlightLines<'a>>,
+    highlighter: Option<LineHighlighter<'a>>,
     syntax_set: &'a SyntaxSet,
+    theme: &'a Theme,
     output_components: OutputComponents,
-    colored_output: bool,
-    true_color: bool,
+    /// Decodes lines which aren't UTF-8 or UTF-16
+    encoding: Option<EncodingRef>,
+    /// Lines are addressed by byte offset, for the `HexdumpPrinter`
+    hexdump: bool,
+    pub timestamps: Option<Timestamps>,
+    /// Number of times the current line occurs in a row
+    pub repeats: usize,
+    line_lengths: Vec<usize>,
+    /// Highlights the whole file upfront to find the definitions of the table of contents
+    contents_highlighter: Option<(LineHighlighter<'a>, usize)>,
+    minimap: Option<Minimap>,
+    minimap_row: usize,
+    /// Matches the words given to `.emphasize_words()`
+    emphasis: Option<Regex>,
+    emphasis_color: Option<highlighting::Color>,
+    link_rules: Vec<LinkRule>,
+    /// Matches the patterns given to `.highlight_pattern()`, with the style painted over them
+    matches: Option<(Regex, Style)>,
+    /// Indices of the tracked spans of the highlighter
+    prose_spans: Option<usize>,
+    identifier_spans: Option<usize>,
+    keyword_spans: Option<usize>,
+    identifiers: IdentifierIndex,
+    grid_chars: GridCharset,
+    /// Documentation comments waiting to be printed as prose
+    prose: Option<Prose>,
+    /// Lines with rows wider than `assert_max_width`
+    too_wide: Vec<usize>,
     term_width: usize,
-    tab_width: usize,
-    show_nonprintable: bool,
-    output_wrap: OutputWrap,
-    use_italic_text: bool,
+    /// The absolute path of the file, if it's linked in the header or by the line numbers
+    linked_path: Option<String>,
+    /// The background of the theme, for `.use_background()`
+    background: Option<Style>,
 }
 
 impl<'a> InteractivePrinter<'a> {
     pub fn new(
+        config: &'a PrettyPrint,
         assets: &'a HighlightingAssets,
         file: &InputFile,
         reader: &mut InputFileReader,
-        output_components: OutputComponents,
-        theme: String,
-        colored_output: bool,
-        true_color: bool,
-        term_width: usize,
-        language: Option<String>,
-        syntax_mapping: SyntaxMapping,
-        tab_width: usize,
-        show_nonprintable: bool,
-        output_wrap: OutputWrap,
-        use_italic_text: bool,
-    ) -> Self {
-        let theme = assets.get_theme(&theme);
+    ) -> Result<Self> {
+        let theme = config.get_theme(assets);
+        let output_components = config.get_output_components();
 
+        let colored_output = config.use_colors();
         let colors = if colored_output {
-            Colors::colored(theme, true_color)
+            Colors::colored(theme, config.true_color)
         } else {
             Colors::plain()
         };
 
+        // Hexdumps are addressed by offset instead of line number.
+        let hexdump = config.shows_hexdump(reader.content_type);
+
         // Create decorations.
-        let mut decorations: Vec<Box<dyn Decoration>> = Vec::new();
+        let mut decorations: Vec<Box<dyn PanelDecoration>> = Vec::new();
 
         if output_components.numbers() {
-            decorations.push(Box::new(LineNumberDecoration::new(&colors)));
+            if hexdump {
+                decorations.push(Box::new(OffsetDecoration::new(&colors)));
+            } else {
+                // Wide enough for the last line number, if the length of the input is known.
+                let lines = match file {
+                    InputFile::String(string) => string.lines().count(),
+                    InputFile::Bytes(bytes) => bytes.split_inclusive(|&byte| byte == b'\n').count(),
+                    _ => 1,
+                };
+                let last_line_number = config.first_line_number + lines.max(1) - 1;
+                let format = config.line_number_formatter.as_ref();
+                let digits = [config.first_line_number, last_line_number]
+                    .iter()
+                    .map(|&line_number| format_line_number(format, line_number).width())
+                    .fold(4, usize::max);
+
+                // Collapsed repeats show a range of line numbers (e.g. `12-15`), as wide as the
+                // widest one if the lines are known ahead. The first output format version always
+                // left room for two line numbers.
+                let width = if config.dedup_repeats && config.output_version() == 1 {
+                    2 * digits + 1
+                } else if config.dedup_repeats && reader.content_type.is_text() {
+                    match repeated_lines(config, file, reader.content_type) {
+                        Some(lines) => {
+                            let format = config.line_number_formatter.as_ref();
+                            widest_run(lines, config.first_line_number, format)
+                                .map_or(digits, |width| width.max(digits))
+                        }
+                        None => 2 * digits + 1,
+                    }
+                } else {
+                    digits
+                };
+                decorations.push(Box::new(LineNumberDecoration::new(
+                    &colors,
+                    &config.continuation_marker,
+                    width,
+                    config.line_number_formatter.clone(),
+                )));
+            }
+        } else if config.output_version() > 1
+            && !config.continuation_marker.is_empty()
+            && config.output_wrap == OutputWrap::Character
+            && !hexdump
+        {
+            decorations.push(Box::new(ContinuationDecoration::new(
+                &colors,
+                &config.continuation_marker,
+            )));
+        }
+
+        if output_components.changes() && !hexdump {
+            let changes = match file {
+                InputFile::Ordinary(filename) if !config.detached => {
+                    changes::get_git_diff(filename)
+                }
+                _ => None,
+            };
+            if let Some(changes) = changes {
+                decorations.push(Box::new(LineChangesDecoration::new(
+                    changes,
+                    config.first_line_number,
+                    colored_output,
+                )));
+            }
+        }
+
+        let timestamps = if config.timestamp_pattern.is_empty() || hexdump {
+            None
+        } else {
+            decorations.push(Box::new(TimestampDecoration::new(&colors)));
+            Some(Timestamps::new(&config.timestamp_pattern)?)
+        };
+
+        if !hexdump {
+            for decoration in &config.custom_decorations {
+                decorations.push(Box::new(CustomDecoration(decoration.clone())));
+            }
         }
 
         let mut panel_width: usize =
@@ -99,101 +275,386 @@ impl<'a> InteractivePrinter<'a> {
         // print_horizontal_line, print_header, and print_footer functions all assume the panel
         // width is without the grid border.
         if output_components.grid() && !decorations.is_empty() {
-            decorations.push(Box::new(GridBorderDecoration::new(&colors)));
+            decorations.push(Box::new(GridBorderDecoration::new(
+                &colors,
+                config.get_grid_charset().vertical,
+            )));
         }
 
         // Disable the panel if the terminal is too small (i.e. can't fit 5 characters with the
         // panel showing).
+        let term_width = config.current_width();
         if term_width < (decorations.len() + decorations.iter().fold(0, |a, x| a + x.width())) + 5 {
             decorations.clear();
             panel_width = 0;
         }
 
+        let mut contents_highlighter = None;
+        let mut prose_spans = None;
+        let mut identifier_spans = None;
+        let mut keyword_spans = None;
+        let mut prose = None;
         let highlighter = if reader.content_type.is_binary() {
             None
         } else {
             // Determine the type of syntax for highlighting
-            let syntax = assets.get_syntax(language, file, reader, &syntax_mapping);
-            Some(HighlightLines::new(syntax, theme))
+            let syntax = assets.get_syntax(
+                config.syntax_language(),
+                file,
+                reader,
+                &config.get_syntax_mapping()?,
+                config.modelines,
+            );
+            if config.table_of_contents {
+                let mut highlighter =
+                    LineHighlighter::new(syntax, theme, &config.injections, &assets.syntax_set);
+                let selector = ScopeSelectors::from_str(xref::DEFINITION_SELECTOR).unwrap();
+                let index = highlighter.track(selector);
+                contents_highlighter = Some((highlighter, index));
+            }
+            let mut highlighter =
+                LineHighlighter::new(syntax, theme, &config.injections, &assets.syntax_set);
+            if config.prose_checker.is_some() {
+                let selector = ScopeSelectors::from_str(overlay::PROSE_SELECTOR).unwrap();
+                prose_spans = Some(highlighter.track(selector));
+            }
+            if config.identifier_index {
+                let selector = ScopeSelectors::from_str(xref::IDENTIFIER_SELECTOR).unwrap();
+                identifier_spans = Some(highlighter.track(selector));
+            }
+            if config.accessibility == Accessibility::Markers {
+                let selector = ScopeSelectors::from_str(overlay::KEYWORD_SELECTOR).unwrap();
+                keyword_spans = Some(highlighter.track(selector));
+            }
+            if config.literate {
+                prose = literate::doc_syntax(&syntax.name).map(Prose::new);
+            }
+            Some(highlighter)
+        };
+
+        let encoding = match config.encoding_label {
+            Some(ref label) => Some(
+                encoding_from_whatwg_label(label)
+                    .ok_or_else(|| format!("Unknown encoding '{}'", label))?,
+            ),
+            None => None,
+        };
+
+        let link_rules = config
+            .link_rules
+            .iter()
+            .map(|(pattern, template)| LinkRule::new(pattern, template))
+            .collect::<Result<_>>()?;
+
+        let linked_path = match file {
+            InputFile::Ordinary(filename)
+                if colored_output
+                    && (config.header_hyperlink || config.line_link_template.is_some()) =>
+            {
+                fs::canonicalize(filename)
+                    .ok()
+                    .map(|path| path.to_string_lossy().into_owned())
+            }
+            _ => None,
         };
 
-        InteractivePrinter {
+        let matches = match_regex(&config.highlight_patterns)?.map(|regex| {
+            let style = match theme.settings.find_highlight {
+                Some(color) if colored_output => {
+                    Style::new().on(to_ansi_color(color, config.true_color))
+                }
+                _ => Style::new().underline(),
+            };
+            (regex, style)
+        });
+
+        let background = theme
+            .settings
+            .background
+            .filter(|_| config.use_background && colored_output)
+            .map(|color| Style::new().on(to_ansi_color(color, config.true_color)));
+
+        Ok(InteractivePrinter {
+            config,
+            term_width,
+            linked_path,
+            background,
+            colored_output,
             panel_width,
             colors,
             decorations,
             content_type: reader.content_type,
+            encoding,
             ansi_prefix_sgr: String::new(),
             highlighter,
             syntax_set: &assets.syntax_set,
+            theme,
             output_components,
-            colored_output,
-            true_color,
-            term_width,
-            tab_width,
-            show_nonprintable,
-            output_wrap,
-            use_italic_text,
-        }
+            hexdump,
+            timestamps,
+            repeats: 1,
+            line_lengths: Vec::new(),
+            contents_highlighter,
+            minimap: None,
+            minimap_row: 0,
+            emphasis: overlay::words_regex(&config.emphasized_words),
+            emphasis_color: theme.settings.find_highlight,
+            link_rules,
+            matches,
+            prose_spans,
+            identifier_spans,
+            keyword_spans,
+            identifiers: IdentifierIndex::default(),
+            grid_chars: config.get_grid_charset(),
+            prose,
+            too_wide: Vec::new(),
+        })
     }
 
-    fn print_horizontal_line(&mut self, handle: &mut Write, grid_char: char) -> Result<()> {
+    fn print_horizontal_line(&mut self, handle: &mut dyn Write, grid_char: char) -> Result<()> {
         if self.panel_width == 0 {
             writeln!(
                 handle,
                 "{}",
-                self.colors.grid.paint("─".repeat(self.term_width))
+                self.colors.grid.paint(self.hline(self.term_width))
             )?;
         } else {
-            let hline = "─".repeat(self.term_width - (self.panel_width + 1));
-            let hline = format!("{}{}{}", "─".repeat(self.panel_width), grid_char, hline);
+            let hline = self.hline(self.term_width - (self.panel_width + 1));
+            let hline = format!("{}{}{}", self.hline(self.panel_width), grid_char, hline);
             writeln!(handle, "{}", self.colors.grid.paint(hline))?;
         }
 
         Ok(())
     }
 
-    fn preprocess(&self, text: &str, cursor: &mut usize) -> String {
-        if self.tab_width > 0 {
-            expand_tabs(text, self.tab_width, cursor)
+    /// A horizontal line of the grid, `width` columns wide.
+    fn hline(&self, width: usize) -> String {
+        iter::repeat_n(self.grid_chars.horizontal, width).collect()
+    }
+
+    /// Prints the top line of the grid with the title embedded (`──┤ main.rs ├──`). Returns
+    /// `false` if the title doesn't fit.
+    fn print_horizontal_line_with_title(
+        &mut self,
+        handle: &mut dyn Write,
+        grid_char: char,
+        title: &str,
+        styled_title: &str,
+    ) -> Result<bool> {
+        let chars = self.grid_chars;
+        let left = if self.panel_width == 0 {
+            format!("{}{} ", self.hline(2), chars.title_left)
+        } else {
+            let panel = self.hline(self.panel_width);
+            format!(
+                "{}{}{}{} ",
+                panel,
+                grid_char,
+                self.hline(2),
+                chars.title_left
+            )
+        };
+        let right = format!(" {}", chars.title_right);
+
+        let width = left.width() + title.width() + right.width();
+        if width > self.term_width {
+            return Ok(false);
+        }
+
+        let right = format!("{}{}", right, self.hline(self.term_width - width));
+        writeln!(
+            handle,
+            "{}{}{}",
+            self.colors.grid.paint(left),
+            styled_title,
+            self.colors.grid.paint(right)
+        )?;
+
+        Ok(true)
+    }
+
+    /// The space of the panel, for rows without decorations.
+    fn panel_padding(&self) -> String {
+        if self.panel_width == 0 {
+            String::new()
+        } else if self.output_components.grid() {
+            format!(
+                "{}{} ",
+                " ".repeat(self.panel_width),
+                self.grid_chars.vertical
+            )
         } else {
-            text.to_string()
+            " ".repeat(self.panel_width + 1)
         }
     }
-}
 
-impl<'a> Printer for InteractivePrinter<'a> {
-    fn print_header(
-        &mut self,
-        handle: &mut Write,
-        file: &InputFile,
-        header_overwrite: Option<String>,
-    ) -> Result<()> {
-        if !self.output_components.header() {
-            return Ok(());
+    fn print_overview(&mut self, handle: &mut dyn Write) -> Result<()> {
+        let panel = self.panel_padding();
+
+        let width = self.term_width.saturating_sub(panel.width());
+        if self.output_components.grid() {
+            self.print_horizontal_line(handle, self.grid_chars.mid_cross)?;
         }
+        writeln!(
+            handle,
+            "{}{}",
+            self.colors.grid.paint(panel),
+            self.colors
+                .line_number
+                .paint(sparkline(&self.line_lengths, width))
+        )?;
+
+        Ok(())
+    }
 
+    /// Prints the identifiers of the file and the lines they occur in.
+    fn print_identifier_index(&mut self, handle: &mut dyn Write) -> Result<()> {
+        let panel = self.panel_padding();
         if self.output_components.grid() {
-            self.print_horizontal_line(handle, '┬')?;
+            self.print_horizontal_line(handle, self.grid_chars.mid_cross)?;
+        }
 
-            write!(
+        let width = self
+            .identifiers
+            .entries()
+            .map(|(identifier, _)| identifier.width())
+            .max()
+            .unwrap_or(0);
+        for (identifier, lines) in self.identifiers.entries() {
+            let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+            writeln!(
                 handle,
-                "{}{}",
-                " ".repeat(self.panel_width),
-                self.colors
-                    .grid
-                    .paint(if self.panel_width > 0 { "│ " } else { "" }),
+                "{}{}{}  {}",
+                self.colors.grid.paint(&panel),
+                self.colors.filename.paint(identifier),
+                " ".repeat(width - identifier.width()),
+                self.colors.line_number.paint(lines.join(", "))
             )?;
+        }
+
+        Ok(())
+    }
+
+    /// Prints the collected documentation comments as wrapped paragraphs.
+    fn print_prose(&mut self, handle: &mut dyn Write) -> Result<()> {
+        let panel = self.panel_padding();
+        let width = self.term_width.saturating_sub(panel.width());
+        let rows = match self.prose {
+            Some(ref mut prose) if !prose.is_empty() => prose.flush(width),
+            _ => return Ok(()),
+        };
+
+        for row in rows {
+            writeln!(handle, "{}{}", self.colors.grid.paint(&panel), row)?;
+        }
+
+        Ok(())
+    }
+
+    /// Prints the repeat counter of the line, if any, and returns its width.
+    fn print_repeats(&self, handle: &mut dyn Write) -> Result<usize> {
+        if self.repeats == 1 {
+            return Ok(0);
+        }
+
+        let counter = format!(" (×{})", self.repeats);
+        if self.colored_output {
+            write!(handle, "{}", Style::new().dimmed().paint(&counter))?;
         } else {
-            write!(handle, "{}", " ".repeat(self.panel_width))?;
+            write!(handle, "{}", counter)?;
+        }
+
+        Ok(counter.width())
+    }
+
+    /// Prints a marker for the line break of the line, if enabled, and returns its width.
+    fn print_line_ending(&self, handle: &mut dyn Write, line: &str) -> Result<usize> {
+        let marker = if !self.config.show_line_endings {
+            return Ok(0);
+        } else if line.ends_with("\r\n") {
+            "␍␊"
+        } else if line.ends_with('\n') {
+            "␊"
+        } else {
+            return Ok(0);
+        };
+
+        if self.colored_output {
+            write!(handle, "{}", Style::new().dimmed().paint(marker))?;
+        } else {
+            write!(handle, "{}", marker)?;
+        }
+
+        Ok(marker.width())
+    }
+
+    /// Prints the next cell of the minimap, `padding` columns after the content of the line.
+    fn print_minimap_cell(&mut self, handle: &mut dyn Write, padding: usize) -> Result<()> {
+        let cell = self
+            .minimap
+            .as_ref()
+            .and_then(|minimap| minimap.cells.get(self.minimap_row))
+            .cloned();
+        self.minimap_row += 1;
+
+        if let Some((color, block)) = cell {
+            write!(handle, "{} ", " ".repeat(padding))?;
+            if self.colored_output {
+                let color = to_ansi_color(color, self.config.true_color);
+                write!(handle, "{}", color.paint(block.to_string()))?;
+            } else {
+                write!(handle, "{}", block)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The metadata of a file selected for the header, like `ls -l` shows it.
+    fn metadata_fields(&self, filename: &str) -> String {
+        if self.config.detached {
+            return String::new();
+        }
+
+        let metadata = match fs::metadata(filename) {
+            Ok(metadata) => metadata,
+            Err(_) => return String::new(),
         };
 
+        let mut fields = Vec::new();
+        if self.output_components.permissions() {
+            fields.extend(metadata::format_permissions(&metadata));
+        }
+        if self.output_components.size() {
+            fields.push(self.config.formatting_hooks.format_size(metadata.len()));
+        }
+        if self.output_components.modified() {
+            let formatting = &self.config.formatting_hooks;
+            fields.extend(
+                metadata
+                    .modified()
+                    .ok()
+                    .map(|modified| formatting.format_modified(modified)),
+            );
+        }
+
+        fields.iter().map(|field| format!("   {}", field)).collect()
+    }
+
+    /// The parts of the header: a prefix like `File: `, the name of the file and details like
+    /// the content type, the transforms and the metadata of the file.
+    fn header_title(
+        &self,
+        file: &InputFile,
+        header_overwrite: Option<String>,
+    ) -> (String, String, String) {
         let (prefix, name): (&str, String) = match header_overwrite {
             Some(overwrite) => ("", overwrite),
             None => match file {
-                InputFile::Ordinary(filename) => ("File: ", filename.to_string()),
+                InputFile::Ordinary(filename) => (&self.config.header_prefix, filename.to_string()),
                 InputFile::String(_) => ("", "".to_string()),
-                // _ => ("", &"STDIN".to_string()),
-                _ => unimplemented!(),
+                InputFile::Bytes(bytes) => ("", format!("{} bytes", bytes.len())),
+                InputFile::StdIn => ("", "STDIN".to_string()),
             },
         };
 
@@ -204,57 +665,68 @@ impl<'a> Printer for InteractivePrinter<'a> {
             _ => "",
         };
 
-        writeln!(
-            handle,
-            "{}{}{}",
-            prefix,
-            self.colors.filename.paint(name),
-            mode
-        )?;
-
-        if self.output_components.grid() {
-            if self.content_type.is_text() {
-                self.print_horizontal_line(handle, '┼')?;
+        let transforms =
+            if !self.config.transforms.is_empty() && transform::applies_to(self.content_type) {
+                let transforms: Vec<String> = self
+                    .config
+                    .transforms
+                    .iter()
+                    .map(|t| t.to_string())
+                    .collect();
+                format!("   <{}>", transforms.join(", "))
             } else {
-                self.print_horizontal_line(handle, '┴')?;
-            }
-        }
+                String::new()
+            };
 
-        Ok(())
+        let metadata = match file {
+            InputFile::Ordinary(filename) => self.metadata_fields(filename),
+            _ => String::new(),
+        };
+
+        (
+            prefix.to_string(),
+            name,
+            format!("{}{}{}", mode, transforms, metadata),
+        )
     }
 
-    fn print_footer(&mut self, handle: &mut Write) -> Result<()> {
-        if self.output_components.grid() && self.content_type.is_text() {
-            self.print_horizontal_line(handle, '┴')
-        } else {
-            Ok(())
+    /// The link of the line number, see `.line_number_links()`.
+    pub fn line_link(&self, line_number: usize) -> Option<String> {
+        match (&self.config.line_link_template, &self.linked_path) {
+            (Some(template), Some(path)) => Some(urls::line_link(template, path, line_number)),
+            _ => None,
         }
     }
 
-    fn print_line(
+    /// Prints the rows of a line, see `print_line`.
+    fn write_line(
         &mut self,
         out_of_range: bool,
-        handle: &mut Write,
+        handle: &mut dyn Write,
         line_number: usize,
         line_buffer: &[u8],
     ) -> Result<()> {
-        let mut line = match self.content_type {
-            ContentType::BINARY => {
-                return Ok(());
+        // Invalid UTF-8 can't be highlighted without losing bytes.
+        if self.config.copy_fidelity && std::str::from_utf8(line_buffer).is_err() {
+            if !out_of_range {
+                handle.write_all(line_buffer)?;
             }
-            ContentType::UTF_16LE => UTF_16LE
-                .decode(&line_buffer, DecoderTrap::Strict)
-                .unwrap_or("Invalid UTF-16LE".into()),
-            ContentType::UTF_16BE => UTF_16BE
-                .decode(&line_buffer, DecoderTrap::Strict)
-                .unwrap_or("Invalid UTF-16BE".into()),
-            _ => String::from_utf8_lossy(&line_buffer).to_string(),
+            return Ok(());
+        }
+
+        let line = match self.decode_line(line_number, line_buffer)? {
+            Some(line) => line,
+            None => return Ok(()),
         };
 
-        if self.show_nonprintable {
-            line = replace_nonprintable(&mut line, self.tab_width);
+        // The timestamps are found in the line as it is, before the transforms and
+        // `.show_nonprintable()` change it.
+        if let Some(ref mut timestamps) = self.timestamps {
+            timestamps.update(&line);
         }
 
+        let line = self.config.preprocess(line, Some(self.config.tab_width));
+
         let regions = {
             let highlighter = match self.highlighter {
                 Some(ref mut highlighter) => highlighter,
@@ -265,10 +737,135 @@ impl<'a> Printer for InteractivePrinter<'a> {
             highlighter.highlight(line.as_ref(), self.syntax_set)
         };
 
+        let regions = match self.emphasis {
+            Some(ref words) => {
+                let color = self.emphasis_color;
+                let ranges = overlay::match_ranges(words, &line);
+                overlay::overlay(&line, regions, &ranges, |style| {
+                    overlay::emphasize(style, color)
+                })
+            }
+            None => regions,
+        };
+
+        let underline = |style: highlighting::Style| highlighting::Style {
+            font_style: style.font_style | FontStyle::UNDERLINE,
+            ..style
+        };
+
+        let regions = match (
+            &self.config.prose_checker,
+            &self.highlighter,
+            self.prose_spans,
+        ) {
+            (Some(checker), Some(highlighter), Some(index)) => {
+                let ranges = checker.check(&line, &highlighter.tracked_spans[index]);
+                overlay::overlay(&line, regions, &ranges, underline)
+            }
+            _ => regions,
+        };
+
+        let regions = match (&self.highlighter, self.keyword_spans) {
+            (Some(highlighter), Some(index)) => {
+                overlay::overlay(&line, regions, &highlighter.tracked_spans[index], underline)
+            }
+            _ => regions,
+        };
+
+        if let (Some(highlighter), Some(index)) = (&self.highlighter, self.identifier_spans) {
+            if !out_of_range {
+                let spans = &highlighter.tracked_spans[index];
+                self.identifiers.add(&line, spans, line_number);
+            }
+        }
+
+        let hyperlinks = self.config.url_hyperlinks && self.colored_output;
+        let links = if self.config.underline_urls || hyperlinks {
+            urls::find_links(&line, &self.link_rules)
+        } else {
+            Vec::new()
+        };
+        // The regions are split at the links for the hyperlinks, even if they aren't underlined.
+        let link_ranges: Vec<_> = links.iter().map(|(range, _)| range.clone()).collect();
+        let regions = if self.config.underline_urls {
+            overlay::overlay(&line, regions, &link_ranges, underline)
+        } else {
+            overlay::overlay(&line, regions, &link_ranges, |style| style)
+        };
+
+        let trailing = match self.config.trailing_whitespace {
+            TrailingWhitespace::None => None,
+            _ => {
+                let nonprintable = Some(&self.config.nonprintable_symbols)
+                    .filter(|_| self.config.show_nonprintable);
+                trailing_whitespace(&line, nonprintable)
+            }
+        };
+        let regions = overlay::overlay(&line, regions, trailing.as_slice(), |style| style);
+
+        let match_ranges = match self.matches {
+            Some((ref regex, _)) => {
+                overlay::match_ranges(regex, line.trim_end_matches(['\r', '\n']))
+            }
+            None => Vec::new(),
+        };
+        let regions = overlay::overlay(&line, regions, &match_ranges, |style| style);
+
+        // The target each region links to. Regions got split at the boundaries of the links.
+        let mut offset = 0;
+        let links: Vec<Option<&str>> = regions
+            .iter()
+            .map(|(_, text)| {
+                let start = offset;
+                offset += text.len();
+                if hyperlinks {
+                    urls::link_at(&links, start)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        // Whether each region is trailing whitespace, which got split off as well.
+        let mut offset = 0;
+        let trailing: Vec<bool> = regions
+            .iter()
+            .map(|(_, text)| {
+                let start = offset;
+                offset += text.len();
+                trailing
+                    .as_ref()
+                    .is_some_and(|range| range.contains(&start))
+            })
+            .collect();
+
+        // Whether each region is a match of `.highlight_pattern()`, split off as well.
+        let mut offset = 0;
+        let matched: Vec<bool> = regions
+            .iter()
+            .map(|(_, text)| {
+                let start = offset;
+                offset += text.len();
+                match_ranges.iter().any(|range| range.contains(&start))
+            })
+            .collect();
+
         if out_of_range {
             return Ok(());
         }
 
+        if self.config.overview {
+            let length = line.trim_end_matches(['\r', '\n']).width();
+            self.line_lengths.push(length);
+        }
+
+        if let Some(ref mut prose) = self.prose {
+            if prose.take(&line, line_number) {
+                return Ok(());
+            }
+        }
+        self.print_prose(handle)?;
+
         let mut cursor: usize = 0;
         let mut cursor_max: usize = self.term_width;
         let mut cursor_total: usize = 0;
@@ -279,7 +876,7 @@ impl<'a> Printer for InteractivePrinter<'a> {
             let decorations = self
                 .decorations
                 .iter()
-                .map(|ref d| d.generate(line_number, false, self))
+                .map(|d| d.generate(line_number, false, self))
                 .collect::<Vec<_>>();
 
             for deco in decorations {
@@ -288,33 +885,86 @@ impl<'a> Printer for InteractivePrinter<'a> {
             }
         }
 
+        // The minimap gets the last two columns.
+        if self.minimap.is_some() {
+            cursor_max = cursor_max.saturating_sub(2);
+        }
+        let minimap_column = cursor_max;
+
         // Line contents.
-        if self.output_wrap == OutputWrap::None {
-            let true_color = self.true_color;
+        if self.config.output_wrap == OutputWrap::None {
+            let true_color = self.config.true_color;
             let colored_output = self.colored_output;
-            let italics = self.use_italic_text;
+            let italics = self.config.use_italic_text;
+
+            // The line ending marker, the repeat counter and the minimap go before the line break.
+            // Painted backgrounds end before it too.
+            let suffixed = self.repeats > 1
+                || self.minimap.is_some()
+                || self.config.show_line_endings
+                || self.background.is_some();
 
-            for &(style, region) in regions.iter() {
-                let text = &*self.preprocess(region, &mut cursor_total);
+            let marks = links.iter().zip(&trailing).zip(&matched);
+            for (&(style, region), ((&link, &trailing), &matched)) in regions.iter().zip(marks) {
+                let region = if suffixed {
+                    region.trim_end_matches(['\r', '\n'])
+                } else {
+                    region
+                };
+                let text = self.preprocess(region, &mut cursor_total);
+                let text = &*self.mark_trailing(trailing, text);
                 write!(
                     handle,
                     "{}",
-                    as_terminal_escaped(style, &*text, true_color, colored_output, italics,)
+                    urls::hyperlink(
+                        link,
+                        self.paint_trailing(
+                            trailing,
+                            self.paint_match(
+                                matched,
+                                as_terminal_escaped(
+                                    style,
+                                    text,
+                                    true_color,
+                                    colored_output,
+                                    italics
+                                )
+                            )
+                        )
+                    )
                 )?;
             }
 
-            if line.bytes().next_back() != Some(b'\n') {
-                write!(handle, "\n")?;
+            let suffix_width =
+                self.print_line_ending(handle, &line)? + self.print_repeats(handle)?;
+            if self.minimap.is_some() {
+                let used = self.line_width(&regions) + suffix_width;
+                self.print_minimap_cell(handle, minimap_column.saturating_sub(used))?;
+            }
+
+            let faithful = self.config.copy_fidelity;
+            if suffixed || (line.bytes().next_back() != Some(b'\n') && !faithful) {
+                writeln!(handle)?;
             }
         } else {
-            for &(style, region) in regions.iter() {
-                let mut ansi_iterator = AnsiCodeIterator::new(region);
+            // Truncated lines keep the last column for the ellipsis.
+            let truncate = self.config.output_wrap == OutputWrap::Truncate
+                && self.line_width(&regions) > cursor_max - cursor;
+            if truncate {
+                cursor_max -= 1;
+            }
+
+            let marks = links.iter().zip(&trailing).zip(&matched);
+            'regions: for (&(style, region), ((&link, &trailing), &matched)) in
+                regions.iter().zip(marks)
+            {
+                let ansi_iterator = AnsiCodeIterator::new(region);
                 let mut ansi_prefix: String = String::new();
                 for chunk in ansi_iterator {
                     match chunk {
                         // ANSI escape passthrough.
                         (text, true) => {
-                            if text.chars().last().map_or(false, |c| c == 'm') {
+                            if text.ends_with('m') {
                                 ansi_prefix.push_str(text);
                                 if text == "\x1B[0m" {
                                     self.ansi_prefix_sgr = "\x1B[0m".to_owned();
@@ -328,39 +978,85 @@ impl<'a> Printer for InteractivePrinter<'a> {
 
                         // Regular text.
                         (text, false) => {
-                            let text = self.preprocess(
-                                text.trim_right_matches(|c| c == '\r' || c == '\n'),
-                                &mut cursor_total,
-                            );
+                            let text = self
+                                .preprocess(text.trim_end_matches(['\r', '\n']), &mut cursor_total);
+                            let text = self.mark_trailing(trailing, text);
 
-                            let mut chars = text.chars();
-                            let mut remaining = text.chars().count();
+                            let mut chars = text.chars().peekable();
+                            let mut remaining = text.width();
 
-                            while remaining > 0 {
+                            // Text without width, e.g. a combining mark, is printed as well.
+                            while chars.peek().is_some() {
                                 let available = cursor_max - cursor;
 
                                 // It fits.
                                 if remaining <= available {
-                                    let text = chars.by_ref().take(remaining).collect::<String>();
+                                    let text = chars.by_ref().collect::<String>();
                                     cursor += remaining;
 
                                     write!(
                                         handle,
                                         "{}",
-                                        as_terminal_escaped(
-                                            style,
-                                            &*format!(
-                                                "{}{}{}",
-                                                self.ansi_prefix_sgr, ansi_prefix, text
-                                            ),
-                                            self.true_color,
-                                            self.colored_output,
-                                            self.use_italic_text
+                                        self.paint_trailing(
+                                            trailing,
+                                            urls::hyperlink(
+                                                link,
+                                                self.paint_match(
+                                                    matched,
+                                                    as_terminal_escaped(
+                                                        style,
+                                                        &format!(
+                                                            "{}{}{}",
+                                                            self.ansi_prefix_sgr, ansi_prefix, text
+                                                        ),
+                                                        self.config.true_color,
+                                                        self.colored_output,
+                                                        self.config.use_italic_text
+                                                    )
+                                                )
+                                            )
                                         )
                                     )?;
                                     break;
                                 }
 
+                                // It gets cut off.
+                                if truncate {
+                                    let (text, width) = take_columns(&mut chars, available, false);
+                                    cursor += width + 1;
+                                    let ellipsis = if self.colored_output {
+                                        Style::new().dimmed().paint("…").to_string()
+                                    } else {
+                                        "…".to_string()
+                                    };
+
+                                    write!(
+                                        handle,
+                                        "{}{}",
+                                        self.paint_trailing(
+                                            trailing,
+                                            urls::hyperlink(
+                                                link,
+                                                self.paint_match(
+                                                    matched,
+                                                    as_terminal_escaped(
+                                                        style,
+                                                        &format!(
+                                                            "{}{}{}",
+                                                            self.ansi_prefix_sgr, ansi_prefix, text
+                                                        ),
+                                                        self.config.true_color,
+                                                        self.colored_output,
+                                                        self.config.use_italic_text
+                                                    )
+                                                )
+                                            )
+                                        ),
+                                        ellipsis
+                                    )?;
+                                    break 'regions;
+                                }
+
                                 // Generate wrap padding if not already generated.
                                 if panel_wrap.is_none() {
                                     panel_wrap = if self.panel_width > 0 {
@@ -368,9 +1064,7 @@ impl<'a> Printer for InteractivePrinter<'a> {
                                             "{} ",
                                             self.decorations
                                                 .iter()
-                                                .map(|ref d| d
-                                                    .generate(line_number, true, self)
-                                                    .text)
+                                                .map(|d| d.generate(line_number, true, self).text)
                                                 .collect::<Vec<String>>()
                                                 .join(" ")
                                         ))
@@ -380,22 +1074,32 @@ impl<'a> Printer for InteractivePrinter<'a> {
                                 }
 
                                 // It wraps.
-                                let text = chars.by_ref().take(available).collect::<String>();
+                                let (text, width) =
+                                    take_columns(&mut chars, available, cursor == 0);
                                 cursor = 0;
-                                remaining -= available;
+                                remaining -= width;
 
                                 write!(
                                     handle,
                                     "{}\n{}",
-                                    as_terminal_escaped(
-                                        style,
-                                        &*format!(
-                                            "{}{}{}",
-                                            self.ansi_prefix_sgr, ansi_prefix, text
-                                        ),
-                                        self.true_color,
-                                        self.colored_output,
-                                        self.use_italic_text
+                                    self.paint_trailing(
+                                        trailing,
+                                        urls::hyperlink(
+                                            link,
+                                            self.paint_match(
+                                                matched,
+                                                as_terminal_escaped(
+                                                    style,
+                                                    &format!(
+                                                        "{}{}{}",
+                                                        self.ansi_prefix_sgr, ansi_prefix, text
+                                                    ),
+                                                    self.config.true_color,
+                                                    self.colored_output,
+                                                    self.config.use_italic_text
+                                                )
+                                            )
+                                        )
                                     ),
                                     panel_wrap.clone().unwrap()
                                 )?;
@@ -408,38 +1112,1495 @@ impl<'a> Printer for InteractivePrinter<'a> {
                 }
             }
 
-            write!(handle, "\n")?;
+            let suffix_width =
+                self.print_line_ending(handle, &line)? + self.print_repeats(handle)?;
+            if self.minimap.is_some() {
+                let padding = minimap_column.saturating_sub(cursor + suffix_width);
+                self.print_minimap_cell(handle, padding)?;
+            }
+            writeln!(handle)?;
         }
 
-        Ok(())
+        self.print_annotations(handle, line_number, &line)
     }
-}
-
-const DEFAULT_GUTTER_COLOR: u8 = 238;
 
-#[derive(Default)]
-pub struct Colors {
-    pub grid: Style,
-    pub filename: Style,
-    pub line_number: Style,
-}
+    /// Prints the annotations of a line beneath it, see `.annotate()`.
+    fn print_annotations(
+        &self,
+        handle: &mut dyn Write,
+        line_number: usize,
+        line: &str,
+    ) -> Result<()> {
+        let mut annotations: Vec<_> = self
+            .config
+            .annotations
+            .iter()
+            .filter(|annotation| annotation.line == line_number)
+            .collect();
+        annotations.sort_by_key(|annotation| annotation.col_range.start);
 
-impl Colors {
-    fn plain() -> Self {
-        Colors::default()
+        for annotation in annotations {
+            if self.panel_width > 0 {
+                for decoration in &self.decorations {
+                    write!(
+                        handle,
+                        "{} ",
+                        decoration.generate(line_number, true, self).text
+                    )?;
+                }
+            }
+            let (column, width) = annotation.carets(line, self.config.tab_width);
+            let mut marker = "^".repeat(width);
+            if !annotation.message.is_empty() {
+                marker.push(' ');
+                marker.push_str(&annotation.message);
+            }
+            let marker = if self.colored_output {
+                annotation.level.style().paint(marker).to_string()
+            } else {
+                marker
+            };
+            writeln!(handle, "{}{}", " ".repeat(column), marker)?;
+        }
+        Ok(())
     }
 
-    fn colored(theme: &Theme, true_color: bool) -> Self {
-        let gutter_color = theme
-            .settings
-            .gutter_foreground
-            .map(|c| to_ansi_color(c, true_color))
-            .unwrap_or(Fixed(DEFAULT_GUTTER_COLOR));
+    /// Fails if rows were wider than `assert_max_width`.
+    fn check_width(&self) -> Result<()> {
+        let max_width = match self.config.assert_max_width {
+            Some(max_width) => max_width,
+            None => return Ok(()),
+        };
 
-        Colors {
-            grid: gutter_color.normal(),
-            filename: Style::new().bold(),
-            line_number: gutter_color.normal(),
+        let mut offenders = Vec::new();
+        if self.output_components.grid() && self.term_width > max_width {
+            offenders.push("the grid".to_string());
+        }
+        if !self.too_wide.is_empty() {
+            let numbers: Vec<String> = self.too_wide.iter().map(|n| n.to_string()).collect();
+            let lines = if numbers.len() == 1 { "line" } else { "lines" };
+            offenders.push(format!("{} {}", lines, numbers.join(", ")));
+        }
+
+        if offenders.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Rows are wider than {} columns: {}",
+                max_width,
+                offenders.join(", ")
+            )
+            .into())
         }
     }
+
+    /// Decodes a line of the input, `None` if it's left out.
+    fn decode_line(&self, line_number: usize, line_buffer: &[u8]) -> Result<Option<String>> {
+        let encoding: Option<EncodingRef> = match self.content_type {
+            ContentType::BINARY => return Ok(None),
+            ContentType::UTF_16LE => Some(UTF_16LE),
+            ContentType::UTF_16BE => Some(UTF_16BE),
+            _ => self.encoding,
+        };
+        let line = match (
+            decoding::decode(line_buffer, encoding),
+            &self.config.invalid_bytes,
+        ) {
+            (Some(line), _) => line,
+            (None, InvalidBytes::Strict) => {
+                return Err(format!(
+                    "Invalid {} in line {}",
+                    decoding::name(encoding),
+                    line_number
+                )
+                .into());
+            }
+            (None, InvalidBytes::SkipLine) => return Ok(None),
+            (None, InvalidBytes::Lossy { replacement }) => {
+                decoding::decode_lossy(line_buffer, encoding, replacement)
+            }
+        };
+        Ok(Some(line))
+    }
+
+    /// The `lines` as they are highlighted when printed, `None` if one of them isn't decoded or
+    /// nothing is highlighted.
+    fn highlighted_texts(&self, lines: &[Vec<u8>]) -> Option<Vec<String>> {
+        self.highlighter.as_ref()?;
+        lines
+            .iter()
+            .map(|line| {
+                let line = self.decode_line(0, line).ok()??;
+                Some(self.config.preprocess(line, Some(self.config.tab_width)))
+            })
+            .collect()
+    }
+
+    fn has_body(&self) -> bool {
+        self.content_type.is_text() || self.hexdump
+    }
+
+    /// Width of the visible text of a highlighted line in terminal columns.
+    fn line_width(&self, regions: &[(highlighting::Style, &str)]) -> usize {
+        let mut cursor = 0;
+        regions
+            .iter()
+            .flat_map(|&(_, region)| AnsiCodeIterator::new(region))
+            .filter(|&(_, is_ansi)| !is_ansi)
+            .map(|(text, _)| {
+                self.preprocess(text.trim_end_matches(['\r', '\n']), &mut cursor)
+                    .width()
+            })
+            .sum()
+    }
+
+    /// Replaces trailing whitespace by markers, if they are enabled. Markers for accessibility
+    /// replace its background too.
+    fn mark_trailing(&self, trailing: bool, text: String) -> String {
+        match self.config.trailing_whitespace {
+            TrailingWhitespace::Markers if trailing => mark_whitespace(&text),
+            TrailingWhitespace::Background
+                if trailing && self.config.accessibility == Accessibility::Markers =>
+            {
+                mark_whitespace(&text)
+            }
+            _ => text,
+        }
+    }
+
+    /// Colors the background of trailing whitespace, if enabled.
+    fn paint_trailing(&self, trailing: bool, painted: String) -> String {
+        match self.config.trailing_whitespace {
+            TrailingWhitespace::Background if trailing && self.colored_output => {
+                Style::new().on(Red).paint(painted).to_string()
+            }
+            _ => painted,
+        }
+    }
+
+    /// Paints the style of `.highlight_pattern()` over a match, keeping its syntax colors.
+    fn paint_match(&self, matched: bool, painted: String) -> String {
+        match self.matches {
+            Some((_, style)) if matched && self.colored_output => style.paint(painted).to_string(),
+            _ => painted,
+        }
+    }
+
+    fn preprocess(&self, text: &str, cursor: &mut usize) -> String {
+        TabExpander {
+            width: self.config.tab_width,
+        }
+        .expand(text, cursor)
+    }
+
+    /// Prints what `write` writes, on the background of the theme if `.use_background()` is set.
+    fn painted<F>(&mut self, handle: &mut dyn Write, write: F) -> Result<()>
+    where
+        F: FnOnce(&mut Self, &mut dyn Write) -> Result<()>,
+    {
+        if self.background.is_none() {
+            return write(self, handle);
+        }
+        let mut rows = Vec::new();
+        write(self, &mut rows)?;
+        self.write_painted(handle, &rows)
+    }
+
+    /// Paints `rows` with the background of the theme, up to the terminal width. Resets within
+    /// the rows turn the background back on.
+    fn write_painted(&self, handle: &mut dyn Write, rows: &[u8]) -> Result<()> {
+        let background = match self.background {
+            Some(background) => background.prefix().to_string(),
+            None => return Ok(handle.write_all(rows)?),
+        };
+        for row in String::from_utf8_lossy(rows).split_inclusive('\n') {
+            let (text, newline) = match row.strip_suffix('\n') {
+                Some(text) => (text, "\n"),
+                None => (row, ""),
+            };
+            let padding = self
+                .term_width
+                .saturating_sub(console::measure_text_width(text));
+            write!(
+                handle,
+                "{}{}{}\x1b[0m{}",
+                background,
+                text.replace("\x1b[0m", &format!("\x1b[0m{}", background)),
+                " ".repeat(padding),
+                newline
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_header(
+        &mut self,
+        handle: &mut dyn Write,
+        file: &InputFile,
+        header_overwrite: Option<String>,
+    ) -> Result<()> {
+        if !self.output_components.header() {
+            return Ok(());
+        }
+
+        let (prefix, name, details) = self.header_title(file, header_overwrite);
+        let title = format!("{}{}{}", prefix, name, details);
+        // The details, like the size, may change between prints of the same file.
+        let identity = format!("{}{}", prefix, name);
+        let url = match self.linked_path {
+            Some(ref path) if self.config.header_hyperlink => {
+                Some(format!("file://{}", urls::url_path(path)))
+            }
+            _ => None,
+        };
+        let styled_name =
+            urls::hyperlink(url.as_deref(), self.colors.filename.paint(name).to_string());
+        let styled_title = format!("{}{}{}", prefix, styled_name, details);
+
+        if self.config.collapse_repeated_header && !identity.is_empty() {
+            let mut last_header = self
+                .config
+                .last_header
+                .lock()
+                .unwrap_or_else(|error| error.into_inner());
+            if last_header.as_ref() == Some(&identity) {
+                if self.output_components.grid() && self.has_body() {
+                    self.print_horizontal_line(handle, self.grid_chars.top_cross)?;
+                }
+                return Ok(());
+            }
+            *last_header = Some(identity);
+        }
+
+        if self.output_components.grid() && self.config.frame_title {
+            let grid_char = if self.has_body() {
+                self.grid_chars.top_cross
+            } else {
+                self.grid_chars.horizontal
+            };
+            if title.is_empty() {
+                return self.print_horizontal_line(handle, grid_char);
+            }
+            if self.print_horizontal_line_with_title(handle, grid_char, &title, &styled_title)? {
+                return Ok(());
+            }
+        }
+
+        if self.output_components.grid() {
+            self.print_horizontal_line(handle, self.grid_chars.top_cross)?;
+
+            let border = if self.panel_width > 0 {
+                format!("{} ", self.grid_chars.vertical)
+            } else {
+                String::new()
+            };
+            write!(
+                handle,
+                "{}{}",
+                " ".repeat(self.panel_width),
+                self.colors.grid.paint(border),
+            )?;
+        } else {
+            write!(handle, "{}", " ".repeat(self.panel_width))?;
+        };
+
+        writeln!(handle, "{}", styled_title)?;
+
+        if self.output_components.grid() {
+            if self.has_body() {
+                self.print_horizontal_line(handle, self.grid_chars.mid_cross)?;
+            } else {
+                self.print_horizontal_line(handle, self.grid_chars.bottom_cross)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_footer(&mut self, handle: &mut dyn Write) -> Result<()> {
+        self.print_prose(handle)?;
+
+        if self.config.overview && !self.line_lengths.is_empty() {
+            self.print_overview(handle)?;
+        }
+
+        if !self.identifiers.is_empty() {
+            self.print_identifier_index(handle)?;
+        }
+
+        if let Some(ref text) = self.config.footer {
+            if self.output_components.grid() && self.has_body() {
+                self.print_horizontal_line(handle, self.grid_chars.mid_cross)?;
+            }
+            writeln!(
+                handle,
+                "{}{}",
+                self.colors.grid.paint(self.panel_padding()),
+                self.colors.filename.paint(text)
+            )?;
+        }
+
+        if self.output_components.grid() && self.has_body() {
+            self.print_horizontal_line(handle, self.grid_chars.bottom_cross)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_snip(&mut self, handle: &mut dyn Write) -> Result<()> {
+        self.print_prose(handle)?;
+
+        let panel = self.panel_padding();
+        let panel_width = panel.width();
+
+        let title = " 8< ";
+        let line = self.hline(self.term_width.saturating_sub(panel_width + title.len()) / 2);
+        writeln!(
+            handle,
+            "{}",
+            self.colors
+                .grid
+                .paint(format!("{}{}{}{}", panel, line, title, line))
+        )?;
+
+        Ok(())
+    }
+}
+
+impl<'a> Printer for InteractivePrinter<'a> {
+    fn print_header(
+        &mut self,
+        handle: &mut dyn Write,
+        file: &InputFile,
+        header_overwrite: Option<String>,
+    ) -> Result<()> {
+        self.painted(handle, |printer, handle| {
+            printer.write_header(handle, file, header_overwrite)
+        })
+    }
+
+    fn print_footer(&mut self, handle: &mut dyn Write) -> Result<()> {
+        self.painted(handle, Self::write_footer)?;
+        self.check_width()
+    }
+
+    fn print_line(
+        &mut self,
+        out_of_range: bool,
+        handle: &mut dyn Write,
+        line_number: usize,
+        line_buffer: &[u8],
+    ) -> Result<()> {
+        let max_width = self.config.assert_max_width.filter(|_| !out_of_range);
+        if max_width.is_none() && self.background.is_none() {
+            return self.write_line(out_of_range, handle, line_number, line_buffer);
+        }
+
+        let mut rows = Vec::new();
+        self.write_line(out_of_range, &mut rows, line_number, line_buffer)?;
+        if let Some(max_width) = max_width {
+            if String::from_utf8_lossy(&rows)
+                .lines()
+                .any(|row| console::measure_text_width(row) > max_width)
+            {
+                self.too_wide.push(line_number);
+            }
+        }
+        self.write_painted(handle, &rows)
+    }
+
+    fn print_snip(&mut self, handle: &mut dyn Write) -> Result<()> {
+        self.painted(handle, Self::write_snip)
+    }
+
+    fn print_table_of_contents(&mut self, handle: &mut dyn Write, lines: &[Vec<u8>]) -> Result<()> {
+        let (mut highlighter, index) = match self.contents_highlighter.take() {
+            Some(highlighter) => highlighter,
+            None => return Ok(()),
+        };
+
+        let mut contents = TableOfContents::default();
+        for (number, line) in lines.iter().enumerate() {
+            let line = String::from_utf8_lossy(line);
+            highlighter.highlight(&line, self.syntax_set);
+            let spans = &highlighter.tracked_spans[index];
+            contents.add(&line, spans, self.config.first_line_number + number);
+        }
+        if contents.entries.is_empty() {
+            return Ok(());
+        }
+
+        let panel = self.panel_padding();
+        let width = contents
+            .entries
+            .iter()
+            .map(|(name, _)| name.width())
+            .max()
+            .unwrap_or(0);
+        for (name, line_number) in &contents.entries {
+            let line_number =
+                format_line_number(self.config.line_number_formatter.as_ref(), *line_number);
+            writeln!(
+                handle,
+                "{}{}{}  {}",
+                self.colors.grid.paint(&panel),
+                self.colors.filename.paint(name),
+                " ".repeat(width - name.width()),
+                self.colors.line_number.paint(line_number)
+            )?;
+        }
+        if self.output_components.grid() {
+            self.print_horizontal_line(handle, self.grid_chars.mid_cross)?;
+        }
+
+        Ok(())
+    }
+
+    fn prepare_highlighting(&mut self, lines: &[Vec<u8>]) -> Result<()> {
+        // The lines must be highlighted as they are printed, or not at all.
+        match (self.highlighted_texts(lines), self.highlighter.as_mut()) {
+            (Some(texts), Some(highlighter)) => {
+                highlighter.parse_ahead(texts, self.syntax_set, self.config.highlight_threads)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn prepare_minimap(&mut self, lines: &[Vec<u8>], height: usize) -> Result<()> {
+        // The lines are parsed once, for the minimap, and highlighted from there as they're
+        // printed.
+        self.prepare_highlighting(lines)?;
+        if let Some(ref highlighter) = self.highlighter {
+            self.minimap = Some(Minimap::new(&highlighter.regions_ahead(), height));
+        }
+        Ok(())
+    }
+
+    fn set_repeats(&mut self, repeats: usize) {
+        self.repeats = repeats;
+    }
+}
+
+/// The lines of `file` in the order they're printed in, for the runs of `.dedup_repeats()`.
+/// Files are read ahead for them, STDIN can't be, and random samples differ each time.
+fn repeated_lines(
+    config: &PrettyPrint,
+    file: &InputFile,
+    content_type: ContentType,
+) -> Option<Vec<Vec<u8>>> {
+    let sampled = config
+        .transforms
+        .iter()
+        .any(|transform| matches!(transform, Transform::Sample(_)));
+    if sampled {
+        return None;
+    }
+
+    let content = match file {
+        InputFile::String(string) => string.as_bytes().to_vec(),
+        InputFile::Bytes(bytes) => bytes.clone(),
+        InputFile::Ordinary(filename) if !config.detached => fs::read(filename).ok()?,
+        _ => return None,
+    };
+    let mut lines: Vec<Vec<u8>> = content
+        .split_inclusive(|&byte| byte == b'\n')
+        .map(|line| line.to_vec())
+        .collect();
+
+    if !config.transforms.is_empty() && transform::applies_to(content_type) {
+        for line in lines.iter_mut().filter(|line| line.last() != Some(&b'\n')) {
+            line.push(b'\n');
+        }
+        lines = transform::apply(&config.transforms, lines);
+    }
+    Some(lines)
+}
+
+/// Takes as many characters as fit into `columns` terminal cells. Wide characters that don't
+/// fit are left for the next row, unless `force` is set and nothing would be taken at all.
+fn take_columns(chars: &mut Peekable<Chars>, columns: usize, force: bool) -> (String, usize) {
+    let mut text = String::new();
+    let mut width = 0;
+
+    while let Some(&c) = chars.peek() {
+        let w = c.width().unwrap_or(0);
+        if width + w > columns && !(force && text.is_empty()) {
+            break;
+        }
+        text.push(c);
+        width += w;
+        chars.next();
+    }
+
+    (text, width)
+}
+
+/// The width of tabs in the markup formats which expand them, where a `tab_width` of zero
+/// (keeping tabs on the terminal) can't keep them. HTML has `html::tab_width_or_default`.
+fn expanded_tab_width(tab_width: usize) -> usize {
+    if tab_width == 0 {
+        4
+    } else {
+        tab_width
+    }
+}
+
+/// Prints the input as a hexdump (offset gutter, hex bytes and an ASCII column), inside the
+/// frame of an `InteractivePrinter`.
+pub struct HexdumpPrinter<'a> {
+    frame: InteractivePrinter<'a>,
+    hexdump: Hexdump,
+    /// Number of bytes shown so far and cut off after `binary_preview_bytes`
+    shown: usize,
+    cut_off: usize,
+    /// Number of bytes of the input so far, and of the whole input if it's known
+    seen: usize,
+    size: Option<usize>,
+}
+
+/// A regex matching any of `patterns`, or `None` if there are none.
+fn match_regex(patterns: &[String]) -> Result<Option<Regex>> {
+    for pattern in patterns {
+        Regex::new(pattern)
+            .map_err(|error| format!("Invalid highlight pattern '{}': {}", pattern, error))?;
+    }
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let alternatives: Vec<String> = patterns
+        .iter()
+        .map(|pattern| format!("(?:{})", pattern))
+        .collect();
+    let regex = Regex::new(&alternatives.join("|"))
+        .chain_err(|| "Could not combine the highlight patterns")?;
+    Ok(Some(regex))
+}
+
+impl<'a> HexdumpPrinter<'a> {
+    pub fn new(frame: InteractivePrinter<'a>) -> Self {
+        HexdumpPrinter {
+            frame,
+            hexdump: Hexdump::new(),
+            shown: 0,
+            cut_off: 0,
+            seen: 0,
+            size: None,
+        }
+    }
+
+    fn print_rows(&mut self, handle: &mut dyn Write, flush: bool) -> Result<()> {
+        let frame = &self.frame;
+        while let Some((offset, bytes)) = self.hexdump.next_row(flush) {
+            if frame.panel_width > 0 {
+                for deco in frame.decorations.iter() {
+                    write!(handle, "{} ", deco.generate(offset, false, frame).text)?;
+                }
+            }
+            writeln!(handle, "{}", format_row(&bytes))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Printer for HexdumpPrinter<'a> {
+    fn print_header(
+        &mut self,
+        handle: &mut dyn Write,
+        file: &InputFile,
+        header_overwrite: Option<String>,
+    ) -> Result<()> {
+        self.size = file.size();
+        self.frame.print_header(handle, file, header_overwrite)
+    }
+
+    fn print_footer(&mut self, handle: &mut dyn Write) -> Result<()> {
+        self.print_rows(handle, true)?;
+        if self.finished() {
+            // The rest of the input isn't read, so it's only counted if its size is known.
+            let note = match self.size {
+                Some(size) => format!(
+                    "… {} more bytes not shown",
+                    size.saturating_sub(self.seen) + self.cut_off
+                ),
+                None => "… more bytes not shown".to_string(),
+            };
+            writeln!(
+                handle,
+                "{}{}",
+                self.frame.colors.grid.paint(self.frame.panel_padding()),
+                self.frame.colors.line_number.paint(note)
+            )?;
+        }
+        self.frame.print_footer(handle)
+    }
+
+    /// Lines outside of the line ranges are left out, the offsets stay those of the input.
+    fn print_line(
+        &mut self,
+        out_of_range: bool,
+        handle: &mut dyn Write,
+        _line_number: usize,
+        line_buffer: &[u8],
+    ) -> Result<()> {
+        self.seen += line_buffer.len();
+        if out_of_range {
+            self.print_rows(handle, true)?;
+            self.hexdump.skip(line_buffer.len());
+            return Ok(());
+        }
+
+        let limit = self.frame.config.binary_preview_bytes.unwrap_or(usize::MAX);
+        let len = line_buffer.len().min(limit - self.shown);
+        self.hexdump.push(&line_buffer[..len]);
+        self.shown += len;
+        self.cut_off += line_buffer.len() - len;
+
+        self.print_rows(handle, false)
+    }
+
+    /// Done once bytes were cut off, the limit may end right at the end of the input otherwise.
+    fn finished(&self) -> bool {
+        self.cut_off > 0
+    }
+}
+
+/// Prints the input as HTML, by default a standalone page with a table for the header, the line
+/// numbers and the highlighted lines.
+pub struct HtmlPrinter<'a> {
+    frame: InteractivePrinter<'a>,
+    wrapper: HtmlWrapper,
+    /// Number of columns of the table
+    columns: usize,
+    /// The classes of `HtmlStyle::Classes` with the indices of their tracked spans
+    classes: Vec<(&'static str, usize)>,
+}
+
+impl<'a> HtmlPrinter<'a> {
+    pub fn new(mut frame: InteractivePrinter<'a>) -> Self {
+        let columns = if frame.output_components.numbers() {
+            2
+        } else {
+            1
+        };
+
+        let mut classes = Vec::new();
+        if let (HtmlStyle::Classes, Some(highlighter)) =
+            (frame.config.html_style, frame.highlighter.as_mut())
+        {
+            for &(class, selector, _) in html::CLASSES {
+                let selector = ScopeSelectors::from_str(selector).unwrap();
+                classes.push((class, highlighter.track(selector)));
+            }
+        }
+
+        HtmlPrinter {
+            wrapper: frame.config.html_wrapper,
+            frame,
+            columns,
+            classes,
+        }
+    }
+
+    /// A row spanning all columns of the table.
+    fn print_wide_row(&self, handle: &mut dyn Write, class: &str, text: &str) -> Result<()> {
+        if self.wrapper == HtmlWrapper::None {
+            return Ok(());
+        }
+        writeln!(
+            handle,
+            "<tr class=\"{}\"><td colspan=\"{}\">{}</td></tr>",
+            class,
+            self.columns,
+            html::escape(text)
+        )?;
+        Ok(())
+    }
+}
+
+impl<'a> Printer for HtmlPrinter<'a> {
+    fn print_header(
+        &mut self,
+        handle: &mut dyn Write,
+        file: &InputFile,
+        header_overwrite: Option<String>,
+    ) -> Result<()> {
+        let header = if self.frame.output_components.header() {
+            Some(self.frame.header_title(file, header_overwrite))
+        } else {
+            None
+        };
+        let title = header.as_ref().map_or("", |(_, name, _)| name.as_str());
+
+        let mut stylesheet = html::stylesheet(
+            self.frame.theme,
+            self.frame
+                .config
+                .html_tabs
+                .tab_size(self.frame.config.tab_width),
+            self.wrapper == HtmlWrapper::Page,
+        );
+        let classes = self.frame.config.html_style == HtmlStyle::Classes;
+        if classes {
+            stylesheet.push_str(&html::class_rules(self.frame.theme));
+        }
+        match self.wrapper {
+            HtmlWrapper::Page => write!(
+                handle,
+                "{}<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n",
+                START_HTML,
+                html::escape(title),
+                stylesheet
+            )?,
+            HtmlWrapper::Table if !classes => write!(handle, "<style>\n{}</style>\n", stylesheet)?,
+            HtmlWrapper::Table => {}
+            HtmlWrapper::None => return Ok(()),
+        }
+
+        let class = if self.frame.output_components.grid() {
+            "prettyprint pp-grid"
+        } else {
+            "prettyprint"
+        };
+        writeln!(handle, "<table class=\"{}\">", class)?;
+        if let Some((prefix, name, details)) = header {
+            writeln!(
+                handle,
+                "<thead><tr><th colspan=\"{}\">{}<span class=\"pp-filename\">{}</span>{}</th></tr></thead>",
+                self.columns,
+                html::escape(&prefix),
+                html::escape(&name),
+                html::escape(&details)
+            )?;
+        }
+        writeln!(handle, "<tbody>")?;
+
+        Ok(())
+    }
+
+    fn print_footer(&mut self, handle: &mut dyn Write) -> Result<()> {
+        if self.wrapper == HtmlWrapper::None {
+            return Ok(());
+        }
+
+        writeln!(handle, "</tbody>")?;
+        if let Some(ref text) = self.frame.config.footer {
+            writeln!(handle, "<tfoot>")?;
+            self.print_wide_row(handle, "pp-footer", text)?;
+            writeln!(handle, "</tfoot>")?;
+        }
+        writeln!(handle, "</table>")?;
+        if self.wrapper == HtmlWrapper::Page {
+            write!(handle, "{}", END_HTML)?;
+        }
+
+        Ok(())
+    }
+
+    fn print_line(
+        &mut self,
+        out_of_range: bool,
+        handle: &mut dyn Write,
+        line_number: usize,
+        line_buffer: &[u8],
+    ) -> Result<()> {
+        let frame = &mut self.frame;
+        let line = match frame.decode_line(line_number, line_buffer)? {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        // Kept tabs stay tabs, their width is up to the `tab-size` of the page.
+        let line = match frame.config.html_tabs {
+            HtmlTabs::Keep => frame.config.preprocess(line, Some(1)),
+            HtmlTabs::Expand => {
+                let width = html::tab_width_or_default(frame.config.tab_width);
+                expand_tabs(&frame.config.preprocess(line, Some(width)), width, &mut 0)
+            }
+        };
+
+        let (regions, highlighter) = match frame.highlighter {
+            Some(ref mut highlighter) => {
+                (highlighter.highlight(&line, frame.syntax_set), highlighter)
+            }
+            None => return Ok(()),
+        };
+        if out_of_range {
+            return Ok(());
+        }
+
+        // The class of each region, by the tracked spans its start falls into.
+        let tracked = &self.classes;
+        let mut offset = 0;
+        let classes: Vec<Option<&str>> = regions
+            .iter()
+            .map(|(_, text)| {
+                let start = offset;
+                offset += text.len();
+                tracked
+                    .iter()
+                    .find(|&&(_, index)| {
+                        highlighter.tracked_spans[index]
+                            .iter()
+                            .any(|span| span.contains(&start))
+                    })
+                    .map(|&(class, _)| class)
+            })
+            .collect();
+
+        // Each line gets an anchor (e.g. `#L42`), which the line numbers link to.
+        let wrapped = self.wrapper != HtmlWrapper::None;
+        if wrapped {
+            write!(handle, "<tr id=\"L{}\">", line_number)?;
+        } else {
+            write!(handle, "<span id=\"L{}\">", line_number)?;
+        }
+        if wrapped && self.columns > 1 {
+            let number = format_line_range(
+                frame.config.line_number_formatter.as_ref(),
+                line_number,
+                frame.repeats,
+            );
+            write!(
+                handle,
+                "<td class=\"pp-number\"><a href=\"#L{}\">{}</a></td>",
+                line_number, number
+            )?;
+        }
+        if wrapped {
+            write!(handle, "<td class=\"pp-code\">")?;
+        }
+        // Neighboring regions which look the same share their span.
+        let mut spans: Vec<(String, String)> = Vec::new();
+        for ((style, text), class) in regions.into_iter().zip(classes) {
+            let attribute = match (frame.config.html_style, class) {
+                (HtmlStyle::Classes, Some(class)) => format!(" class=\"{}\"", class),
+                (HtmlStyle::Classes, None) => String::new(),
+                (HtmlStyle::Inline, _) => match html::inline_style(style) {
+                    css if css.is_empty() => css,
+                    css => format!(" style=\"{}\"", css),
+                },
+            };
+            let text = html::escape(text.trim_end_matches(['\r', '\n']));
+            match spans.last_mut() {
+                Some((last, last_text)) if *last == attribute => last_text.push_str(&text),
+                _ => spans.push((attribute, text)),
+            }
+        }
+        for (attribute, text) in spans {
+            if text.is_empty() {
+                continue;
+            }
+            if attribute.is_empty() {
+                write!(handle, "{}", text)?;
+            } else {
+                write!(handle, "<span{}>{}</span>", attribute, text)?;
+            }
+        }
+        if wrapped {
+            writeln!(handle, "</td></tr>")?;
+        } else {
+            writeln!(handle, "</span>")?;
+        }
+
+        Ok(())
+    }
+
+    fn print_snip(&mut self, handle: &mut dyn Write) -> Result<()> {
+        self.print_wide_row(handle, "pp-snip", "8<")
+    }
+
+    fn set_repeats(&mut self, repeats: usize) {
+        self.frame.repeats = repeats;
+    }
+}
+
+/// Prints the input as an SVG image. The rows are collected until the end, when the size of the
+/// image is known.
+pub struct SvgPrinter<'a> {
+    frame: InteractivePrinter<'a>,
+    header: Option<String>,
+    rows: Vec<svg::Row>,
+}
+
+impl<'a> SvgPrinter<'a> {
+    pub fn new(frame: InteractivePrinter<'a>) -> Self {
+        SvgPrinter {
+            frame,
+            header: None,
+            rows: Vec::new(),
+        }
+    }
+}
+
+impl<'a> Printer for SvgPrinter<'a> {
+    fn print_header(
+        &mut self,
+        _handle: &mut dyn Write,
+        file: &InputFile,
+        header_overwrite: Option<String>,
+    ) -> Result<()> {
+        if self.frame.output_components.header() {
+            let (prefix, name, details) = self.frame.header_title(file, header_overwrite);
+            self.header = Some(format!("{}{}{}", prefix, name, details));
+        }
+        Ok(())
+    }
+
+    fn print_footer(&mut self, handle: &mut dyn Write) -> Result<()> {
+        let footer = self.frame.config.footer.as_ref().map(|text| svg::Row {
+            number: None,
+            spans: html::escape(text),
+            width: text.width(),
+        });
+        self.rows.extend(footer);
+
+        svg::write_document(
+            handle,
+            self.frame.theme,
+            self.header.as_deref(),
+            &self.rows,
+            self.frame.output_components.grid(),
+        )
+    }
+
+    fn print_line(
+        &mut self,
+        out_of_range: bool,
+        _handle: &mut dyn Write,
+        line_number: usize,
+        line_buffer: &[u8],
+    ) -> Result<()> {
+        let frame = &mut self.frame;
+        let line = match frame.decode_line(line_number, line_buffer)? {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        // Tabs have no width in SVG text, so they are always expanded.
+        let tab_width = expanded_tab_width(frame.config.tab_width);
+        let line = frame.config.preprocess(line, Some(tab_width));
+
+        let regions = match frame.highlighter {
+            Some(ref mut highlighter) => highlighter.highlight(&line, frame.syntax_set),
+            None => return Ok(()),
+        };
+        if out_of_range {
+            return Ok(());
+        }
+
+        let mut cursor = 0;
+        let mut spans = String::new();
+        for (style, text) in regions {
+            let text = expand_tabs(text.trim_end_matches(['\r', '\n']), tab_width, &mut cursor);
+            if !text.is_empty() {
+                spans.push_str(&svg::tspan(style, &text));
+            }
+        }
+
+        let number = if frame.output_components.numbers() {
+            Some(format_line_number(
+                frame.config.line_number_formatter.as_ref(),
+                line_number,
+            ))
+        } else {
+            None
+        };
+        self.rows.push(svg::Row {
+            number,
+            spans,
+            width: cursor,
+        });
+
+        Ok(())
+    }
+
+    fn print_snip(&mut self, _: &mut dyn Write) -> Result<()> {
+        self.rows.push(svg::Row {
+            number: None,
+            spans: "8&lt;".to_string(),
+            width: 2,
+        });
+        Ok(())
+    }
+
+    /// The repeats are shown as lines of their own.
+    fn print_repeated_line(
+        &mut self,
+        handle: &mut dyn Write,
+        line_number: usize,
+        line_buffer: &[u8],
+        repeats: usize,
+    ) -> Result<()> {
+        for offset in 0..repeats {
+            self.print_line(false, handle, line_number + offset, line_buffer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Prints the input as a `Verbatim` environment of LaTeX.
+pub struct LatexPrinter<'a> {
+    frame: InteractivePrinter<'a>,
+    grid_color: String,
+}
+
+impl<'a> LatexPrinter<'a> {
+    pub fn new(frame: InteractivePrinter<'a>) -> Self {
+        LatexPrinter {
+            grid_color: latex::grid_color(frame.theme),
+            frame,
+        }
+    }
+}
+
+impl<'a> Printer for LatexPrinter<'a> {
+    fn print_header(
+        &mut self,
+        handle: &mut dyn Write,
+        file: &InputFile,
+        header_overwrite: Option<String>,
+    ) -> Result<()> {
+        write!(
+            handle,
+            "{}",
+            latex::begin(self.frame.theme, self.frame.output_components.grid())
+        )?;
+        if self.frame.output_components.header() {
+            let (prefix, name, details) = self.frame.header_title(file, header_overwrite);
+            let name = if name.is_empty() {
+                name
+            } else {
+                format!("\\textbf{{{}}}", latex::escape(&name))
+            };
+            writeln!(
+                handle,
+                "{}{}{}",
+                latex::escape(&prefix),
+                name,
+                latex::escape(&details)
+            )?;
+        }
+        Ok(())
+    }
+
+    fn print_footer(&mut self, handle: &mut dyn Write) -> Result<()> {
+        if let Some(ref text) = self.frame.config.footer {
+            writeln!(handle, "{}", latex::escape(text))?;
+        }
+        write!(handle, "{}", latex::END)?;
+        Ok(())
+    }
+
+    fn print_line(
+        &mut self,
+        out_of_range: bool,
+        handle: &mut dyn Write,
+        line_number: usize,
+        line_buffer: &[u8],
+    ) -> Result<()> {
+        let frame = &mut self.frame;
+        let line = match frame.decode_line(line_number, line_buffer)? {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        // `Verbatim` sets tabs as single spaces, so they are expanded.
+        let tab_width = expanded_tab_width(frame.config.tab_width);
+        let line = frame.config.preprocess(line, Some(tab_width));
+
+        let regions = match frame.highlighter {
+            Some(ref mut highlighter) => highlighter.highlight(&line, frame.syntax_set),
+            None => return Ok(()),
+        };
+        if out_of_range {
+            return Ok(());
+        }
+
+        if frame.output_components.numbers() {
+            let number = format_line_range(
+                frame.config.line_number_formatter.as_ref(),
+                line_number,
+                frame.repeats,
+            );
+            write!(
+                handle,
+                "\\textcolor[HTML]{{{}}}{{{}}} ",
+                self.grid_color,
+                latex::escape(&format!("{:>4}", number))
+            )?;
+        }
+
+        let mut cursor = 0;
+        for (style, text) in regions {
+            let text = expand_tabs(text.trim_end_matches(['\r', '\n']), tab_width, &mut cursor);
+            if !text.is_empty() {
+                write!(handle, "{}", latex::command(style, &text))?;
+            }
+        }
+        writeln!(handle)?;
+
+        Ok(())
+    }
+
+    fn print_snip(&mut self, handle: &mut dyn Write) -> Result<()> {
+        writeln!(handle, "\\textcolor[HTML]{{{}}}{{8<}}", self.grid_color)?;
+        Ok(())
+    }
+
+    fn set_repeats(&mut self, repeats: usize) {
+        self.frame.repeats = repeats;
+    }
+}
+
+/// Prints the input as an RTF document, e.g. to paste it into a word processor. The body is
+/// collected until the end, when the colors for the table at its start are known.
+pub struct RtfPrinter<'a> {
+    frame: InteractivePrinter<'a>,
+    colors: rtf::ColorTable,
+    body: String,
+}
+
+impl<'a> RtfPrinter<'a> {
+    pub fn new(frame: InteractivePrinter<'a>) -> Self {
+        RtfPrinter {
+            frame,
+            colors: rtf::ColorTable::default(),
+            body: String::new(),
+        }
+    }
+
+    /// A paragraph in the color of the grid.
+    fn push_dimmed(&mut self, text: &str) {
+        let grid = self.grid_color();
+        self.body
+            .push_str(&format!("{{\\cf{} {}}}\\par\n", grid, rtf::escape(text)));
+    }
+
+    fn grid_color(&mut self) -> usize {
+        let color = self
+            .frame
+            .theme
+            .settings
+            .gutter_foreground
+            .unwrap_or(rtf::DEFAULT_GRID_COLOR);
+        self.colors.index(color)
+    }
+}
+
+impl<'a> Printer for RtfPrinter<'a> {
+    fn print_header(
+        &mut self,
+        _handle: &mut dyn Write,
+        file: &InputFile,
+        header_overwrite: Option<String>,
+    ) -> Result<()> {
+        if let Some(color) = self.frame.theme.settings.foreground {
+            let index = self.colors.index(color);
+            self.body.push_str(&format!("\\cf{}\n", index));
+        }
+        if self.frame.output_components.header() {
+            let (prefix, name, details) = self.frame.header_title(file, header_overwrite);
+            if prefix.is_empty() && name.is_empty() && details.is_empty() {
+                return Ok(());
+            }
+            self.body.push_str(&format!(
+                "{}{{\\b {}}}{}\\par\n",
+                rtf::escape(&prefix),
+                rtf::escape(&name),
+                rtf::escape(&details)
+            ));
+        }
+        Ok(())
+    }
+
+    fn print_footer(&mut self, handle: &mut dyn Write) -> Result<()> {
+        if let Some(text) = self.frame.config.footer.clone() {
+            self.push_dimmed(&text);
+        }
+        write!(handle, "{}", rtf::document(&self.colors, &self.body))?;
+        Ok(())
+    }
+
+    fn print_line(
+        &mut self,
+        out_of_range: bool,
+        _handle: &mut dyn Write,
+        line_number: usize,
+        line_buffer: &[u8],
+    ) -> Result<()> {
+        let grid = self.grid_color();
+        let frame = &mut self.frame;
+        let line = match frame.decode_line(line_number, line_buffer)? {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        // Tabs stay tabs, their width is up to the tab stops of the document.
+        let line = frame.config.preprocess(line, Some(1));
+
+        let regions = match frame.highlighter {
+            Some(ref mut highlighter) => highlighter.highlight(&line, frame.syntax_set),
+            None => return Ok(()),
+        };
+        if out_of_range {
+            return Ok(());
+        }
+
+        if frame.output_components.numbers() {
+            let number = format_line_range(
+                frame.config.line_number_formatter.as_ref(),
+                line_number,
+                frame.repeats,
+            );
+            self.body.push_str(&format!(
+                "{{\\cf{} {}}} ",
+                grid,
+                rtf::escape(&format!("{:>4}", number))
+            ));
+        }
+        for (style, text) in regions {
+            let text = text.trim_end_matches(['\r', '\n']);
+            if !text.is_empty() {
+                self.body
+                    .push_str(&rtf::group(&mut self.colors, style, text));
+            }
+        }
+        self.body.push_str("\\par\n");
+
+        Ok(())
+    }
+
+    fn print_snip(&mut self, _: &mut dyn Write) -> Result<()> {
+        self.push_dimmed("8<");
+        Ok(())
+    }
+
+    fn set_repeats(&mut self, repeats: usize) {
+        self.frame.repeats = repeats;
+    }
+}
+
+/// Prints the input with BBCode tags, e.g. for forums which don't allow HTML.
+pub struct BbCodePrinter<'a> {
+    frame: InteractivePrinter<'a>,
+    grid_color: String,
+}
+
+impl<'a> BbCodePrinter<'a> {
+    pub fn new(frame: InteractivePrinter<'a>) -> Self {
+        let grid_color = frame
+            .theme
+            .settings
+            .gutter_foreground
+            .and_then(html::css_color)
+            .unwrap_or_else(|| "#444444".to_string());
+        BbCodePrinter { frame, grid_color }
+    }
+
+    fn print_dimmed(&self, handle: &mut dyn Write, text: &str) -> Result<()> {
+        writeln!(
+            handle,
+            "[color={}]{}[/color]",
+            self.grid_color,
+            bbcode::escape(text)
+        )?;
+        Ok(())
+    }
+}
+
+impl<'a> Printer for BbCodePrinter<'a> {
+    fn print_header(
+        &mut self,
+        handle: &mut dyn Write,
+        file: &InputFile,
+        header_overwrite: Option<String>,
+    ) -> Result<()> {
+        if !self.frame.output_components.header() {
+            return Ok(());
+        }
+        let (prefix, name, details) = self.frame.header_title(file, header_overwrite);
+        if !name.is_empty() {
+            writeln!(
+                handle,
+                "{}[b]{}[/b]{}",
+                bbcode::escape(&prefix),
+                bbcode::escape(&name),
+                bbcode::escape(&details)
+            )?;
+        }
+        Ok(())
+    }
+
+    fn print_footer(&mut self, handle: &mut dyn Write) -> Result<()> {
+        if let Some(ref text) = self.frame.config.footer {
+            self.print_dimmed(handle, text)?;
+        }
+        Ok(())
+    }
+
+    fn print_line(
+        &mut self,
+        out_of_range: bool,
+        handle: &mut dyn Write,
+        line_number: usize,
+        line_buffer: &[u8],
+    ) -> Result<()> {
+        let frame = &mut self.frame;
+        let line = match frame.decode_line(line_number, line_buffer)? {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        // Forums don't agree on the width of tabs, so they are expanded.
+        let tab_width = expanded_tab_width(frame.config.tab_width);
+        let line = frame.config.preprocess(line, Some(tab_width));
+
+        let regions = match frame.highlighter {
+            Some(ref mut highlighter) => highlighter.highlight(&line, frame.syntax_set),
+            None => return Ok(()),
+        };
+        if out_of_range {
+            return Ok(());
+        }
+
+        if frame.output_components.numbers() {
+            let number = format_line_range(
+                frame.config.line_number_formatter.as_ref(),
+                line_number,
+                frame.repeats,
+            );
+            write!(
+                handle,
+                "[color={}]{:>4}[/color] ",
+                self.grid_color,
+                bbcode::escape(&number)
+            )?;
+        }
+
+        // Neighboring regions which look the same share their tags.
+        let mut cursor = 0;
+        let mut merged: Vec<(highlighting::Style, String)> = Vec::new();
+        for (style, text) in regions {
+            let text = expand_tabs(text.trim_end_matches(['\r', '\n']), tab_width, &mut cursor);
+            match merged.last_mut() {
+                Some((last, last_text)) if *last == style => last_text.push_str(&text),
+                _ => merged.push((style, text)),
+            }
+        }
+        for (style, text) in merged {
+            write!(handle, "{}", bbcode::tag(style, &text))?;
+        }
+        writeln!(handle)?;
+
+        Ok(())
+    }
+
+    fn print_snip(&mut self, handle: &mut dyn Write) -> Result<()> {
+        self.print_dimmed(handle, "8<")
+    }
+
+    fn set_repeats(&mut self, repeats: usize) {
+        self.frame.repeats = repeats;
+    }
+}
+
+/// Prints the highlighted regions of each line as a JSON object of its own (JSON Lines), e.g.
+/// for other tools or golden-file tests. There's no header, footer or snip.
+pub struct JsonPrinter<'a> {
+    frame: InteractivePrinter<'a>,
+}
+
+impl<'a> JsonPrinter<'a> {
+    pub fn new(frame: InteractivePrinter<'a>) -> Self {
+        JsonPrinter { frame }
+    }
+}
+
+impl<'a> Printer for JsonPrinter<'a> {
+    fn print_header(
+        &mut self,
+        _handle: &mut dyn Write,
+        _file: &InputFile,
+        _header_overwrite: Option<String>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn print_footer(&mut self, _handle: &mut dyn Write) -> Result<()> {
+        Ok(())
+    }
+
+    fn print_line(
+        &mut self,
+        out_of_range: bool,
+        handle: &mut dyn Write,
+        line_number: usize,
+        line_buffer: &[u8],
+    ) -> Result<()> {
+        let frame = &mut self.frame;
+        let line = match frame.decode_line(line_number, line_buffer)? {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        let line = frame.config.preprocess(line, None);
+
+        let regions = match frame.highlighter {
+            Some(ref mut highlighter) => highlighter.highlight(&line, frame.syntax_set),
+            None => return Ok(()),
+        };
+        if out_of_range {
+            return Ok(());
+        }
+
+        let regions: Vec<String> = regions
+            .iter()
+            .map(|&(style, text)| (style, text.trim_end_matches(['\r', '\n'])))
+            .filter(|(_, text)| !text.is_empty())
+            .map(|(style, text)| json::region(style, text))
+            .collect();
+        writeln!(
+            handle,
+            "{}",
+            json::line(line_number, frame.repeats, &regions)
+        )?;
+        Ok(())
+    }
+
+    fn set_repeats(&mut self, repeats: usize) {
+        self.frame.repeats = repeats;
+    }
+}
+
+const DEFAULT_GUTTER_COLOR: u8 = 238;
+
+#[derive(Default)]
+pub struct Colors {
+    pub grid: Style,
+    pub filename: Style,
+    pub line_number: Style,
+}
+
+impl Colors {
+    pub fn plain() -> Self {
+        Colors::default()
+    }
+
+    pub fn colored(theme: &Theme, true_color: bool) -> Self {
+        let gutter_color = theme
+            .settings
+            .gutter_foreground
+            .map(|c| to_ansi_color(c, true_color))
+            .unwrap_or(Fixed(DEFAULT_GUTTER_COLOR));
+
+        Colors {
+            grid: gutter_color.normal(),
+            filename: Style::new().bold(),
+            line_number: gutter_color.normal(),
+        }
+    }
+}
+
+#[test]
+fn take_wide_columns() {
+    let mut chars = "ab日本".chars().peekable();
+    assert_eq!(("ab".to_string(), 2), take_columns(&mut chars, 3, false));
+    assert_eq!(("日".to_string(), 2), take_columns(&mut chars, 3, true));
+    assert_eq!(("本".to_string(), 2), take_columns(&mut chars, 1, true));
+    assert_eq!(None, chars.next());
 }
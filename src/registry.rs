@@ -0,0 +1,61 @@
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+use crate::builder::PrettyPrint;
+use crate::errors::*;
+
+/// Named option bundles ("looks"), so that an application can configure in one place how it
+/// prints code for reviews, logs or documentation, e.g. `registry.define("log", options)` and
+/// later `registry.get("log")?.file("server.log")`.
+#[derive(Debug, Default)]
+pub struct Registry {
+    bundles: RwLock<BTreeMap<String, PrettyPrint>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry::default()
+    }
+
+    /// Defines the bundle `name`, replacing an earlier definition.
+    pub fn define<S: Into<String>>(&self, name: S, options: PrettyPrint) {
+        self.bundles
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(name.into(), options);
+    }
+
+    /// A printer with the options of the bundle `name`.
+    pub fn get(&self, name: &str) -> Result<PrettyPrint> {
+        self.bundles
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("Unknown option bundle '{}'", name).into())
+    }
+
+    /// The names of all bundles, sorted.
+    pub fn names(&self) -> Vec<String> {
+        self.bundles
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .keys()
+            .cloned()
+            .collect()
+    }
+}
+
+#[test]
+fn bundles() {
+    use crate::builder::PrettyPrinter;
+
+    let registry = Registry::new();
+    registry.define("review", PrettyPrinter::default().build());
+    registry.define("log", PrettyPrinter::default().tab_width(2usize).build());
+    registry.define("log", PrettyPrinter::default().tab_width(8usize).build());
+
+    assert_eq!(vec!["log", "review"], registry.names());
+    assert_eq!(8, registry.get("log").unwrap().tab_width);
+    assert!(registry.get("doc").is_err());
+}
@@ -0,0 +1,66 @@
+use crate::builder::PrettyPrint;
+use crate::errors::*;
+use crate::inputfile::InputFile;
+
+/// The output of `render`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedDocument {
+    /// The rendered input, with ANSI escape sequences or as HTML, depending on the output format
+    pub text: String,
+}
+
+/// Renders `input` with the options of `settings`, without access to files, the terminal or the
+/// environment, e.g. in tests, GUIs or WebAssembly: colors are only used if `.colored_output()`
+/// is set, the width is `.term_width()` or what the `.width_provider()` tells, the header shows
+/// `name` but no file metadata and only the integrated syntaxes and themes are available (plus
+/// themes given as strings or builders).
+/// The language comes from `.language()` or the first line of the input.
+pub fn render(
+    input: &[u8],
+    name: Option<&str>,
+    settings: &PrettyPrint,
+) -> Result<RenderedDocument> {
+    let settings = PrettyPrint {
+        detached: true,
+        ..settings.clone()
+    };
+
+    let mut output = Vec::new();
+    settings.render(
+        &InputFile::Bytes(input.to_vec()),
+        name.map(str::to_string),
+        &mut output,
+    )?;
+
+    Ok(RenderedDocument {
+        text: String::from_utf8_lossy(&output).into_owned(),
+    })
+}
+
+#[test]
+fn detached() {
+    use crate::builder::PrettyPrinter;
+
+    let settings = PrettyPrinter::default()
+        .colored_output(false)
+        .grid(false)
+        .line_numbers(false)
+        .header_size(true)
+        .build();
+    let document = render(b"fn main() {}\n", Some("main.rs"), &settings).unwrap();
+    assert_eq!("main.rs\nfn main() {}\n", document.text);
+
+    let settings = PrettyPrinter::default()
+        .grid(false)
+        .header(false)
+        .line_numbers(false)
+        .language("rust")
+        .build();
+    let document = render(b"fn main() {}\n", None, &settings).unwrap();
+    assert!(document.text.starts_with("\x1b["));
+
+    let settings = PrettyPrinter::default()
+        .add_syntax_from_folder("fixtures/syntaxes")
+        .build();
+    assert!(render(b"", None, &settings).is_err());
+}
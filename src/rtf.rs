@@ -0,0 +1,131 @@
+use std::fmt::Write;
+
+use syntect::highlighting::{Color, FontStyle, Style};
+
+/// Grid lines and line numbers, like the gutter color of themes without one.
+pub const DEFAULT_GRID_COLOR: Color = Color {
+    r: 0x44,
+    g: 0x44,
+    b: 0x44,
+    a: 0xff,
+};
+
+/// Escapes the control characters of RTF. Other characters than ASCII become `\u` escapes, with
+/// a `?` for readers which don't know them.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\t' => escaped.push_str("\\tab "),
+            ' '..='~' => escaped.push(c),
+            c => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    write!(escaped, "\\u{}?", *unit as i16).ok();
+                }
+            }
+        }
+    }
+    escaped
+}
+
+/// The colors of a document, which are referred to by their index in the table.
+#[derive(Debug, Default)]
+pub struct ColorTable {
+    colors: Vec<Color>,
+}
+
+impl ColorTable {
+    /// The index of `color` for `\cf`, which is added to the table if it's new. Colors with an
+    /// alpha of zero encode terminal colors (e.g. in the `ansi` theme) and are left to the
+    /// default color, 0.
+    pub fn index(&mut self, color: Color) -> usize {
+        if color.a == 0 {
+            return 0;
+        }
+        let color = Color { a: 0xff, ..color };
+        match self.colors.iter().position(|&known| known == color) {
+            Some(position) => position + 1,
+            None => {
+                self.colors.push(color);
+                self.colors.len()
+            }
+        }
+    }
+
+    /// The `\colortbl` group, starting with the default color.
+    pub fn to_rtf(&self) -> String {
+        let mut table = String::from("{\\colortbl;");
+        for color in &self.colors {
+            write!(
+                table,
+                "\\red{}\\green{}\\blue{};",
+                color.r, color.g, color.b
+            )
+            .ok();
+        }
+        table.push('}');
+        table
+    }
+}
+
+/// A highlighted region, e.g. `{\cf1\b fn}`.
+pub fn group(colors: &mut ColorTable, style: Style, text: &str) -> String {
+    let mut group = format!("{{\\cf{}", colors.index(style.foreground));
+    if style.font_style.contains(FontStyle::BOLD) {
+        group.push_str("\\b");
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        group.push_str("\\i");
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        group.push_str("\\ul");
+    }
+    write!(group, " {}}}", escape(text)).ok();
+    group
+}
+
+/// The document around `body`, in a monospace font.
+pub fn document(colors: &ColorTable, body: &str) -> String {
+    format!(
+        "{{\\rtf1\\ansi\\deff0{{\\fonttbl{{\\f0\\fmodern Courier New;}}}}{}\n\\f0\\fs20\n{}}}\n",
+        colors.to_rtf(),
+        body
+    )
+}
+
+#[test]
+fn escapes() {
+    assert_eq!(
+        "\\{a\\}\\\\\\tab b \\u228?\\u-10179?\\u-8704?",
+        escape("{a}\\\tb ä😀")
+    );
+}
+
+#[test]
+fn color_tables() {
+    let mut colors = ColorTable::default();
+    let red = Color {
+        r: 0xf9,
+        g: 0x26,
+        b: 0x72,
+        a: 0xff,
+    };
+    let style = Style {
+        foreground: red,
+        font_style: FontStyle::BOLD,
+        ..Style::default()
+    };
+    assert_eq!("{\\cf1\\b fn}", group(&mut colors, style, "fn"));
+    assert_eq!(1, colors.index(red));
+    assert_eq!(2, colors.index(DEFAULT_GRID_COLOR));
+    assert_eq!(0, colors.index(Color { a: 0, ..red }));
+    assert_eq!(
+        "{\\colortbl;\\red249\\green38\\blue114;\\red68\\green68\\blue68;}",
+        colors.to_rtf()
+    );
+}
@@ -0,0 +1,304 @@
+use std::iter;
+
+use ansi_term::Colour::{Green, Red, Yellow};
+use ansi_term::Style;
+
+use crate::printer::Colors;
+use crate::style::GridCharset;
+
+/// A row of the side-by-side view, with the indices of the lines shown on the left and on the
+/// right.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Row {
+    Same(usize, usize),
+    Changed(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+impl Row {
+    fn left(self) -> Option<usize> {
+        match self {
+            Row::Same(left, _) | Row::Changed(left, _) | Row::Removed(left) => Some(left),
+            Row::Added(_) => None,
+        }
+    }
+
+    fn right(self) -> Option<usize> {
+        match self {
+            Row::Same(_, right) | Row::Changed(_, right) | Row::Added(right) => Some(right),
+            Row::Removed(_) => None,
+        }
+    }
+
+    /// The markers of the left and of the right line.
+    fn markers(self) -> (char, char) {
+        match self {
+            Row::Same(..) => (' ', ' '),
+            Row::Changed(..) => ('~', '~'),
+            Row::Removed(_) => ('-', ' '),
+            Row::Added(_) => (' ', '+'),
+        }
+    }
+}
+
+/// Pairs the lines of `a` and `b` along their longest common subsequence. The lines removed and
+/// added between two common lines share their rows, as changed lines.
+pub fn pair_lines<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Row> {
+    // lengths[i][j] is the length of the longest common subsequence of a[i..] and b[j..].
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut rows = Vec::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            pair_changes(&mut rows, &mut removed, &mut added);
+            rows.push(Row::Same(i, j));
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lengths[i + 1][j] >= lengths[i][j + 1]) {
+            removed.push(i);
+            i += 1;
+        } else {
+            added.push(j);
+            j += 1;
+        }
+    }
+    pair_changes(&mut rows, &mut removed, &mut added);
+    rows
+}
+
+fn pair_changes(rows: &mut Vec<Row>, removed: &mut Vec<usize>, added: &mut Vec<usize>) {
+    for index in 0..removed.len().max(added.len()) {
+        rows.push(match (removed.get(index), added.get(index)) {
+            (Some(&left), Some(&right)) => Row::Changed(left, right),
+            (Some(&left), None) => Row::Removed(left),
+            (None, Some(&right)) => Row::Added(right),
+            (None, None) => unreachable!(),
+        });
+    }
+    removed.clear();
+    added.clear();
+}
+
+/// The layout of the two columns, each with a marker, the line numbers and the text of a file:
+/// `- 12 │ text │ + 12 │ text`.
+pub struct Columns {
+    /// Digits of the line numbers, zero without line numbers
+    pub number_width: usize,
+    /// Width of the text of a file
+    pub text_width: usize,
+    pub grid_chars: GridCharset,
+    /// Whether the columns are separated by lines
+    pub grid: bool,
+    pub colors: Colors,
+    pub colored_output: bool,
+}
+
+impl Columns {
+    /// Splits `width` into the columns of two files with at most `lines` lines.
+    pub fn new(width: usize, lines: usize, line_numbers: bool) -> Self {
+        let number_width = if line_numbers {
+            lines.to_string().len().max(4)
+        } else {
+            0
+        };
+        let column = width.saturating_sub(3) / 2;
+        Columns {
+            number_width,
+            text_width: column
+                .saturating_sub(Columns::gutter_width(number_width))
+                .max(1),
+            grid_chars: GridCharset::UNICODE,
+            grid: true,
+            colors: Colors::default(),
+            colored_output: false,
+        }
+    }
+
+    /// The marker, the line number and the separator in front of the text.
+    fn gutter_width(number_width: usize) -> usize {
+        if number_width == 0 {
+            2
+        } else {
+            number_width + 5
+        }
+    }
+
+    fn vertical(&self) -> char {
+        if self.grid {
+            self.grid_chars.vertical
+        } else {
+            ' '
+        }
+    }
+
+    fn paint(&self, style: Style, text: &str) -> String {
+        if self.colored_output {
+            style.paint(text).to_string()
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// The horizontal line of the grid, crossing the vertical lines with `cross`.
+    pub fn rule(&self, cross: char) -> String {
+        let gutter = Columns::gutter_width(self.number_width);
+        let mut column = String::new();
+        if self.number_width > 0 {
+            column.extend(iter::repeat_n(self.grid_chars.horizontal, gutter - 2));
+            column.push(cross);
+            column.push(self.grid_chars.horizontal);
+        } else {
+            column.extend(iter::repeat_n(self.grid_chars.horizontal, gutter));
+        }
+        column.extend(iter::repeat_n(self.grid_chars.horizontal, self.text_width));
+
+        let mut rule = column.clone();
+        rule.push(self.grid_chars.horizontal);
+        rule.push(cross);
+        rule.push(self.grid_chars.horizontal);
+        rule.push_str(&column);
+        self.paint(self.colors.grid, &rule) + "\n"
+    }
+
+    /// The names of the files, above their text.
+    pub fn header(&self, left: &str, right: &str) -> String {
+        let cell = |name: &str| {
+            let gutter = " ".repeat(Columns::gutter_width(self.number_width));
+            let name = truncate(name, self.text_width);
+            let padding = " ".repeat(self.text_width.saturating_sub(name.chars().count()));
+            format!(
+                "{}{}{}",
+                gutter,
+                self.paint(self.colors.filename, &name),
+                padding
+            )
+        };
+        let header = format!("{}{}{}", cell(left), self.separator(), cell(right));
+        header.trim_end_matches(' ').to_string() + "\n"
+    }
+
+    fn separator(&self) -> String {
+        format!(
+            " {} ",
+            self.paint(self.colors.grid, &self.vertical().to_string())
+        )
+    }
+
+    fn cell(&self, marker: char, line_number: Option<usize>, text: Option<&str>) -> String {
+        let marker_style = match marker {
+            '-' => Red.normal(),
+            '+' => Green.normal(),
+            '~' => Yellow.normal(),
+            _ => Style::default(),
+        };
+        let mut cell = self.paint(marker_style, &marker.to_string());
+        cell.push(' ');
+        if self.number_width > 0 {
+            let number = line_number.map_or_else(String::new, |n| n.to_string());
+            cell.push_str(&self.paint(
+                self.colors.line_number,
+                &format!("{:>width$}", number, width = self.number_width),
+            ));
+            cell.push(' ');
+            cell.push_str(&self.paint(self.colors.grid, &self.vertical().to_string()));
+            cell.push(' ');
+        }
+        let text = text.unwrap_or("");
+        cell.push_str(text);
+        if self.colored_output {
+            cell.push_str("\x1b[0m");
+        }
+        let width = console::measure_text_width(text);
+        cell.extend(iter::repeat_n(' ', self.text_width.saturating_sub(width)));
+        cell
+    }
+
+    /// A row with the rendered lines of the files, `left` and `right`, which are at most
+    /// `text_width` wide. Line numbers start at `first_line_number`.
+    pub fn row(
+        &self,
+        row: Row,
+        left: &[String],
+        right: &[String],
+        first_line_number: usize,
+    ) -> String {
+        let (left_marker, right_marker) = row.markers();
+        let text = |lines: &'_ [String], index: Option<usize>| -> Option<String> {
+            index.and_then(|index| lines.get(index).cloned())
+        };
+        let line = format!(
+            "{}{}{}",
+            self.cell(
+                left_marker,
+                row.left().map(|index| index + first_line_number),
+                text(left, row.left()).as_deref()
+            ),
+            self.separator(),
+            self.cell(
+                right_marker,
+                row.right().map(|index| index + first_line_number),
+                text(right, row.right()).as_deref()
+            ),
+        );
+        line.trim_end_matches(' ').to_string() + "\n"
+    }
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+#[test]
+fn pairs_lines() {
+    let a = ["a", "b", "c", "d"];
+    let b = ["a", "x", "c", "d", "e"];
+    assert_eq!(
+        vec![
+            Row::Same(0, 0),
+            Row::Changed(1, 1),
+            Row::Same(2, 2),
+            Row::Same(3, 3),
+            Row::Added(4),
+        ],
+        pair_lines(&a, &b)
+    );
+
+    assert_eq!(
+        vec![Row::Removed(0), Row::Removed(1)],
+        pair_lines(&["a", "b"], &[])
+    );
+}
+
+#[test]
+fn lays_out_rows() {
+    let mut columns = Columns::new(40, 12, true);
+    columns.grid_chars = GridCharset::ASCII;
+    assert_eq!(9, columns.text_width);
+
+    let left = vec!["let a;".to_string()];
+    let right = vec!["let b;".to_string(), "let c;".to_string()];
+    assert_eq!(
+        "~    1 | let a;    | ~    1 | let b;\n",
+        columns.row(Row::Changed(0, 0), &left, &right, 1)
+    );
+    assert_eq!(
+        "       |           | +    2 | let c;\n",
+        columns.row(Row::Added(1), &left, &right, 1)
+    );
+    assert_eq!(
+        "-------+-----------+--------+----------\n",
+        columns.rule('+')
+    );
+}
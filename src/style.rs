@@ -6,24 +6,107 @@ use crate::errors::*;
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
 pub enum OutputComponent {
     Auto,
+    /// Markers for the lines changed since the last commit, from `git diff`
     Changes,
     Grid,
     Header,
+    /// File size in the header
+    Size,
+    /// Modification time in the header
+    Modified,
+    /// Unix permissions in the header
+    Permissions,
     Numbers,
     Full,
     Plain,
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash, Default)]
 pub enum OutputWrap {
     Character,
+    #[default]
     None,
+    /// Cut lines at the terminal width and mark them with `…`
+    Truncate,
 }
 
-impl Default for OutputWrap {
-    fn default() -> Self {
-        OutputWrap::None
-    }
+/// Whether the information carried by colors is also given by other means, see
+/// `.accessibility()`.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash, Default)]
+pub enum Accessibility {
+    #[default]
+    Colors,
+    /// Markers for screen readers and monochrome terminals, in addition to the colors: keywords
+    /// are underlined and trailing whitespace colored by `TrailingWhitespace::Background` gets
+    /// the markers of `TrailingWhitespace::Markers`
+    Markers,
+}
+
+/// How to mark whitespace at the end of lines.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash, Default)]
+pub enum TrailingWhitespace {
+    #[default]
+    None,
+    /// Replace spaces and tabs by `·` and `→`
+    Markers,
+    /// Color the background red
+    Background,
+}
+
+/// Characters to draw the grid with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridCharset {
+    pub vertical: char,
+    pub horizontal: char,
+    pub top_cross: char,
+    pub mid_cross: char,
+    pub bottom_cross: char,
+    /// Left of a title embedded in a horizontal line
+    pub title_left: char,
+    /// Right of a title embedded in a horizontal line
+    pub title_right: char,
+}
+
+impl GridCharset {
+    pub const UNICODE: GridCharset = GridCharset {
+        vertical: '│',
+        horizontal: '─',
+        top_cross: '┬',
+        mid_cross: '┼',
+        bottom_cross: '┴',
+        title_left: '┤',
+        title_right: '├',
+    };
+
+    pub const ASCII: GridCharset = GridCharset {
+        vertical: '|',
+        horizontal: '-',
+        top_cross: '+',
+        mid_cross: '+',
+        bottom_cross: '+',
+        title_left: '|',
+        title_right: '|',
+    };
+
+    pub const DOUBLE: GridCharset = GridCharset {
+        vertical: '║',
+        horizontal: '═',
+        top_cross: '╦',
+        mid_cross: '╬',
+        bottom_cross: '╩',
+        title_left: '╣',
+        title_right: '╠',
+    };
+
+    pub const DOTTED: GridCharset = GridCharset {
+        vertical: '┊',
+        horizontal: '┄',
+        top_cross: '┬',
+        mid_cross: '┼',
+        bottom_cross: '┴',
+        title_left: '┤',
+        title_right: '├',
+    };
 }
 
 impl FromStr for OutputComponent {
@@ -35,6 +118,9 @@ impl FromStr for OutputComponent {
             "changes" => Ok(OutputComponent::Changes),
             "grid" => Ok(OutputComponent::Grid),
             "header" => Ok(OutputComponent::Header),
+            "size" => Ok(OutputComponent::Size),
+            "modified" => Ok(OutputComponent::Modified),
+            "permissions" => Ok(OutputComponent::Permissions),
             "numbers" => Ok(OutputComponent::Numbers),
             "full" => Ok(OutputComponent::Full),
             "plain" => Ok(OutputComponent::Plain),
@@ -63,6 +149,22 @@ impl OutputComponents {
         self.0.contains(&OutputComponent::Header)
     }
 
+    pub fn size(&self) -> bool {
+        self.0.contains(&OutputComponent::Size)
+    }
+
+    pub fn modified(&self) -> bool {
+        self.0.contains(&OutputComponent::Modified)
+    }
+
+    pub fn permissions(&self) -> bool {
+        self.0.contains(&OutputComponent::Permissions)
+    }
+
+    pub fn changes(&self) -> bool {
+        self.0.contains(&OutputComponent::Changes)
+    }
+
     pub fn numbers(&self) -> bool {
         self.0.contains(&OutputComponent::Numbers)
     }
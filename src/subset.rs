@@ -0,0 +1,294 @@
+use std::collections::{BTreeSet, HashMap};
+use std::mem;
+
+use syntect::dumps::{dump_binary, from_binary};
+use syntect::parsing::syntax_definition::{
+    Context, ContextId, ContextReference, MatchOperation, Pattern, SyntaxDefinition,
+};
+use syntect::parsing::{Scope, SyntaxSet, SyntaxSetBuilder};
+
+/// The fields of a `SyntaxReference`, in the order in which they are serialized. Its contexts
+/// (by name) can't be accessed otherwise.
+type RawSyntax = (
+    String,
+    Vec<String>,
+    Scope,
+    Option<String>,
+    bool,
+    HashMap<String, String>,
+    HashMap<String, ContextId>,
+);
+
+/// The fields of a `SyntaxSet`: the syntaxes, all their contexts and the paths of the syntaxes
+/// loaded from files.
+type RawSyntaxSet = (Vec<RawSyntax>, Vec<Context>, Vec<(String, usize)>);
+
+/// A syntax set with only the syntaxes named `names`, the syntaxes they embed (e.g. the
+/// JavaScript of HTML) and plain text.
+pub fn syntax_subset(syntax_set: &SyntaxSet, names: &[String]) -> SyntaxSet {
+    let (syntaxes, mut contexts, _): RawSyntaxSet = from_binary(&dump_binary(syntax_set));
+
+    let owners = context_owners(&syntaxes, contexts.len());
+
+    // Syntax sets always need a plain text syntax.
+    let plain_text = Scope::new("text.plain").ok();
+    let mut kept: BTreeSet<usize> = syntaxes
+        .iter()
+        .enumerate()
+        .filter(|(_, syntax)| names.contains(&syntax.0) || Some(syntax.2) == plain_text)
+        .map(|(index, _)| index)
+        .collect();
+
+    // Add the syntaxes which the kept ones refer to, until there are no new ones.
+    let mut pending: Vec<usize> = kept.iter().cloned().collect();
+    while let Some(index) = pending.pop() {
+        for id in syntaxes[index].6.values() {
+            visit_ids(&mut contexts[context_index(*id)], |referenced| {
+                let owner = owners[context_index(*referenced)];
+                if kept.insert(owner) {
+                    pending.push(owner);
+                }
+            });
+        }
+    }
+
+    // The contexts of the kept syntaxes get new indices, in their old order.
+    let mut renumbered = HashMap::new();
+    for (index, owner) in owners.iter().enumerate() {
+        if kept.contains(owner) {
+            renumbered.insert(index, ContextId::new(renumbered.len()));
+        }
+    }
+    let mut subset_contexts = Vec::with_capacity(renumbered.len());
+    for (index, context) in contexts.iter_mut().enumerate() {
+        if kept.contains(&owners[index]) {
+            let mut context = mem::replace(context, Context::new(false));
+            visit_ids(&mut context, |id| *id = renumbered[&context_index(*id)]);
+            subset_contexts.push(context);
+        }
+    }
+    let subset_syntaxes: Vec<RawSyntax> = syntaxes
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| kept.contains(index))
+        .map(|(_, mut syntax)| {
+            for id in syntax.6.values_mut() {
+                *id = renumbered[&context_index(*id)];
+            }
+            syntax
+        })
+        .collect();
+
+    let subset: RawSyntaxSet = (subset_syntaxes, subset_contexts, Vec::new());
+    from_binary(&dump_binary(&subset))
+}
+
+/// A syntax as listed in a `SplitSyntaxSet`: its name, file extensions, scope, first line
+/// pattern and whether it's hidden, the index of its first context, its number of contexts and
+/// the syntaxes it refers to.
+pub type SplitSyntax = (
+    String,
+    Vec<String>,
+    Scope,
+    Option<String>,
+    bool,
+    usize,
+    usize,
+    Vec<usize>,
+);
+
+/// Dumps each syntax of `syntax_set` on its own, for a `SplitSyntaxSet`: the list of the
+/// syntaxes and the dumped `SyntaxDefinition` of each, which still refers to the contexts of
+/// the whole set by their indices.
+#[cfg(test)]
+pub fn split_syntax_set(syntax_set: &SyntaxSet) -> (Vec<SplitSyntax>, Vec<Vec<u8>>) {
+    let (syntaxes, contexts, _): RawSyntaxSet = from_binary(&dump_binary(syntax_set));
+    let owners = context_owners(&syntaxes, contexts.len());
+    let mut contexts: Vec<Option<Context>> = contexts.into_iter().map(Some).collect();
+
+    syntaxes
+        .into_iter()
+        .enumerate()
+        .map(
+            |(index, (name, extensions, scope, first_line, hidden, variables, ids))| {
+                let mut ids: Vec<(String, usize)> = ids
+                    .into_iter()
+                    .map(|(name, id)| (name, context_index(id)))
+                    .collect();
+                ids.sort_unstable();
+                // `SyntaxSetBuilder::build()` numbers the contexts of a syntax in the order of
+                // their names, which `SplitSyntaxSet::subset()` relies on.
+                let first = ids.first().map_or(0, |(_, id)| *id);
+                assert!(ids.iter().enumerate().all(|(k, (_, id))| *id == first + k));
+
+                let mut references = BTreeSet::new();
+                let mut definition_contexts = HashMap::new();
+                for (context_name, id) in &ids {
+                    let mut context = contexts[*id].take().unwrap_or_else(|| Context::new(false));
+                    visit_ids(&mut context, |referenced| {
+                        references.insert(owners[context_index(*referenced)]);
+                    });
+                    definition_contexts.insert(context_name.clone(), context);
+                }
+                references.remove(&index);
+
+                let definition = SyntaxDefinition {
+                    name: name.clone(),
+                    file_extensions: extensions.clone(),
+                    scope,
+                    first_line_match: first_line.clone(),
+                    hidden,
+                    variables,
+                    contexts: definition_contexts,
+                };
+                let syntax = (
+                    name,
+                    extensions,
+                    scope,
+                    first_line,
+                    hidden,
+                    first,
+                    ids.len(),
+                    references.into_iter().collect(),
+                );
+                (syntax, dump_binary(&definition))
+            },
+        )
+        .unzip()
+}
+
+/// A syntax set, dumped syntax by syntax with `split_syntax_set()`, of which only the syntaxes
+/// that are used get loaded.
+pub struct SplitSyntaxSet<'a> {
+    syntaxes: Vec<SplitSyntax>,
+    definitions: Vec<&'a [u8]>,
+}
+
+impl<'a> SplitSyntaxSet<'a> {
+    pub fn new(syntaxes: Vec<SplitSyntax>, definitions: Vec<&'a [u8]>) -> Self {
+        SplitSyntaxSet {
+            syntaxes,
+            definitions,
+        }
+    }
+
+    /// A syntax set with the syntaxes named `names`, the syntaxes they embed and plain text,
+    /// like `syntax_subset()`. The other syntaxes aren't loaded at all.
+    pub fn subset(&self, names: &[String]) -> SyntaxSet {
+        // Syntax sets always need a plain text syntax.
+        let plain_text = Scope::new("text.plain").ok();
+        let mut kept: BTreeSet<usize> = self
+            .syntaxes
+            .iter()
+            .enumerate()
+            .filter(|(_, syntax)| names.contains(&syntax.0) || Some(syntax.2) == plain_text)
+            .map(|(index, _)| index)
+            .collect();
+        let mut pending: Vec<usize> = kept.iter().cloned().collect();
+        while let Some(index) = pending.pop() {
+            for referenced in &self.syntaxes[index].7 {
+                if kept.insert(*referenced) {
+                    pending.push(*referenced);
+                }
+            }
+        }
+
+        // The contexts of the kept syntaxes get the indices which `build()` gives them, so
+        // that it only has to link the ones which refer to each other by name.
+        let mut firsts = HashMap::new();
+        let mut next = 0;
+        for index in &kept {
+            firsts.insert(*index, next);
+            next += self.syntaxes[*index].6;
+        }
+        let renumber = |id: &mut ContextId| {
+            let old = context_index(*id);
+            let owner = self.syntaxes.partition_point(|syntax| syntax.5 <= old) - 1;
+            *id = ContextId::new(firsts[&owner] + old - self.syntaxes[owner].5);
+        };
+
+        let mut builder = SyntaxSetBuilder::new();
+        for index in &kept {
+            let mut definition: SyntaxDefinition = from_binary(self.definitions[*index]);
+            for context in definition.contexts.values_mut() {
+                visit_ids(context, &renumber);
+            }
+            builder.add(definition);
+        }
+        builder.build()
+    }
+
+    /// A syntax set with all syntaxes but none of their contexts, to look syntaxes up by name,
+    /// file name or first line. It can't highlight anything.
+    pub fn index(&self) -> SyntaxSet {
+        let mut builder = SyntaxSetBuilder::new();
+        for syntax in &self.syntaxes {
+            let mut contexts = HashMap::new();
+            contexts.insert("main".to_string(), Context::new(false));
+            builder.add(SyntaxDefinition {
+                name: syntax.0.clone(),
+                file_extensions: syntax.1.clone(),
+                scope: syntax.2,
+                first_line_match: syntax.3.clone(),
+                hidden: syntax.4,
+                variables: HashMap::new(),
+                contexts,
+            });
+        }
+        builder.build()
+    }
+}
+
+/// The syntax which each context belongs to, by the index of the context.
+fn context_owners(syntaxes: &[RawSyntax], count: usize) -> Vec<usize> {
+    let mut owners = vec![0; count];
+    for (index, syntax) in syntaxes.iter().enumerate() {
+        for id in syntax.6.values() {
+            owners[context_index(*id)] = index;
+        }
+    }
+    owners
+}
+
+/// Calls `visit` with the ids of the contexts which `context` refers to: its prototype and the
+/// contexts which its patterns include, push or set.
+fn visit_ids<F: FnMut(&mut ContextId)>(context: &mut Context, mut visit: F) {
+    if let Some(ref mut prototype) = context.prototype {
+        visit(prototype);
+    }
+    for pattern in &mut context.patterns {
+        let mut references = Vec::new();
+        match pattern {
+            Pattern::Include(reference) => references.push(reference),
+            Pattern::Match(pattern) => {
+                if let MatchOperation::Push(ref mut targets)
+                | MatchOperation::Set(ref mut targets) = pattern.operation
+                {
+                    references.extend(targets.iter_mut());
+                }
+                references.extend(pattern.with_prototype.as_mut());
+            }
+        }
+        for reference in references {
+            if let ContextReference::Direct(ref mut id) = reference {
+                visit(id);
+            }
+        }
+    }
+}
+
+/// The index of a context in the syntax set. `ContextId` keeps it private, but shows it in its
+/// `Debug` output: `ContextId { index: 5 }`.
+fn context_index(id: ContextId) -> usize {
+    let debug = format!("{:?}", id);
+    let digits: String = debug.chars().filter(char::is_ascii_digit).collect();
+    digits
+        .parse()
+        .expect("the Debug output of ContextId shows its index")
+}
+
+#[test]
+fn context_indices() {
+    assert_eq!(0, context_index(ContextId::new(0)));
+    assert_eq!(4711, context_index(ContextId::new(4711)));
+}
@@ -0,0 +1,77 @@
+/// Edit distance between `a` and `b`: the number of inserted, removed or replaced characters.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+
+    row[b.len()]
+}
+
+/// A hint with the candidates closest to the unknown `name` (ignoring case), e.g.
+/// `, did you mean 'Monokai Extended'?`, or nothing if none is close. Only the candidates with
+/// the smallest distance are given, at most three.
+pub fn did_you_mean<'a, I: IntoIterator<Item = &'a str>>(name: &str, candidates: I) -> String {
+    let name = name.to_lowercase();
+    let limit = (name.chars().count() / 3).max(2);
+
+    let mut matches: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .map(|candidate| (levenshtein(&name, &candidate.to_lowercase()), candidate))
+        .filter(|&(distance, _)| distance <= limit)
+        .collect();
+    matches.sort();
+    matches.dedup_by(|a, b| a.1 == b.1);
+    if let Some(&(best, _)) = matches.first() {
+        matches.retain(|&(distance, _)| distance == best);
+    }
+
+    let names: Vec<String> = matches
+        .iter()
+        .take(3)
+        .map(|(_, candidate)| format!("'{}'", candidate))
+        .collect();
+    match names.len() {
+        0 => String::new(),
+        1 => format!(", did you mean {}?", names[0]),
+        _ => format!(", did you mean one of {}?", names.join(", ")),
+    }
+}
+
+#[test]
+fn distances() {
+    assert_eq!(0, levenshtein("rust", "rust"));
+    assert_eq!(3, levenshtein("kitten", "sitting"));
+    assert_eq!(4, levenshtein("", "ruby"));
+}
+
+#[test]
+fn suggestions() {
+    let themes = [
+        "Monokai Extended",
+        "Monokai Extended Light",
+        "GitHub",
+        "Nord",
+    ];
+    assert_eq!(
+        ", did you mean 'Monokai Extended'?",
+        did_you_mean("monokai extnded", themes.iter().copied())
+    );
+    assert_eq!(
+        ", did you mean one of 'Lord', 'Nord'?",
+        did_you_mean("mord", ["GitHub", "Lord", "Nord", "Nordic"].iter().copied())
+    );
+    assert_eq!("", did_you_mean("solarized", themes.iter().copied()));
+}
@@ -0,0 +1,173 @@
+use std::io::Write;
+
+use syntect::highlighting::{FontStyle, Style, Theme};
+
+use crate::errors::*;
+use crate::html::{css_color, escape};
+
+const FONT_SIZE: usize = 14;
+/// Width of a column of the monospace font, 0.6em
+const COLUMN_WIDTH: f64 = 8.4;
+const LINE_HEIGHT: usize = 20;
+const PADDING: usize = 10;
+const DEFAULT_GRID_COLOR: &str = "#444444";
+
+/// A row of the image: the line number and the `<tspan>` elements of the highlighted text.
+pub struct Row {
+    pub number: Option<String>,
+    pub spans: String,
+    /// Width of the text in columns
+    pub width: usize,
+}
+
+/// A `<tspan>` with the colors and font style of a highlighted region.
+pub fn tspan(style: Style, text: &str) -> String {
+    let mut attributes = String::new();
+    if let Some(color) = css_color(style.foreground) {
+        attributes.push_str(&format!(" fill=\"{}\"", color));
+    }
+    if style.font_style.contains(FontStyle::BOLD) {
+        attributes.push_str(" font-weight=\"bold\"");
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        attributes.push_str(" font-style=\"italic\"");
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        attributes.push_str(" text-decoration=\"underline\"");
+    }
+    format!("<tspan{}>{}</tspan>", attributes, escape(text))
+}
+
+fn x(columns: usize) -> String {
+    format!("{:.1}", PADDING as f64 + columns as f64 * COLUMN_WIDTH)
+}
+
+/// Writes the image: the background of `theme`, the `header`, the rows with their line numbers
+/// in a gutter and, for the `grid`, lines below the header and next to the gutter.
+pub fn write_document(
+    handle: &mut dyn Write,
+    theme: &Theme,
+    header: Option<&str>,
+    rows: &[Row],
+    grid: bool,
+) -> Result<()> {
+    let settings = &theme.settings;
+    let gutter = rows
+        .iter()
+        .filter_map(|row| row.number.as_ref())
+        .map(|number| number.chars().count())
+        .max()
+        .map_or(0, |digits| digits + 2);
+    let columns = rows
+        .iter()
+        .map(|row| gutter + row.width)
+        .chain(header.map(|header| header.chars().count()))
+        .max()
+        .unwrap_or(0);
+    let header_rows = if header.is_some() { 1 } else { 0 };
+
+    let width = 2.0 * PADDING as f64 + columns as f64 * COLUMN_WIDTH;
+    let height = 2 * PADDING + (header_rows + rows.len()) * LINE_HEIGHT;
+    let background = settings
+        .background
+        .and_then(css_color)
+        .unwrap_or_else(|| "#000000".to_string());
+    let foreground = settings
+        .foreground
+        .and_then(css_color)
+        .unwrap_or_else(|| "#ffffff".to_string());
+    let grid_color = settings
+        .gutter_foreground
+        .and_then(css_color)
+        .unwrap_or_else(|| DEFAULT_GRID_COLOR.to_string());
+
+    writeln!(
+        handle,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.1}\" height=\"{}\" \
+         font-family=\"monospace\" font-size=\"{}\" fill=\"{}\">",
+        width, height, FONT_SIZE, foreground
+    )?;
+    writeln!(
+        handle,
+        "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+        background
+    )?;
+
+    // Text sits on its baseline, a bit above the bottom of its row.
+    let baseline = |row: usize| PADDING + (row + 1) * LINE_HEIGHT - LINE_HEIGHT / 4;
+
+    if let Some(header) = header {
+        writeln!(
+            handle,
+            "<text x=\"{}\" y=\"{}\" font-weight=\"bold\" xml:space=\"preserve\">{}</text>",
+            x(0),
+            baseline(0),
+            escape(header)
+        )?;
+        if grid {
+            let y = PADDING + LINE_HEIGHT;
+            writeln!(
+                handle,
+                "<line x1=\"0\" y1=\"{y}\" x2=\"{:.1}\" y2=\"{y}\" stroke=\"{}\"/>",
+                width,
+                grid_color,
+                y = y
+            )?;
+        }
+    }
+    if grid && gutter > 0 {
+        let x = x(gutter - 1);
+        writeln!(
+            handle,
+            "<line x1=\"{x}\" y1=\"{}\" x2=\"{x}\" y2=\"{}\" stroke=\"{}\"/>",
+            PADDING + header_rows * LINE_HEIGHT,
+            height,
+            grid_color,
+            x = x
+        )?;
+    }
+
+    for (index, row) in rows.iter().enumerate() {
+        let y = baseline(header_rows + index);
+        if let Some(ref number) = row.number {
+            writeln!(
+                handle,
+                "<text x=\"{}\" y=\"{}\" text-anchor=\"end\" fill=\"{}\">{}</text>",
+                x(gutter - 2),
+                y,
+                grid_color,
+                escape(number)
+            )?;
+        }
+        writeln!(
+            handle,
+            "<text x=\"{}\" y=\"{}\" xml:space=\"preserve\">{}</text>",
+            x(gutter),
+            y,
+            row.spans
+        )?;
+    }
+
+    writeln!(handle, "</svg>")?;
+    Ok(())
+}
+
+#[test]
+fn tspans() {
+    use syntect::highlighting::Color;
+
+    let style = Style {
+        foreground: Color {
+            r: 0xa6,
+            g: 0xe2,
+            b: 0x2e,
+            a: 0xff,
+        },
+        font_style: FontStyle::BOLD,
+        ..Style::default()
+    };
+    assert_eq!(
+        "<tspan fill=\"#a6e22e\" font-weight=\"bold\">a &lt; b</tspan>",
+        tspan(style, "a < b")
+    );
+}
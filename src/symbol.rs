@@ -0,0 +1,165 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use regex::{self, Regex};
+
+use crate::line_range::LineRange;
+
+/// Finds the definition matching `query` (e.g. `fn parse_config` or `class Parser`) and returns
+/// the lines of its block, `context` lines around it included. Line numbers start at 1.
+pub fn find_symbol(lines: &[Vec<u8>], query: &str, context: usize) -> Option<LineRange> {
+    let lines: Vec<String> = lines
+        .iter()
+        .map(|line| String::from_utf8_lossy(line).into_owned())
+        .collect();
+    let regex = query_regex(query)?;
+
+    let (start, column) = lines
+        .iter()
+        .enumerate()
+        .find_map(|(index, line)| regex.find(line).map(|m| (index, m.start())))?;
+    let end = block_end(&lines, start, column);
+
+    Some(LineRange {
+        lower: (start + 1).saturating_sub(context).max(1),
+        upper: (end + 1).saturating_add(context).min(lines.len()),
+    })
+}
+
+/// Matches the words of the query as whole words, separated by any whitespace.
+fn query_regex(query: &str) -> Option<Regex> {
+    let words: Vec<String> = query.split_whitespace().map(regex::escape).collect();
+    if words.is_empty() {
+        return None;
+    }
+    Regex::new(&format!(r"(^|\W){}($|\W)", words.join(r"\s+"))).ok()
+}
+
+/// The index of the last line of the block which starts at `column` of the line at `start`.
+/// Blocks are delimited by braces or, after a trailing `:`, by indentation. Brackets inside of
+/// the signature, strings, chars and comments don't count.
+fn block_end(lines: &[String], start: usize, column: usize) -> usize {
+    let mut depth: usize = 0;
+    let (mut in_string, mut in_comment) = (false, false);
+    for (index, line) in lines.iter().enumerate().skip(start) {
+        let code = if index == start {
+            &line[column..]
+        } else {
+            line
+        };
+        let mut chars = code.chars().peekable();
+        while let Some(c) = chars.next() {
+            if in_comment {
+                if c == '*' && chars.next_if_eq(&'/').is_some() {
+                    in_comment = false;
+                }
+                continue;
+            }
+            if in_string {
+                match c {
+                    '\\' => {
+                        chars.next();
+                    }
+                    '"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match c {
+                '"' => in_string = true,
+                '\'' => skip_char(&mut chars),
+                '/' if chars.next_if_eq(&'/').is_some() => break,
+                '/' if chars.next_if_eq(&'*').is_some() => in_comment = true,
+                '{' | '(' | '[' => depth += 1,
+                '}' if depth == 1 => return index,
+                '}' | ')' | ']' => depth = depth.saturating_sub(1),
+                ';' if depth == 0 => return index,
+                _ => {}
+            }
+        }
+
+        if depth == 0 && !in_string && !in_comment && code.trim_end().ends_with(':') {
+            return indented_block_end(lines, start);
+        }
+    }
+
+    start
+}
+
+/// Skips the rest of a char like `'}'` or `'\n'` after its opening quote, but not the quote of
+/// a lifetime like `'a`.
+fn skip_char(chars: &mut Peekable<Chars>) {
+    let mut ahead = chars.clone();
+    let closed = match ahead.next() {
+        Some('\\') => ahead.by_ref().take(10).any(|c| c == '\''),
+        Some(_) => ahead.next() == Some('\''),
+        None => false,
+    };
+    if closed {
+        *chars = ahead;
+    }
+}
+
+fn indented_block_end(lines: &[String], start: usize) -> usize {
+    let indentation = |line: &str| line.len() - line.trim_start().len();
+    let outer = indentation(&lines[start]);
+
+    lines
+        .iter()
+        .enumerate()
+        .skip(start + 1)
+        .take_while(|(_, line)| line.trim().is_empty() || indentation(line) > outer)
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, _)| index)
+        .last()
+        .unwrap_or(start)
+}
+
+#[cfg(test)]
+fn lines(text: &str) -> Vec<Vec<u8>> {
+    text.split_inclusive('\n')
+        .map(|line| line.as_bytes().to_vec())
+        .collect()
+}
+
+#[test]
+fn braced_blocks() {
+    let lines =
+        lines("use std::io;\n\nfn parse_config() {\n    if true {\n    }\n}\n\nfn parse() {}\n");
+
+    let range = find_symbol(&lines, "fn  parse_config", 0).unwrap();
+    assert_eq!((3, 6), (range.lower, range.upper));
+
+    let range = find_symbol(&lines, "fn parse", 1).unwrap();
+    assert_eq!((7, 8), (range.lower, range.upper));
+
+    assert!(find_symbol(&lines, "fn parse_conf", 0).is_none());
+}
+
+#[test]
+fn brackets_strings_and_comments() {
+    let lines = lines(concat!(
+        "fn fill<'a>(buf: [u8; 4], name: &'a str) {\n",
+        "    let s = \"}; \\\" }\";\n",
+        "    let c = '}';\n",
+        "    // }\n",
+        "    /* }\n",
+        "    } */\n",
+        "}\n",
+        "const N: usize = 4;\n",
+    ));
+
+    let range = find_symbol(&lines, "fn fill", 0).unwrap();
+    assert_eq!((1, 7), (range.lower, range.upper));
+}
+
+#[test]
+fn indented_blocks() {
+    let lines = lines("class Parser:\n    def parse(self):\n\n        pass\n\nx = 1\n");
+
+    let range = find_symbol(&lines, "def parse", 0).unwrap();
+    assert_eq!((2, 4), (range.lower, range.upper));
+
+    let range = find_symbol(&lines, "class Parser", 0).unwrap();
+    assert_eq!((1, 4), (range.lower, range.upper));
+}
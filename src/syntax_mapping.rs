@@ -1,30 +1,74 @@
-use std::borrow::Cow;
 use std::collections::HashMap;
+use std::path::Path;
 
-#[derive(Debug, Clone)]
-pub struct SyntaxMapping(HashMap<String, String>);
+use glob::Pattern;
 
-impl Default for SyntaxMapping {
-    fn default() -> Self {
-        SyntaxMapping(HashMap::new())
-    }
+use crate::errors::*;
+
+/// Maps file names, extensions and glob patterns of file names to languages.
+#[derive(Debug, Clone, Default)]
+pub struct SyntaxMapping {
+    names: HashMap<String, String>,
+    patterns: Vec<(Pattern, String)>,
 }
 
 impl SyntaxMapping {
     pub fn new() -> SyntaxMapping {
-        SyntaxMapping(HashMap::new())
+        SyntaxMapping::default()
     }
 
+    /// Maps a file name or extension.
     pub fn insert(&mut self, from: String, to: String) -> Option<String> {
-        self.0.insert(from, to)
+        self.names.insert(from, to)
     }
 
-    pub fn replace<'a>(&self, input: &'a str) -> Cow<'a, str> {
-        let mut out = Cow::from(input);
-        if let Some(value) = self.0.get(input) {
-            out = Cow::from(value.clone())
+    /// Maps a file name, an extension or a glob pattern like `*.conf`. Patterns containing a `/`
+    /// are matched against the whole path, all others against the file name.
+    pub fn insert_pattern(&mut self, from: &str, to: String) -> Result<()> {
+        if !from.contains(['*', '?', '[']) {
+            self.insert(from.to_string(), to);
+            return Ok(());
         }
-        out
+
+        let pattern = Pattern::new(from)
+            .map_err(|error| format!("Invalid syntax mapping pattern '{}': {}", from, error))?;
+        // Later mappings win.
+        self.patterns.insert(0, (pattern, to));
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.names
+            .iter()
+            .map(|(from, to)| (from.as_str(), to.as_str()))
+            .chain(
+                self.patterns
+                    .iter()
+                    .map(|(from, to)| (from.as_str(), to.as_str())),
+            )
+    }
+
+    /// The language for `path` by file name, then by pattern and then by extension.
+    pub fn get(&self, path: &str) -> Option<&str> {
+        let file_name = Path::new(path).file_name().and_then(|n| n.to_str())?;
+        let extension = Path::new(path).extension().and_then(|x| x.to_str());
+
+        self.names
+            .get(file_name)
+            .or_else(|| {
+                self.patterns
+                    .iter()
+                    .find(|(pattern, _)| {
+                        if pattern.as_str().contains('/') {
+                            pattern.matches(path)
+                        } else {
+                            pattern.matches(file_name)
+                        }
+                    })
+                    .map(|(_, to)| to)
+            })
+            .or_else(|| extension.and_then(|extension| self.names.get(extension)))
+            .map(String::as_str)
     }
 }
 
@@ -33,9 +77,30 @@ fn basic() {
     let mut map = SyntaxMapping::new();
     map.insert("Cargo.lock".into(), "toml".into());
     map.insert(".ignore".into(), ".gitignore".into());
+    map.insert("conf".into(), "ini".into());
+
+    assert_eq!(Some("toml"), map.get("Cargo.lock"));
+    assert_eq!(None, map.get("other.lock"));
+    assert_eq!(Some("ini"), map.get("/etc/app.conf"));
+
+    assert_eq!(Some(".gitignore"), map.get(".ignore"));
+}
+
+#[test]
+fn patterns() {
+    let mut map = SyntaxMapping::new();
+    map.insert_pattern("*.conf", "INI".into()).unwrap();
+    map.insert_pattern("Dockerfile.*", "Dockerfile".into())
+        .unwrap();
+    map.insert_pattern("**/nginx/*.conf", "nginx".into())
+        .unwrap();
+    map.insert_pattern("Makefile", "make".into()).unwrap();
 
-    assert_eq!("toml", map.replace("Cargo.lock"));
-    assert_eq!("other.lock", map.replace("other.lock"));
+    assert_eq!(Some("INI"), map.get("app.conf"));
+    assert_eq!(Some("nginx"), map.get("/etc/nginx/app.conf"));
+    assert_eq!(Some("Dockerfile"), map.get("docker/Dockerfile.dev"));
+    assert_eq!(Some("make"), map.get("Makefile"));
+    assert_eq!(None, map.get("Dockerfile"));
 
-    assert_eq!(".gitignore", map.replace(".ignore"));
+    assert!(map.insert_pattern("[", "INI".into()).is_err());
 }
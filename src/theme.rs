@@ -0,0 +1,140 @@
+use std::str::FromStr;
+
+use syntect::highlighting::{
+    Color, FontStyle, ScopeSelectors, StyleModifier, Theme, ThemeItem, ThemeSettings,
+};
+
+use crate::errors::*;
+
+/// Defines a theme from a small palette in code, instead of writing a `.tmTheme` file.
+///
+/// Colors are given as `#RRGGBB` (or `#RRGGBBAA`), scopes as selectors like in a `.tmTheme`
+/// (e.g. `"keyword, storage"`) and font styles as space separated list of `bold`, `italic` and
+/// `underline`.
+#[derive(Debug, Clone)]
+pub struct ThemeBuilder {
+    name: String,
+    background: Option<String>,
+    foreground: Option<String>,
+    gutter: Option<String>,
+    scopes: Vec<(String, Option<String>, Option<String>)>,
+}
+
+impl ThemeBuilder {
+    pub fn new<T: Into<String>>(name: T) -> Self {
+        ThemeBuilder {
+            name: name.into(),
+            background: None,
+            foreground: None,
+            gutter: None,
+            scopes: Vec::new(),
+        }
+    }
+
+    pub fn background<T: Into<String>>(mut self, color: T) -> Self {
+        self.background = Some(color.into());
+        self
+    }
+
+    pub fn foreground<T: Into<String>>(mut self, color: T) -> Self {
+        self.foreground = Some(color.into());
+        self
+    }
+
+    /// Color of line numbers and the grid.
+    pub fn gutter<T: Into<String>>(mut self, color: T) -> Self {
+        self.gutter = Some(color.into());
+        self
+    }
+
+    /// Colors the text matching `selector`.
+    pub fn scope<T: Into<String>>(mut self, selector: T, color: T) -> Self {
+        self.scopes
+            .push((selector.into(), Some(color.into()), None));
+        self
+    }
+
+    /// Colors the text matching `selector` and sets its font style (e.g. `"bold italic"`).
+    pub fn scope_with_font_style<T: Into<String>>(
+        mut self,
+        selector: T,
+        color: T,
+        font_style: T,
+    ) -> Self {
+        self.scopes
+            .push((selector.into(), Some(color.into()), Some(font_style.into())));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn build(&self) -> Result<Theme> {
+        let scopes = self
+            .scopes
+            .iter()
+            .map(|(selector, color, font_style)| {
+                let scope = ScopeSelectors::from_str(selector)
+                    .map_err(|_| format!("Invalid scope selector '{}'", selector))?;
+                let font_style = match font_style {
+                    Some(font_style) => Some(
+                        FontStyle::from_str(font_style)
+                            .map_err(|_| format!("Invalid font style '{}'", font_style))?,
+                    ),
+                    None => None,
+                };
+
+                Ok(ThemeItem {
+                    scope,
+                    style: StyleModifier {
+                        foreground: parse_color(color)?,
+                        background: None,
+                        font_style,
+                    },
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Theme {
+            name: Some(self.name.clone()),
+            author: None,
+            settings: ThemeSettings {
+                background: parse_color(&self.background)?,
+                foreground: parse_color(&self.foreground)?,
+                gutter_foreground: parse_color(&self.gutter)?,
+                ..ThemeSettings::default()
+            },
+            scopes,
+        })
+    }
+}
+
+fn parse_color(color: &Option<String>) -> Result<Option<Color>> {
+    match color {
+        Some(color) => Color::from_str(color)
+            .map(Some)
+            .map_err(|_| format!("Invalid color '{}'", color).into()),
+        None => Ok(None),
+    }
+}
+
+#[test]
+fn build() {
+    let theme = ThemeBuilder::new("Corporate")
+        .background("#FFFFFF")
+        .foreground("#1A1A1A")
+        .scope("comment", "#808080")
+        .scope_with_font_style("keyword, storage", "#0050A0", "bold")
+        .build()
+        .unwrap();
+
+    assert_eq!(Some("Corporate".to_string()), theme.name);
+    assert_eq!(2, theme.scopes.len());
+    assert_eq!(Some(FontStyle::BOLD), theme.scopes[1].style.font_style);
+
+    assert!(ThemeBuilder::new("Broken")
+        .background("white")
+        .build()
+        .is_err());
+}
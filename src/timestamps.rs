@@ -0,0 +1,114 @@
+use std::time::UNIX_EPOCH;
+
+use regex::Regex;
+
+use crate::errors::*;
+
+/// Finds the timestamps of log lines and keeps track of the time elapsed between them.
+pub struct Timestamps {
+    regex: Regex,
+    previous: Option<f64>,
+    /// Seconds since the previous timestamp, for the current line
+    pub delta: Option<f64>,
+}
+
+impl Timestamps {
+    /// Uses the first capture group of `pattern` as timestamp, or the whole match if there is no
+    /// group.
+    pub fn new(pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern)
+            .map_err(|error| format!("Invalid timestamp pattern '{}': {}", pattern, error))?;
+
+        Ok(Timestamps {
+            regex,
+            previous: None,
+            delta: None,
+        })
+    }
+
+    /// Updates `delta` for the next line. Lines without a timestamp have no delta.
+    pub fn update(&mut self, line: &str) {
+        let timestamp = self.regex.captures(line).and_then(|captures| {
+            let text = captures.get(1).or_else(|| captures.get(0))?.as_str();
+            parse_timestamp(text)
+        });
+
+        self.delta = match (timestamp, self.previous) {
+            (Some(timestamp), Some(previous)) => Some(timestamp - previous),
+            _ => None,
+        };
+        if timestamp.is_some() {
+            self.previous = timestamp;
+        }
+    }
+}
+
+/// Parses a timestamp into seconds. Supported are RFC 3339 like dates (`2019-01-01 10:00:00.5Z`),
+/// times of day (`10:00:00.5`) and Unix timestamps (`1546336800.5`).
+fn parse_timestamp(text: &str) -> Option<f64> {
+    if let Ok(time) = humantime::parse_rfc3339_weak(text.trim_end_matches('Z')) {
+        return time
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|duration| duration.as_secs_f64());
+    }
+
+    let parts: Vec<&str> = text.split(':').collect();
+    if let [hours, minutes, seconds] = parts[..] {
+        let hours: u64 = hours.parse().ok()?;
+        let minutes: u64 = minutes.parse().ok()?;
+        let seconds: f64 = seconds.replace(',', ".").parse().ok()?;
+        let whole = hours
+            .checked_mul(3600)?
+            .checked_add(minutes.checked_mul(60)?)?;
+        return Some(whole as f64 + seconds);
+    }
+
+    text.parse().ok()
+}
+
+/// Formats a delta to fit into 8 columns, e.g. `+0.250s`, `+12m05s` or `+3h20m`.
+pub fn format_delta(delta: f64) -> String {
+    let sign = if delta < 0.0 { "-" } else { "+" };
+    let seconds = delta.abs();
+
+    if seconds < 60.0 {
+        format!("{}{:.3}s", sign, seconds)
+    } else if seconds < 3600.0 {
+        let seconds = seconds as u64;
+        format!("{}{}m{:02}s", sign, seconds / 60, seconds % 60)
+    } else {
+        let minutes = seconds as u64 / 60;
+        format!("{}{}h{:02}m", sign, minutes / 60, minutes % 60)
+    }
+}
+
+#[test]
+fn parse_formats() {
+    assert_eq!(Some(36000.5), parse_timestamp("10:00:00.5"));
+    assert_eq!(Some(1546336800.0), parse_timestamp("2019-01-01T10:00:00Z"));
+    assert_eq!(
+        Some(1546336800.25),
+        parse_timestamp("2019-01-01 10:00:00.25")
+    );
+    assert_eq!(Some(1546336800.0), parse_timestamp("1546336800"));
+    assert_eq!(None, parse_timestamp("yesterday"));
+    assert_eq!(Some(359999996400.0), parse_timestamp("99999999:00:00"));
+    assert_eq!(None, parse_timestamp("18446744073709551615:00:00"));
+}
+
+#[test]
+fn deltas() {
+    let mut timestamps = Timestamps::new(r"^\[([^\]]+)\]").unwrap();
+
+    timestamps.update("[10:00:00.000] start");
+    assert_eq!(None, timestamps.delta);
+    timestamps.update("no timestamp");
+    assert_eq!(None, timestamps.delta);
+    timestamps.update("[10:00:01.500] done");
+    assert_eq!(Some(1.5), timestamps.delta);
+
+    assert_eq!("+1.500s", format_delta(1.5));
+    assert_eq!("+12m05s", format_delta(725.0));
+    assert_eq!("+3h20m", format_delta(12000.0));
+}
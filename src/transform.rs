@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use content_inspector::ContentType;
+
+/// A transformation of the lines of the input, applied before printing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transform {
+    /// Sort the lines
+    Sort,
+    /// Remove duplicate lines, keeping the first occurrence
+    Uniq,
+    /// Shuffle the lines and keep the first N
+    Sample(usize),
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Transform::Sort => write!(f, "sorted"),
+            Transform::Uniq => write!(f, "unique"),
+            Transform::Sample(n) => write!(f, "sample of {}", n),
+        }
+    }
+}
+
+/// How to pick the lines printed from a huge file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sampling {
+    /// Every Nth line, starting with the first
+    EveryNth(usize),
+    /// N random lines, in the order of the file
+    Random(usize),
+}
+
+/// Transforms only work on lines of UTF-8 text.
+pub fn applies_to(content_type: ContentType) -> bool {
+    content_type == ContentType::UTF_8 || content_type == ContentType::UTF_8_BOM
+}
+
+/// Applies `transforms` in order. Every line has to end with a line break.
+pub fn apply(transforms: &[Transform], mut lines: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+    for transform in transforms {
+        match *transform {
+            Transform::Sort => lines.sort(),
+            Transform::Uniq => {
+                let mut seen = HashSet::new();
+                lines.retain(|line| seen.insert(line.clone()));
+            }
+            Transform::Sample(n) => {
+                Random::from_time().shuffle(&mut lines);
+                lines.truncate(n);
+            }
+        }
+    }
+
+    lines
+}
+
+/// What a `.line_filter()` does with a line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineAction {
+    /// Print the line as it is
+    Keep,
+    /// Don't print the line. The following lines keep their numbers.
+    Skip,
+    /// Print this text instead of the line
+    Replace(String),
+    /// Mask the byte ranges of the line with `*`, e.g. the values of secrets
+    Redact(Vec<Range<usize>>),
+}
+
+/// A callback which gets the number (as shown) and the text of each line, without the line
+/// break, and decides what to print of it.
+#[derive(Clone)]
+pub struct LineFilter(pub Arc<LineFilterFn>);
+
+pub type LineFilterFn = dyn Fn(usize, &str) -> LineAction + Send + Sync;
+
+impl fmt::Debug for LineFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("LineFilter")
+    }
+}
+
+impl LineFilter {
+    /// The line to print instead of `line`, with its line break. `None` if it is skipped.
+    pub fn apply(&self, line_number: usize, line: &[u8]) -> Option<Vec<u8>> {
+        let text = String::from_utf8_lossy(line);
+        let content = text.trim_end_matches(['\r', '\n']);
+        let line_break = &text[content.len()..];
+        let replaced = match (self.0)(line_number, content) {
+            LineAction::Keep => return Some(line.to_vec()),
+            LineAction::Skip => return None,
+            LineAction::Replace(text) => text,
+            LineAction::Redact(ranges) => redact(content, &ranges),
+        };
+        Some((replaced + line_break).into_bytes())
+    }
+}
+
+/// Replaces each character in the byte `ranges` of `text` with `*`.
+fn redact(text: &str, ranges: &[Range<usize>]) -> String {
+    text.char_indices()
+        .map(|(index, c)| {
+            if ranges.iter().any(|range| range.contains(&index)) {
+                '*'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// A small xorshift generator. Good enough to pick lines, not for anything else.
+pub struct Random(u64);
+
+impl Random {
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0);
+        Random::new(nanos)
+    }
+
+    pub fn new(seed: u64) -> Self {
+        // The state must never be zero.
+        Random(seed | 1)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number in `0..bound`.
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    /// Reservoir sampling: the slot of the item at `index` in a sample of `n` items, if the
+    /// item gets picked. Allows to sample items which are only seen once.
+    pub fn reservoir_slot(&mut self, index: usize, n: usize) -> Option<usize> {
+        if index < n {
+            Some(index)
+        } else {
+            Some(self.below(index + 1)).filter(|&slot| slot < n)
+        }
+    }
+
+    /// Fisher-Yates shuffle.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+#[test]
+fn transforms() {
+    let lines: Vec<Vec<u8>> = vec![b"b\n".to_vec(), b"a\n".to_vec(), b"b\n".to_vec()];
+
+    assert_eq!(
+        vec![b"a\n".to_vec(), b"b\n".to_vec()],
+        apply(&[Transform::Sort, Transform::Uniq], lines.clone())
+    );
+    assert_eq!(2, apply(&[Transform::Sample(2)], lines.clone()).len());
+    assert_eq!(3, apply(&[Transform::Sample(10)], lines).len());
+}
+
+#[test]
+fn shuffle() {
+    let mut items: Vec<usize> = (0..10).collect();
+    Random::new(42).shuffle(&mut items);
+    items.sort();
+    assert_eq!((0..10).collect::<Vec<_>>(), items);
+}
+
+#[test]
+fn reservoir() {
+    let mut random = Random::new(7);
+    let mut sample = Vec::new();
+    for item in 0..1000 {
+        match random.reservoir_slot(item, 10) {
+            Some(slot) if slot == sample.len() => sample.push(item),
+            Some(slot) => sample[slot] = item,
+            None => {}
+        }
+    }
+
+    sample.sort();
+    sample.dedup();
+    assert_eq!(10, sample.len());
+}
+
+#[test]
+fn filters_lines() {
+    let filter = LineFilter(Arc::new(|line_number: usize, line: &str| {
+        if line_number == 1 {
+            LineAction::Skip
+        } else if line.starts_with("token=") {
+            LineAction::Redact(vec![6..13, 18..20])
+        } else {
+            LineAction::Replace(line.to_uppercase())
+        }
+    }));
+    assert_eq!(None, filter.apply(1, b"# secrets\n"));
+    assert_eq!(
+        Some(b"token=******;key=**\r\n".to_vec()),
+        filter.apply(2, b"token=s\xc3\xa9cret;key=ab\r\n")
+    );
+    assert_eq!(Some(b"[AUTH]".to_vec()), filter.apply(3, b"[auth]"));
+}
@@ -0,0 +1,195 @@
+use std::ops::Range;
+
+use regex::Regex;
+
+use crate::errors::*;
+
+lazy_static! {
+    static ref URL: Regex =
+        Regex::new(r#"\b(?:https?|ftp|file)://[^\s<>"'`\x00-\x1f\x7f]+"#).unwrap();
+}
+
+/// The byte ranges of the URLs in `line`. Punctuation at the end, like the period ending a
+/// sentence or the parenthesis around a link, isn't part of them.
+pub fn url_ranges(line: &str) -> Vec<Range<usize>> {
+    URL.find_iter(line)
+        .map(|m| {
+            let mut url = m.as_str();
+            loop {
+                let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?']);
+                // Only strip closing brackets which weren't opened inside of the URL.
+                let trimmed = match trimmed.chars().next_back() {
+                    Some(close @ (')' | ']' | '}')) => {
+                        let open = match close {
+                            ')' => '(',
+                            ']' => '[',
+                            _ => '{',
+                        };
+                        if trimmed.matches(open).count() < trimmed.matches(close).count() {
+                            &trimmed[..trimmed.len() - 1]
+                        } else {
+                            trimmed
+                        }
+                    }
+                    _ => trimmed,
+                };
+                if trimmed.len() == url.len() {
+                    break;
+                }
+                url = trimmed;
+            }
+            m.start()..m.start() + url.len()
+        })
+        .collect()
+}
+
+/// Turns matches of a regex into links, e.g. `JIRA-\d+` into `https://jira.example.com/browse/$0`.
+#[derive(Debug)]
+pub struct LinkRule {
+    regex: Regex,
+    template: String,
+}
+
+impl LinkRule {
+    /// The `template` may refer to capture groups like `$1` or `${name}`, `$0` is the match.
+    pub fn new(pattern: &str, template: &str) -> Result<Self> {
+        let regex = Regex::new(pattern)
+            .map_err(|error| format!("Invalid link pattern '{}': {}", pattern, error))?;
+
+        Ok(LinkRule {
+            regex,
+            template: template.to_string(),
+        })
+    }
+}
+
+/// The links in `line`: its URLs linking to themselves, then the matches of `rules` which
+/// don't overlap with earlier links. Sorted by position.
+pub fn find_links(line: &str, rules: &[LinkRule]) -> Vec<(Range<usize>, String)> {
+    let mut links: Vec<(Range<usize>, String)> = url_ranges(line)
+        .into_iter()
+        .map(|range| (range.clone(), line[range].to_string()))
+        .collect();
+
+    for rule in rules {
+        for captures in rule.regex.captures_iter(line) {
+            let range = captures.get(0).unwrap().range();
+            let overlaps = links
+                .iter()
+                .any(|(link, _)| link.start < range.end && range.start < link.end);
+            if range.is_empty() || overlaps {
+                continue;
+            }
+
+            let mut target = String::new();
+            captures.expand(&rule.template, &mut target);
+            links.push((range, target));
+        }
+    }
+
+    links.sort_by_key(|(range, _)| range.start);
+    links
+}
+
+/// The target of the link which the region starting at `offset` belongs to.
+pub fn link_at(links: &[(Range<usize>, String)], offset: usize) -> Option<&str> {
+    links
+        .iter()
+        .find(|(range, _)| range.start <= offset && offset < range.end)
+        .map(|(_, target)| target.as_str())
+}
+
+/// Wraps `text` (which may contain escape sequences) in an OSC 8 hyperlink to `url`.
+pub fn hyperlink(url: Option<&str>, text: String) -> String {
+    match url {
+        Some(url) => format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text),
+        None => text,
+    }
+}
+
+/// The path part of a `file://` URL for the absolute `path`: `/` separated and with the
+/// characters which have a meaning in URLs percent-encoded.
+pub fn url_path(path: &str) -> String {
+    let path = path.trim_start_matches(r"\\?\").replace('\\', "/");
+    let mut encoded = String::with_capacity(path.len() + 1);
+    if !path.starts_with('/') {
+        encoded.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'.' | b'_' | b'~' | b':' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// The link of a line number, from a template like `vscode://file{path}:{line}`.
+pub fn line_link(template: &str, path: &str, line_number: usize) -> String {
+    template
+        .replace("{path}", &url_path(path))
+        .replace("{line}", &line_number.to_string())
+}
+
+#[test]
+fn urls() {
+    let line = "See https://example.com/a_(b). Or (ftp://x.org/y), not http:/z";
+    let urls: Vec<&str> = url_ranges(line)
+        .into_iter()
+        .map(|range| &line[range])
+        .collect();
+    assert_eq!(vec!["https://example.com/a_(b)", "ftp://x.org/y"], urls);
+}
+
+#[test]
+fn urls_end_at_control_characters() {
+    let line = "\x1b]8;;https://example.com/a\x1b\\a\x1b]8;;\x1b\\ and http://x.org/\x07b\x7f";
+    let urls: Vec<&str> = url_ranges(line)
+        .into_iter()
+        .map(|range| &line[range])
+        .collect();
+    assert_eq!(vec!["https://example.com/a", "http://x.org/"], urls);
+}
+
+#[test]
+fn hyperlinks() {
+    assert_eq!(
+        "\x1b]8;;https://a.org\x1b\\a\x1b]8;;\x1b\\",
+        hyperlink(Some("https://a.org"), "a".to_string())
+    );
+    assert_eq!("a", hyperlink(None, "a".to_string()));
+}
+
+#[test]
+fn link_rules() {
+    let rules = vec![
+        LinkRule::new(r"JIRA-(\d+)", "https://jira.example.com/browse/JIRA-$1").unwrap(),
+        LinkRule::new(r"#\d+", "https://example.com/$0").unwrap(),
+    ];
+    let line = "JIRA-12, see https://example.com/#1";
+
+    assert_eq!(
+        vec![
+            (0..7, "https://jira.example.com/browse/JIRA-12".to_string()),
+            (13..35, "https://example.com/#1".to_string()),
+        ],
+        find_links(line, &rules)
+    );
+    assert_eq!(
+        Some("https://example.com/#1"),
+        link_at(&find_links(line, &rules), 13)
+    );
+    assert!(LinkRule::new("(", "").is_err());
+}
+
+#[test]
+fn file_urls() {
+    assert_eq!("/home/a%20b/c%23.rs", url_path("/home/a b/c#.rs"));
+    assert_eq!("/C:/src/main.rs", url_path(r"\\?\C:\src\main.rs"));
+    assert_eq!(
+        "vscode://file/src/main.rs:42",
+        line_link("vscode://file{path}:{line}", "/src/main.rs", 42)
+    );
+}
@@ -0,0 +1,26 @@
+use std::fmt::Debug;
+
+use console::Term;
+
+/// The width when it can't be determined, like `console` assumes.
+pub const DEFAULT_WIDTH: usize = 79;
+
+/// Tells the width available for the output. It's asked at each print rather than once when the
+/// printer is built, e.g. for tmux panes which get resized or SSH sessions which learn their size
+/// late.
+pub trait WidthProvider: Debug + Send + Sync {
+    /// The width in columns, `None` if it isn't known (yet).
+    fn width(&self) -> Option<usize>;
+}
+
+/// The width of the terminal on stdout, the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConsoleWidth;
+
+impl WidthProvider for ConsoleWidth {
+    fn width(&self) -> Option<usize> {
+        Term::stdout()
+            .size_checked()
+            .map(|(_, columns)| columns as usize)
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use regex::Regex;
+
+/// Scopes of the identifiers in the index.
+pub const IDENTIFIER_SELECTOR: &str =
+    "entity.name, variable - variable.language, support.function, support.type, support.class";
+
+/// Scopes of the definitions in the table of contents.
+pub const DEFINITION_SELECTOR: &str = "entity.name.function, entity.name.class, \
+     entity.name.struct, entity.name.enum, entity.name.union, entity.name.trait, \
+     entity.name.interface, entity.name.type, entity.name.module, entity.name.namespace, \
+     entity.name.section";
+
+lazy_static! {
+    static ref IDENTIFIER: Regex = Regex::new(r"[\p{Alphabetic}_][\p{Alphabetic}\d_]*").unwrap();
+}
+
+/// The identifiers of a file and the lines they occur in, a small cross reference.
+#[derive(Debug, Default)]
+pub struct IdentifierIndex {
+    entries: BTreeMap<String, Vec<usize>>,
+}
+
+impl IdentifierIndex {
+    /// Adds the identifiers found at `spans` of the line with `line_number`.
+    pub fn add(&mut self, line: &str, spans: &[Range<usize>], line_number: usize) {
+        for span in spans {
+            for identifier in IDENTIFIER.find_iter(&line[span.clone()]) {
+                let lines = self
+                    .entries
+                    .entry(identifier.as_str().to_string())
+                    .or_default();
+                if lines.last() != Some(&line_number) {
+                    lines.push(line_number);
+                }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The identifiers, sorted, and their line numbers.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &[usize])> {
+        self.entries
+            .iter()
+            .map(|(identifier, lines)| (identifier.as_str(), lines.as_slice()))
+    }
+}
+
+/// The definitions of a file, in order, with the line they occur in.
+#[derive(Debug, Default)]
+pub struct TableOfContents {
+    pub entries: Vec<(String, usize)>,
+}
+
+impl TableOfContents {
+    /// Adds the definitions found at `spans` of the line with `line_number`.
+    pub fn add(&mut self, line: &str, spans: &[Range<usize>], line_number: usize) {
+        for span in spans {
+            let name = line[span.clone()].trim();
+            if !name.is_empty() {
+                self.entries.push((name.to_string(), line_number));
+            }
+        }
+    }
+}
+
+#[test]
+fn index() {
+    let mut index = IdentifierIndex::default();
+    index.add("fn fib(n: usize)", &[3..6, 7..8], 1);
+    index.add("fib(n - 1) + fib(n - 2)", &[0..3, 13..16], 2);
+
+    let entries: Vec<_> = index.entries().collect();
+    assert_eq!(vec![("fib", &[1, 2][..]), ("n", &[1][..])], entries);
+}
+
+#[test]
+fn table_of_contents() {
+    let mut contents = TableOfContents::default();
+    contents.add("struct Point {", &[6..7, 7..12], 1);
+    contents.add("fn main() {}", &[3..7, 7..7], 5);
+    contents.add("# Usage \n", &[1..2, 2..9], 9);
+
+    assert_eq!(
+        vec![
+            ("Point".to_string(), 1),
+            ("main".to_string(), 5),
+            ("Usage".to_string(), 9)
+        ],
+        contents.entries
+    );
+}